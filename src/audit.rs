@@ -0,0 +1,94 @@
+use anyhow::Result;
+use aws_smithy_runtime_api::box_error::BoxError;
+use aws_smithy_runtime_api::client::interceptors::context::BeforeSerializationInterceptorContextRef;
+use aws_smithy_runtime_api::client::interceptors::Intercept;
+use aws_smithy_runtime_api::client::orchestrator::Metadata;
+use aws_smithy_types::config_bag::ConfigBag;
+use aws_types::region::Region;
+use chrono::Utc;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// An append-only JSONL record of every AWS API operation invoked by a scan.
+#[derive(Debug)]
+pub struct AuditLog {
+    file: Mutex<File>,
+}
+
+#[derive(Serialize)]
+struct AuditEntry<'a> {
+    timestamp: String,
+    profile: &'a str,
+    region: &'a str,
+    service: &'a str,
+    operation: &'a str,
+}
+
+impl AuditLog {
+    /// Opens `path` for appending, creating it if needed.
+    pub fn open(path: &Path) -> Result<Arc<Self>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open audit log {:?}: {}", path, e))?;
+        Ok(Arc::new(Self { file: Mutex::new(file) }))
+    }
+
+    /// Creates an interceptor that records the operations of one SDK client.
+    pub fn interceptor(self: &Arc<Self>, profile: &str, region: Option<&Region>) -> AuditInterceptor {
+        AuditInterceptor {
+            log: Arc::clone(self),
+            profile: profile.to_string(),
+            region: region.map(|r| r.to_string()).unwrap_or_default(),
+        }
+    }
+
+    fn record(&self, entry: &AuditEntry) -> Result<()> {
+        let line = serde_json::to_string(entry)?;
+        let mut file = self.file.lock().map_err(|_| anyhow::anyhow!("Audit log lock poisoned"))?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct AuditInterceptor {
+    log: Arc<AuditLog>,
+    profile: String,
+    region: String,
+}
+
+impl Intercept for AuditInterceptor {
+    fn name(&self) -> &'static str {
+        "AuditInterceptor"
+    }
+
+    fn read_before_execution(
+        &self,
+        _context: &BeforeSerializationInterceptorContextRef<'_>,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let (service, operation) = cfg
+            .load::<Metadata>()
+            .map(|m| (m.service(), m.name()))
+            .unwrap_or(("unknown", "unknown"));
+
+        let entry = AuditEntry {
+            timestamp: Utc::now().to_rfc3339(),
+            profile: &self.profile,
+            region: &self.region,
+            service,
+            operation,
+        };
+        // A failed write must not fail the API call, but it shouldn't go unnoticed either.
+        if let Err(e) = self.log.record(&entry) {
+            warn!("could not write to audit log: {}", e);
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,92 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Name of the optional defaults file read by `load_file_config`.
+pub const CONFIG_FILE_NAME: &str = "aws-inventory.toml";
+
+/// Defaults for `inventory` read from `aws-inventory.toml`. Flags given on the
+/// command line take precedence over every field.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FileConfig {
+    pub profile: Vec<String>,
+    pub regions: Vec<String>,
+    pub services: Vec<String>,
+    pub output: Option<PathBuf>,
+    pub assume_role_arn: Option<String>,
+    pub assume_role_chain: Vec<String>,
+    pub external_id: Option<String>,
+}
+
+/// Reads `aws-inventory.toml` from the working directory or, failing that,
+/// from next to the executable. Neither existing is not an error: every
+/// default is then empty.
+pub fn load_file_config() -> Result<FileConfig> {
+    let next_to_exe = std::env::current_exe().ok().and_then(|exe| Some(exe.parent()?.join(CONFIG_FILE_NAME)));
+    let path = std::iter::once(PathBuf::from(CONFIG_FILE_NAME))
+        .chain(next_to_exe)
+        .find(|path| path.is_file());
+    match path {
+        Some(path) => parse_file_config(&path),
+        None => Ok(FileConfig::default()),
+    }
+}
+
+fn parse_file_config(path: &Path) -> Result<FileConfig> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read config file {:?}: {}", path, e))?;
+    let config: FileConfig = toml::from_str(&contents)
+        .map_err(|e| anyhow::anyhow!("Invalid config file {:?}: {}", path, e))?;
+    if config.assume_role_arn.is_some() && !config.assume_role_chain.is_empty() {
+        anyhow::bail!("Config file {:?} sets both assume-role-arn and assume-role-chain; use one", path);
+    }
+    Ok(config)
+}
+
 pub fn get_available_regions() -> Vec<&'static str> {
     vec!["us-east-1", "eu-central-1", "ap-southeast-1", "ap-southeast-2"]
 }
+
+/// Expands the `all` sentinel into every known region; other lists are returned as given.
+pub fn expand_regions(regions: &[String]) -> Vec<String> {
+    if regions.iter().any(|r| r == "all") {
+        get_available_regions().iter().map(|s| s.to_string()).collect()
+    } else {
+        regions.to_vec()
+    }
+}
+
+/// Every commercial AWS region code, used to catch typos in region arguments.
+const KNOWN_REGIONS: &[&str] = &[
+    "af-south-1", "ap-east-1", "ap-east-2", "ap-northeast-1", "ap-northeast-2", "ap-northeast-3",
+    "ap-south-1", "ap-south-2", "ap-southeast-1", "ap-southeast-2", "ap-southeast-3",
+    "ap-southeast-4", "ap-southeast-5", "ap-southeast-7", "ca-central-1", "ca-west-1",
+    "eu-central-1", "eu-central-2", "eu-north-1", "eu-south-1", "eu-south-2", "eu-west-1",
+    "eu-west-2", "eu-west-3", "il-central-1", "me-central-1", "me-south-1", "mx-central-1",
+    "sa-east-1", "us-east-1", "us-east-2", "us-west-1", "us-west-2",
+];
+
+/// Returns the entries of `regions` that aren't AWS region codes.
+pub fn unknown_regions(regions: &[String]) -> Vec<&str> {
+    regions
+        .iter()
+        .map(|r| r.as_str())
+        .filter(|r| !KNOWN_REGIONS.contains(r))
+        .collect()
+}
+
+/// Removes `excluded` from `regions`.
+pub fn exclude_regions(regions: Vec<String>, excluded: &[String]) -> Vec<String> {
+    regions.into_iter().filter(|r| !excluded.contains(r)).collect()
+}
+
+/// Loads a JSON file mapping profile names to the regions scanned for that profile,
+/// e.g. `{"dev": ["us-east-1"], "prod": ["eu-west-1", "eu-central-1"]}`.
+pub fn load_profile_region_matrix(path: &Path) -> Result<HashMap<String, Vec<String>>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read profile/region matrix {:?}: {}", path, e))?;
+    serde_json::from_str(&contents)
+        .map_err(|e| anyhow::anyhow!("Invalid profile/region matrix {:?}: {}", path, e))
+}
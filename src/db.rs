@@ -1,7 +1,29 @@
-use crate::inventory::CollectedResource;
+use crate::inventory::{CollectedResource, ScanContext};
 use anyhow::Result;
-use rusqlite::{params, Connection};
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, OptionalExtension};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::path::Path;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// How long a reader waits on a scan's write lock before failing.
+const READ_BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Opens an existing inventory read-only, for queries that may run while a
+/// scan (e.g. `inventory --watch`) is writing to it. WAL mode lets readers
+/// proceed alongside the writer; the busy timeout covers the brief moments,
+/// such as checkpoints, when SQLite still needs a lock.
+pub fn open_read_only(path: &Path) -> Result<Connection> {
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to open inventory {:?}: {}", path, e))?;
+    conn.busy_timeout(READ_BUSY_TIMEOUT)?;
+    Ok(conn)
+}
 
 pub fn init_db(path: &Path) -> Result<Connection> {
     let conn = Connection::open(path)?;
@@ -14,7 +36,8 @@ pub fn init_db(path: &Path) -> Result<Connection> {
         "
         CREATE TABLE IF NOT EXISTS resources (
             id INTEGER PRIMARY KEY,
-            arn TEXT NOT NULL UNIQUE,
+            uid TEXT NOT NULL UNIQUE, -- CollectedResource::id, an ARN or a service id
+            arn TEXT, -- The real ARN, when the resource has one
             region TEXT NOT NULL,
             resource_type TEXT NOT NULL,
             name TEXT,
@@ -37,27 +60,164 @@ pub fn init_db(path: &Path) -> Result<Connection> {
             PRIMARY KEY(resource_id, ip_address)
         );
 
+        -- Human annotations. Keyed by uid rather than resource id so they
+        -- survive the INSERT OR REPLACE performed on every rescan.
+        CREATE TABLE IF NOT EXISTS notes (
+            uid TEXT PRIMARY KEY,
+            note TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+
+        -- Full SDK responses from --store-raw, kept apart from resources so
+        -- normal queries and exports never read them.
+        CREATE TABLE IF NOT EXISTS raw_responses (
+            uid TEXT PRIMARY KEY,
+            response TEXT NOT NULL,
+            captured_at TEXT NOT NULL
+        );
+
+        -- Per-scan resource counts for `trends`, cheap enough to keep forever.
+        CREATE TABLE IF NOT EXISTS resource_counts_history (
+            recorded_at TEXT NOT NULL,
+            resource_type TEXT NOT NULL,
+            region TEXT NOT NULL,
+            account_id TEXT,
+            count INTEGER NOT NULL
+        );
+
         CREATE INDEX IF NOT EXISTS idx_ip_address ON ip_addresses(ip_address);
         CREATE INDEX IF NOT EXISTS idx_tags ON tags(key, value);
         ",
     )?;
 
+    // Before uid, the unique key was stored as `arn` even when it was not one.
+    // Renaming keeps the UNIQUE constraint; `arn` is then refilled with the
+    // keys that are real ARNs.
+    if !has_column(&conn, "resources", "uid")? {
+        conn.execute_batch(
+            "ALTER TABLE resources RENAME COLUMN arn TO uid;
+             ALTER TABLE resources ADD COLUMN arn TEXT;
+             UPDATE resources SET arn = uid WHERE uid LIKE 'arn:%';",
+        )?;
+    }
+    for table in ["notes", "raw_responses"] {
+        if !has_column(&conn, table, "uid")? {
+            conn.execute(&format!("ALTER TABLE {} RENAME COLUMN arn TO uid", table), [])?;
+        }
+    }
+
+    // Columns added after the initial schema; older databases are migrated in place.
+    ensure_column(&conn, "resources", "last_seen", "TEXT")?;
+    ensure_column(&conn, "ip_addresses", "eni_id", "TEXT")?;
+    ensure_column(&conn, "ip_addresses", "description", "TEXT")?;
+    ensure_column(&conn, "resources", "account_id", "TEXT")?;
+    ensure_column(&conn, "resources", "created_at", "TEXT")?;
+    ensure_column(&conn, "resources", "source", "TEXT")?;
+    ensure_column(&conn, "resources", "profile", "TEXT")?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_created_at ON resources(created_at)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_arn ON resources(arn)", [])?;
+
     Ok(conn)
 }
 
-pub fn save_resources(conn: &mut Connection, resources: &[CollectedResource]) -> Result<()> {
+/// Trades durability for speed while a scan is being written: with
+/// `enabled`, commits no longer wait for the disk and the rollback journal is
+/// kept in memory, so a crash or power loss mid-write can corrupt the file.
+/// Disabling restores the WAL journal and full syncing that `init_db` sets up.
+pub fn set_fast_import(conn: &Connection, enabled: bool) -> Result<()> {
+    if enabled {
+        conn.pragma_update(None, "journal_mode", "MEMORY")?;
+        conn.pragma_update(None, "synchronous", "OFF")?;
+    } else {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "FULL")?;
+    }
+    Ok(())
+}
+
+/// Adds `column` to `table` if a database created by an older version lacks it.
+fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    if !has_column(conn, table, column)? {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+    Ok(())
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+    Ok(exists)
+}
+
+/// The current time in the same format `save_resources` uses for `last_seen`,
+/// so the two can be compared as strings.
+pub fn now_timestamp() -> String {
+    chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// Saves `resources`, recording the account they were collected from (when
+/// known), the profile that collected them and the scan's source label from
+/// `ctx`. Resources with an empty id
+/// are skipped with a warning, since they would all collapse into one row.
+/// Tags with a blank key are dropped for the same reason; empty values are
+/// kept, and stored as `""` rather than treated as a missing tag.
+/// With `ctx.merge_details`, an existing row's `details` are kept and the
+/// incoming fields merged over them. Returns the number of resources written.
+pub fn save_resources(conn: &mut Connection, resources: &[CollectedResource], ctx: &ScanContext) -> Result<usize> {
     let tx = conn.transaction()?;
+    let mut saved = 0;
+    let mut skipped: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut blank_tag_keys: BTreeMap<&str, usize> = BTreeMap::new();
 
     for resource in resources {
+        if resource.id.is_empty() {
+            *skipped.entry(resource.resource_type.as_str()).or_default() += 1;
+            continue;
+        }
+
+        let details = if ctx.merge_details {
+            Cow::Owned(merged_details(&tx, &resource.id, &resource.details)?)
+        } else {
+            Cow::Borrowed(&resource.details)
+        };
+
         // Insert the main resource
         tx.execute(
-            "INSERT OR REPLACE INTO resources (arn, region, resource_type, name, details) VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![resource.arn, resource.region, resource.resource_type, resource.name, serde_json::to_value(&resource.details)?],
+            "INSERT OR REPLACE INTO resources (uid, arn, region, resource_type, name, details, last_seen, account_id, created_at, source, profile) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                resource.id,
+                resource.arn(),
+                resource.region,
+                resource.resource_type,
+                resource.name,
+                serde_json::to_value(&*details)?,
+                now_timestamp(),
+                ctx.account_id,
+                // Promoted from details so age queries can use an index.
+                details.get("created_at").and_then(|v| v.as_str()),
+                ctx.source_label,
+                // The default credential chain has no profile name to record.
+                Some(&ctx.profile).filter(|p| !p.is_empty()),
+            ],
         )?;
         let resource_id = tx.last_insert_rowid();
 
+        if let Some(raw) = &resource.raw_response {
+            tx.execute(
+                "INSERT OR REPLACE INTO raw_responses (uid, response, captured_at) VALUES (?1, ?2, ?3)",
+                params![resource.id, raw, now_timestamp()],
+            )?;
+        }
+
         // Insert tags
         for (key, value) in &resource.tags {
+            if key.trim().is_empty() {
+                *blank_tag_keys.entry(resource.resource_type.as_str()).or_default() += 1;
+                continue;
+            }
             tx.execute(
                 "INSERT OR REPLACE INTO tags (resource_id, key, value) VALUES (?1, ?2, ?3)",
                 params![resource_id, key, value],
@@ -66,42 +226,156 @@ pub fn save_resources(conn: &mut Connection, resources: &[CollectedResource]) ->
 
         // Insert IPs
         for ip in &resource.ips {
+            let detail = resource.ip_details.get(ip);
             tx.execute(
-                "INSERT OR REPLACE INTO ip_addresses (resource_id, ip_address, is_public) VALUES (?1, ?2, ?3)",
-                params![resource_id, ip.to_string(), is_public(ip)],
+                "INSERT OR REPLACE INTO ip_addresses (resource_id, ip_address, is_public, eni_id, description) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    resource_id,
+                    ip.to_string(),
+                    is_public(ip),
+                    detail.and_then(|d| d.eni_id.as_deref()),
+                    detail.and_then(|d| d.description.as_deref()),
+                ],
             )?;
         }
+        saved += 1;
     }
 
     tx.commit()?;
-    Ok(())
+
+    for (resource_type, count) in skipped {
+        warn!("skipped {} {} resource(s) with an empty id.", count, resource_type);
+    }
+    for (resource_type, count) in blank_tag_keys {
+        debug!("ignored {} tag(s) with a blank key on {} resources.", count, resource_type);
+    }
+    Ok(saved)
+}
+
+/// The stored `details` of `uid` with the fields of `incoming` written over
+/// them, or just `incoming` when there is no stored object to merge into.
+fn merged_details(conn: &Connection, uid: &str, incoming: &Value) -> Result<Value> {
+    let stored: Option<String> = conn
+        .query_row("SELECT details FROM resources WHERE uid = ?1", params![uid], |row| row.get(0))
+        .optional()?
+        .flatten();
+    let mut merged = match stored.and_then(|s| serde_json::from_str::<Value>(&s).ok()) {
+        Some(Value::Object(map)) => map,
+        _ => return Ok(incoming.clone()),
+    };
+    match incoming {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                merged.insert(key.clone(), value.clone());
+            }
+            Ok(Value::Object(merged))
+        }
+        _ => Ok(Value::Object(merged)),
+    }
+}
+
+/// Appends the current number of resources per (resource type, region,
+/// account) to `resource_counts_history`, stamped with the current time.
+pub fn record_resource_counts(conn: &Connection) -> Result<usize> {
+    let rows = conn.execute(
+        "INSERT INTO resource_counts_history (recorded_at, resource_type, region, account_id, count)
+         SELECT ?1, resource_type, region, account_id, COUNT(*)
+         FROM resources
+         GROUP BY resource_type, region, account_id",
+        params![now_timestamp()],
+    )?;
+    Ok(rows)
+}
+
+/// Sets the note attached to the resource with id or ARN `key`, or clears it
+/// when `note` is `None`. Returns whether the resource is currently present in
+/// the inventory; notes for unknown keys are stored under `key` as given.
+pub fn set_note(conn: &Connection, key: &str, note: Option<&str>) -> Result<bool> {
+    let uid: Option<String> = conn
+        .query_row("SELECT uid FROM resources WHERE uid = ?1 OR arn = ?1", params![key], |row| row.get(0))
+        .optional()?;
+    let known = uid.is_some();
+    let uid = uid.as_deref().unwrap_or(key);
+
+    match note {
+        Some(note) => conn.execute(
+            "INSERT OR REPLACE INTO notes (uid, note, updated_at) VALUES (?1, ?2, ?3)",
+            params![uid, note, now_timestamp()],
+        )?,
+        None => conn.execute("DELETE FROM notes WHERE uid = ?1", params![uid])?,
+    };
+    Ok(known)
+}
+
+/// The part of the inventory a run covered, so that `prune_resources_in_scope`
+/// leaves everything else alone.
+#[derive(Debug, Default)]
+pub struct PruneScope {
+    pub resource_types: Vec<String>,
+    /// Each scanned account with the regions scanned in it (including
+    /// `global`). A region only counts for the accounts that scanned it.
+    pub regions_by_account: BTreeMap<String, Vec<String>>,
+}
+
+/// Deletes resources that were not saved since `seen_since` (a timestamp from
+/// `now_timestamp`) and fall within `scope`, along with their tags and IP
+/// addresses. Returns the number of resources removed.
+pub fn prune_resources_in_scope(conn: &mut Connection, seen_since: &str, scope: &PruneScope) -> Result<usize> {
+    let mut values = vec![seen_since.to_string()];
+    let mut covered = Vec::new();
+    for (account_id, regions) in scope.regions_by_account.iter().filter(|(_, regions)| !regions.is_empty()) {
+        covered.push(format!("(account_id = ? AND region IN ({}))", vec!["?"; regions.len()].join(", ")));
+        values.push(account_id.clone());
+        values.extend(regions.iter().cloned());
+    }
+    if covered.is_empty() || scope.resource_types.is_empty() {
+        return Ok(0);
+    }
+    let sql = format!(
+        "DELETE FROM resources WHERE (last_seen IS NULL OR last_seen < ?) AND ({}) AND resource_type IN ({})",
+        covered.join(" OR "),
+        vec!["?"; scope.resource_types.len()].join(", ")
+    );
+    values.extend(scope.resource_types.iter().cloned());
+
+    let tx = conn.transaction()?;
+    let pruned = tx.execute(&sql, params_from_iter(values.iter()))?;
+    // Also sweeps up rows orphaned by earlier INSERT OR REPLACE upserts.
+    tx.execute("DELETE FROM tags WHERE resource_id NOT IN (SELECT id FROM resources)", [])?;
+    tx.execute("DELETE FROM ip_addresses WHERE resource_id NOT IN (SELECT id FROM resources)", [])?;
+    tx.execute("DELETE FROM raw_responses WHERE uid NOT IN (SELECT uid FROM resources)", [])?;
+
+    tx.commit()?;
+    Ok(pruned)
 }
 
 /// A stable implementation to check if an IP address is considered public.
 /// This is a simplified version of the unstable `is_global()` method.
-fn is_public(ip: &std::net::IpAddr) -> bool {
+pub(crate) fn is_public(ip: &std::net::IpAddr) -> bool {
     match ip {
-        std::net::IpAddr::V4(ipv4) => {
-            !ipv4.is_private()
-                && !ipv4.is_loopback()
-                && !ipv4.is_link_local()
-                && !ipv4.is_broadcast()
-                && !ipv4.is_documentation()
-                && !ipv4.is_unspecified()
-        }
+        std::net::IpAddr::V4(ipv4) => is_public_v4(ipv4),
         std::net::IpAddr::V6(ipv6) => {
+            // IPv4-mapped addresses (::ffff:0:0/96) are as public as the IPv4 address.
+            if let Some(ipv4) = ipv6.to_ipv4_mapped() {
+                return is_public_v4(&ipv4);
+            }
             let segments = ipv6.segments();
-            // Check for Unique Local Addresses (fc00::/7)
-            let is_unique_local = (segments[0] & 0xfe00) == 0xfc00;
-            // Check for Link-Local Addresses (fe80::/10)
-            let is_link_local = (segments[0] & 0xffc0) == 0xfe80;
-
-            !ipv6.is_loopback()
-                && !ipv6.is_unspecified()
-                && !is_unique_local
-                && !is_link_local
-                // Also check for documentation prefixes (2001:db8::/32)
+            // Only global unicast (2000::/3) is routable, which also rules out
+            // multicast, unique local, link-local, loopback and unspecified.
+            let is_global_unicast = (segments[0] & 0xe000) == 0x2000;
+
+            is_global_unicast
+                // Except the documentation prefix (2001:db8::/32)
                 && !((segments[0] == 0x2001) && (segments[1] == 0xdb8))
         }
     }
 }
+
+fn is_public_v4(ipv4: &std::net::Ipv4Addr) -> bool {
+    !ipv4.is_private()
+        && !ipv4.is_loopback()
+        && !ipv4.is_link_local()
+        && !ipv4.is_broadcast()
+        && !ipv4.is_documentation()
+        && !ipv4.is_unspecified()
+}
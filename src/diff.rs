@@ -0,0 +1,231 @@
+use crate::query::{self, QueryFilter, Resource};
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// The differences between two inventories, as produced by `diff_inventories`.
+/// Each list is sorted by resource type, then id.
+#[derive(Serialize, Debug, Default)]
+pub struct InventoryDiff {
+    pub added: Vec<DiffResource>,
+    pub removed: Vec<DiffResource>,
+    pub changed: Vec<ChangedResource>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DiffResource {
+    pub id: String,
+    pub name: String,
+    pub resource_type: String,
+    pub region: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ChangedResource {
+    #[serde(flatten)]
+    pub resource: DiffResource,
+    pub changes: Vec<FieldChange>,
+}
+
+/// One differing field: `name`, `ips`, `tags`, or `details.<key>` for each
+/// top-level details field that was added, removed or modified.
+#[derive(Serialize, Debug)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// The RFC 6902 operations that turn a changed resource's old state into its
+/// new one, as printed by `diff --patch`.
+#[derive(Serialize, Debug)]
+pub struct ResourcePatch {
+    #[serde(flatten)]
+    pub resource: DiffResource,
+    pub patch: Vec<PatchOperation>,
+}
+
+/// One JSON Patch operation. Paths are JSON Pointers into the resource state
+/// built by `patch_document`, e.g. `/details/state` or `/tags/Env`.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+}
+
+impl From<&Resource> for DiffResource {
+    fn from(resource: &Resource) -> Self {
+        DiffResource {
+            id: resource.id.clone(),
+            name: resource.name.clone(),
+            resource_type: resource.resource_type.clone(),
+            region: resource.region.clone(),
+        }
+    }
+}
+
+/// Compares the resources of two inventories, matched by id (the ARN where
+/// the resource has one). IPs and tags are compared regardless of order.
+pub fn diff_inventories(old: &Path, new: &Path) -> Result<InventoryDiff> {
+    let old = load(old)?;
+    let mut new = load(new)?;
+
+    let mut diff = InventoryDiff::default();
+    for (key, old_resource) in &old {
+        match new.remove(key) {
+            None => diff.removed.push(old_resource.into()),
+            Some(new_resource) => {
+                let changes = field_changes(old_resource, &new_resource);
+                if !changes.is_empty() {
+                    diff.changed.push(ChangedResource { resource: (&new_resource).into(), changes });
+                }
+            }
+        }
+    }
+    diff.added = new.values().map(DiffResource::from).collect();
+    Ok(diff)
+}
+
+/// JSON Patches for the resources present in both inventories that changed,
+/// sorted like `diff_inventories`. Added and removed resources are left out.
+pub fn patch_inventories(old: &Path, new: &Path) -> Result<Vec<ResourcePatch>> {
+    let old = load(old)?;
+    let new = load(new)?;
+    Ok(old
+        .iter()
+        .filter_map(|(key, old_resource)| {
+            let new_resource = new.get(key)?;
+            let patch = json_patch(&patch_document(old_resource), &patch_document(new_resource));
+            (!patch.is_empty()).then(|| ResourcePatch { resource: new_resource.into(), patch })
+        })
+        .collect())
+}
+
+fn load(path: &Path) -> Result<BTreeMap<(String, String), Resource>> {
+    Ok(query::run_query(path, &QueryFilter::default())?
+        .into_iter()
+        .map(|r| ((r.resource_type.clone(), r.id.clone()), r))
+        .collect())
+}
+
+/// The part of a resource that patches describe. IPs are sorted so that a
+/// reordering alone is not reported.
+fn patch_document(resource: &Resource) -> Value {
+    let mut ips = resource.ips.clone();
+    ips.sort();
+    serde_json::json!({
+        "name": resource.name,
+        "ips": ips,
+        "tags": resource.tags,
+        "details": resource.details,
+    })
+}
+
+/// Computes the RFC 6902 operations turning `old` into `new`. Objects are
+/// compared key by key; any other differing value, arrays included, is
+/// replaced whole.
+pub fn json_patch(old: &Value, new: &Value) -> Vec<PatchOperation> {
+    let mut ops = Vec::new();
+    diff_values("", old, new, &mut ops);
+    ops
+}
+
+fn diff_values(path: &str, old: &Value, new: &Value, ops: &mut Vec<PatchOperation>) {
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            for (key, old_value) in old_map {
+                let child = format!("{}/{}", path, escape_pointer(key));
+                match new_map.get(key) {
+                    Some(new_value) => diff_values(&child, old_value, new_value, ops),
+                    None => ops.push(PatchOperation::Remove { path: child }),
+                }
+            }
+            for (key, new_value) in new_map {
+                if !old_map.contains_key(key) {
+                    ops.push(PatchOperation::Add { path: format!("{}/{}", path, escape_pointer(key)), value: new_value.clone() });
+                }
+            }
+        }
+        _ if old != new => ops.push(PatchOperation::Replace { path: path.to_string(), value: new.clone() }),
+        _ => {}
+    }
+}
+
+/// Escapes a key for use in a JSON Pointer (RFC 6901).
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn field_changes(old: &Resource, new: &Resource) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    if old.name != new.name {
+        changes.push(FieldChange { field: "name".to_string(), old: Value::from(old.name.clone()), new: Value::from(new.name.clone()) });
+    }
+
+    let old_ips: BTreeSet<&String> = old.ips.iter().collect();
+    let new_ips: BTreeSet<&String> = new.ips.iter().collect();
+    if old_ips != new_ips {
+        changes.push(FieldChange {
+            field: "ips".to_string(),
+            old: Value::from(old_ips.into_iter().cloned().collect::<Vec<_>>()),
+            new: Value::from(new_ips.into_iter().cloned().collect::<Vec<_>>()),
+        });
+    }
+
+    // serde_json objects compare by key, so tag order never matters.
+    if old.tags != new.tags {
+        changes.push(FieldChange { field: "tags".to_string(), old: old.tags.clone(), new: new.tags.clone() });
+    }
+
+    match (old.details.as_object(), new.details.as_object()) {
+        (Some(old_details), Some(new_details)) => {
+            let keys: BTreeSet<&String> = old_details.keys().chain(new_details.keys()).collect();
+            for key in keys {
+                let old_value = old_details.get(key).cloned().unwrap_or(Value::Null);
+                let new_value = new_details.get(key).cloned().unwrap_or(Value::Null);
+                if old_value != new_value {
+                    changes.push(FieldChange { field: format!("details.{}", key), old: old_value, new: new_value });
+                }
+            }
+        }
+        _ if old.details != new.details => {
+            changes.push(FieldChange { field: "details".to_string(), old: old.details.clone(), new: new.details.clone() });
+        }
+        _ => {}
+    }
+    changes
+}
+
+/// Prints the changed resources' patches as a JSON array.
+pub fn print_patches(old: &Path, new: &Path) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&patch_inventories(old, new)?)?);
+    Ok(())
+}
+
+pub fn print_diff(old: &Path, new: &Path, json_output: bool) -> Result<()> {
+    let diff = diff_inventories(old, new)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+        return Ok(());
+    }
+
+    let line = |marker: char, r: &DiffResource| println!("  {} {}  {}  {} ({})", marker, r.resource_type, r.name, r.id, r.region);
+
+    println!("Added ({}):", diff.added.len());
+    diff.added.iter().for_each(|r| line('+', r));
+    println!("\nRemoved ({}):", diff.removed.len());
+    diff.removed.iter().for_each(|r| line('-', r));
+    println!("\nChanged ({}):", diff.changed.len());
+    for changed in &diff.changed {
+        line('~', &changed.resource);
+        for change in &changed.changes {
+            println!("      {}: {} -> {}", change.field, change.old, change.new);
+        }
+    }
+    Ok(())
+}
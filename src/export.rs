@@ -1,18 +1,469 @@
+use crate::query::{self, QueryFilter};
 use anyhow::Result;
-use rusqlite::Connection;
+use rusqlite::{params_from_iter, Connection};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::File;
 use std::io::{BufWriter, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use chrono::Utc;
 
+/// Replaces AWS account ids with pseudonyms (`account-1`, `account-2`, ...)
+/// for `--anonymize-accounts`. Ids are numbered in the order first seen, and
+/// one instance is used per export so the same account always gets the same
+/// pseudonym. Only account ids are replaced; names, tags and notes are kept.
+#[derive(Debug, Default)]
+pub struct AccountAnonymizer {
+    pseudonyms: HashMap<String, String>,
+}
+
+impl AccountAnonymizer {
+    pub fn account(&mut self, account_id: &str) -> String {
+        let next = self.pseudonyms.len() + 1;
+        self.pseudonyms
+            .entry(account_id.to_string())
+            .or_insert_with(|| format!("account-{}", next))
+            .clone()
+    }
+
+    /// `text` with a bare account id, or the account field of every ARN it
+    /// contains, replaced by the account's pseudonym.
+    pub fn scrub(&mut self, text: &str) -> String {
+        if is_account_id(text) {
+            return self.account(text);
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("arn:") {
+            let (before, arn) = rest.split_at(start);
+            out.push_str(before);
+            // arn:partition:service:region:account-id:resource
+            let fields: Vec<&str> = arn.splitn(6, ':').collect();
+            if fields.len() == 6 && is_account_id(fields[4]) {
+                let prefix_len: usize = fields[..4].iter().map(|f| f.len() + 1).sum();
+                out.push_str(&arn[..prefix_len]);
+                out.push_str(&self.account(fields[4]));
+                rest = &arn[prefix_len + fields[4].len()..];
+            } else {
+                out.push_str("arn:");
+                rest = &arn["arn:".len()..];
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// Applies `scrub` to every string within `value`.
+    pub fn scrub_value(&mut self, value: &mut Value) {
+        match value {
+            Value::String(s) => *s = self.scrub(s),
+            Value::Array(items) => items.iter_mut().for_each(|item| self.scrub_value(item)),
+            Value::Object(map) => map.values_mut().for_each(|item| self.scrub_value(item)),
+            _ => {}
+        }
+    }
+}
+
+fn is_account_id(s: &str) -> bool {
+    s.len() == 12 && s.bytes().all(|b| b.is_ascii_digit())
+}
+
 /// Sanitizes a string to be a valid part of a hostname.
 fn sanitize(name: &str) -> String {
     name.replace(|c: char| !(c.is_ascii_alphanumeric() || c == '-'), "-")
         .to_ascii_lowercase()
 }
 
-pub fn to_hosts_file_from_db(db_path: &Path, output_path: &Path) -> Result<()> {
-    let conn = Connection::open(db_path)?;
+/// Placeholders a hosts `template` may use. `{hostname}` is the generated
+/// name of the default format, e.g. `ec2.web-1.i-0abc.us-east-1`.
+pub const HOSTS_TEMPLATE_FIELDS: &[&str] = &["ip", "hostname", "name", "id", "region", "resource_type"];
+
+/// Fills the `{field}` placeholders of `template` from `fields`, turning a
+/// literal `\t` into a tab. Fails on a placeholder not in `HOSTS_TEMPLATE_FIELDS`.
+fn render_hosts_template(template: &str, fields: &[(&str, &str)]) -> Result<String> {
+    let mut line = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        line.push_str(&rest[..start].replace("\\t", "\t"));
+        let Some(len) = rest[start..].find('}') else {
+            anyhow::bail!("Template '{}' has an unclosed '{{'", template);
+        };
+        let field = &rest[start + 1..start + len];
+        if !HOSTS_TEMPLATE_FIELDS.contains(&field) {
+            anyhow::bail!(
+                "Unknown placeholder '{{{}}}' in template; expected one of {}",
+                field,
+                HOSTS_TEMPLATE_FIELDS.iter().map(|f| format!("{{{}}}", f)).collect::<Vec<_>>().join(" ")
+            );
+        }
+        line.push_str(fields.iter().find(|(key, _)| *key == field).map(|(_, value)| *value).unwrap_or_default());
+        rest = &rest[start + len + 1..];
+    }
+    line.push_str(&rest.replace("\\t", "\t"));
+    Ok(line)
+}
+
+/// Writes a hosts file for the resources matching `filter`, one line per IP
+/// address. With `template`, each line is rendered from it (see
+/// `HOSTS_TEMPLATE_FIELDS`) instead of the default `ip<TAB>hostname` format.
+pub fn to_hosts_file_from_db(db_path: &Path, output_path: &Path, filter: &QueryFilter, template: Option<&str>) -> Result<()> {
+    let conn = crate::db::open_read_only(db_path)?;
+    let (condition, params) = filter_condition(filter);
+    write_hosts_file(&conn, output_path, &condition, &params, template)
+}
+
+/// Writes the hosts file from an already open inventory, such as the
+/// in-memory database of an `inventory --output :memory:` run.
+pub fn to_hosts_file(conn: &Connection, output_path: &Path) -> Result<()> {
+    write_hosts_file(conn, output_path, "", &[], None)
+}
+
+/// The `WHERE` clause and parameters selecting the resources matching `filter`.
+fn filter_condition(filter: &QueryFilter) -> (String, Vec<String>) {
+    let mut condition = "WHERE 1=1".to_string();
+    let mut params = Vec::new();
+    query::push_filter_clauses(filter, &mut condition, &mut params);
+    (condition, params)
+}
+
+/// Writes one hosts file per distinct value of the `tag_key` tag, named after
+/// `output_path` with the value as extension (`hosts.txt` -> `hosts.prod`),
+/// plus `hosts.untagged` for resources without the tag. Only resources matching
+/// `filter` are written, formatted as by `to_hosts_file_from_db`. Returns the
+/// files written.
+pub fn to_hosts_files_split_by_tag(
+    db_path: &Path,
+    output_path: &Path,
+    tag_key: &str,
+    filter: &QueryFilter,
+    template: Option<&str>,
+) -> Result<Vec<PathBuf>> {
+    let conn = crate::db::open_read_only(db_path)?;
+    let (base_condition, base_params) = filter_condition(filter);
+    let stem = output_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "hosts".to_string());
+
+    // Values that sanitize to the same file name (e.g. "Prod" and "prod") share a file.
+    let mut values_by_suffix: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut stmt = conn.prepare("SELECT DISTINCT value FROM tags WHERE key = ?1 ORDER BY value")?;
+    for value in stmt.query_map([tag_key], |row| row.get::<_, String>(0))? {
+        let value = value?;
+        values_by_suffix.entry(sanitize(&value)).or_default().push(value);
+    }
+
+    let mut written = Vec::new();
+    for (suffix, values) in values_by_suffix {
+        let path = output_path.with_file_name(format!("{}.{}", stem, suffix));
+        let placeholders = values.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        let condition = format!(
+            "{} AND EXISTS (SELECT 1 FROM tags t WHERE t.resource_id = r.id AND t.key = ? AND t.value IN ({}))",
+            base_condition, placeholders
+        );
+        let mut params = base_params.clone();
+        params.push(tag_key.to_string());
+        params.extend(values);
+        write_hosts_file(&conn, &path, &condition, &params, template)?;
+        written.push(path);
+    }
+
+    let path = output_path.with_file_name(format!("{}.untagged", stem));
+    let mut params = base_params;
+    params.push(tag_key.to_string());
+    write_hosts_file(
+        &conn,
+        &path,
+        &format!("{} AND NOT EXISTS (SELECT 1 FROM tags t WHERE t.resource_id = r.id AND t.key = ?)", base_condition),
+        &params,
+        template,
+    )?;
+    written.push(path);
+
+    Ok(written)
+}
+
+/// Quotes a CSV field per RFC 4180 when it contains a comma, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Writes the resources matching `filter` as CSV and returns the number of
+/// rows. IPs are joined with semicolons, and every tag key found among the
+/// results gets its own `tag:<key>` column, in sorted order.
+pub fn to_csv_from_db(db_path: &Path, output_path: &Path, filter: &QueryFilter, anonymize: bool) -> Result<usize> {
+    let mut resources = query::run_query(db_path, filter)?;
+    if anonymize {
+        anonymize_resources(&mut resources);
+    }
+    let tag_keys: BTreeSet<&str> = resources
+        .iter()
+        .filter_map(|r| r.tags.as_object())
+        .flat_map(|tags| tags.keys().map(|k| k.as_str()))
+        .collect();
+
+    let mut writer = BufWriter::new(File::create(output_path)?);
+    let mut header = vec!["id".to_string(), "arn".to_string(), "name".to_string(), "resource_type".to_string(), "region".to_string(), "ips".to_string()];
+    header.extend(tag_keys.iter().map(|k| format!("tag:{}", k)));
+    // RFC 4180 lines end with CRLF.
+    write!(writer, "{}\r\n", header.iter().map(|h| csv_field(h)).collect::<Vec<_>>().join(","))?;
+
+    for resource in &resources {
+        let mut row = vec![
+            csv_field(&resource.id),
+            csv_field(resource.arn.as_deref().unwrap_or_default()),
+            csv_field(&resource.name),
+            csv_field(&resource.resource_type),
+            csv_field(&resource.region),
+            csv_field(&resource.ips.join(";")),
+        ];
+        for key in &tag_keys {
+            let value = resource.tags.get(*key).and_then(Value::as_str).unwrap_or_default();
+            row.push(csv_field(value));
+        }
+        write!(writer, "{}\r\n", row.join(","))?;
+    }
+    writer.flush()?;
+    Ok(resources.len())
+}
+
+/// Scrubs the account ids out of the ids, ARNs and details of `resources`.
+fn anonymize_resources(resources: &mut [query::Resource]) {
+    let mut anonymizer = AccountAnonymizer::default();
+    for resource in resources {
+        resource.id = anonymizer.scrub(&resource.id);
+        resource.arn = resource.arn.as_deref().map(|arn| anonymizer.scrub(arn));
+        anonymizer.scrub_value(&mut resource.details);
+    }
+}
+
+/// The `details` fields shown for each resource type in the runbook. Types
+/// not listed get just the common columns.
+fn runbook_fields(resource_type: &str) -> &'static [&'static str] {
+    match resource_type {
+        "ec2:instance" => &["instance_type", "state", "tenancy"],
+        "elbv2:loadbalancer" => &["type", "scheme", "dns_name"],
+        "rds:db_instance" => &["engine", "instance_class", "multi_az", "deletion_protection"],
+        "dynamodb:table" => &["item_count", "table_size_bytes"],
+        "elasticache:cluster" => &["engine", "engine_version", "cache_node_type"],
+        "eks:pod" => &["cluster", "namespace"],
+        "eks:node" => &["cluster", "instance_id", "ready"],
+        "eks:service" => &["cluster", "namespace", "type"],
+        "ecs:task" => &["cluster_arn", "launch_type", "last_status"],
+        "ec2:vpc" => &["cidr_block", "is_default"],
+        "ec2:subnet" => &["vpc_id", "cidr_block", "utilization"],
+        "cloudtrail:trail" => &["is_logging", "is_multi_region", "log_file_validation_enabled"],
+        "mq:broker" => &["engine_type", "deployment_mode"],
+        "workspaces:workspace" => &["user_name", "state"],
+        _ => &[],
+    }
+}
+
+/// Whether a resource is reachable from the internet: it holds a public
+/// address, or its details say so (RDS and MQ `publicly_accessible`, an
+/// internet-facing load balancer scheme).
+fn is_publicly_exposed(resource: &query::Resource) -> bool {
+    resource
+        .ips
+        .iter()
+        .filter_map(|ip| ip.parse().ok())
+        .any(|ip| crate::db::is_public(&ip))
+        || resource.details.get("publicly_accessible").and_then(Value::as_bool) == Some(true)
+        || resource.details.get("scheme").and_then(Value::as_str) == Some("internet-facing")
+}
+
+/// Escapes a value for a Markdown table cell.
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Writes a Markdown runbook of the whole inventory: a count summary, then a
+/// section per resource type with a table of name, region, public exposure and
+/// the type's key details. Returns the number of resources covered.
+pub fn to_runbook_from_db(db_path: &Path, output_path: &Path, anonymize: bool) -> Result<usize> {
+    let mut resources = query::run_query(db_path, &QueryFilter::default())?;
+    if anonymize {
+        anonymize_resources(&mut resources);
+    }
+    let mut by_type: BTreeMap<&str, Vec<&query::Resource>> = BTreeMap::new();
+    for resource in &resources {
+        by_type.entry(resource.resource_type.as_str()).or_default().push(resource);
+    }
+
+    let mut writer = BufWriter::new(File::create(output_path)?);
+    writeln!(writer, "# AWS Inventory Runbook\n")?;
+    writeln!(writer, "Generated by aws-inventory-sdk on {}.\n", Utc::now().format("%Y-%m-%d %H:%M UTC"))?;
+
+    writeln!(writer, "## Summary\n")?;
+    writeln!(writer, "| Resource type | Count | Regions | Publicly exposed |")?;
+    writeln!(writer, "|---|---:|---|---:|")?;
+    for (resource_type, group) in &by_type {
+        let regions: BTreeSet<&str> = group.iter().map(|r| r.region.as_str()).collect();
+        let exposed = group.iter().filter(|r| is_publicly_exposed(r)).count();
+        writeln!(
+            writer,
+            "| {} | {} | {} | {} |",
+            resource_type,
+            group.len(),
+            regions.into_iter().collect::<Vec<_>>().join(", "),
+            exposed
+        )?;
+    }
+
+    for (resource_type, group) in &by_type {
+        let fields = runbook_fields(resource_type);
+        writeln!(writer, "\n## {}\n", resource_type)?;
+
+        let mut header = vec!["Name", "Region", "Exposure"];
+        header.extend(fields.iter().copied());
+        writeln!(writer, "| {} |", header.join(" | "))?;
+        writeln!(writer, "|{}", "---|".repeat(header.len()))?;
+
+        let mut rows: Vec<&&query::Resource> = group.iter().collect();
+        rows.sort_by(|a, b| (&a.region, &a.name).cmp(&(&b.region, &b.name)));
+        for resource in rows {
+            let mut cells = vec![
+                markdown_cell(&resource.name),
+                markdown_cell(&resource.region),
+                if is_publicly_exposed(resource) { "public".to_string() } else { "private".to_string() },
+            ];
+            for field in fields {
+                let value = match resource.details.get(*field) {
+                    None | Some(Value::Null) => String::new(),
+                    Some(Value::String(s)) => s.clone(),
+                    Some(other) => other.to_string(),
+                };
+                cells.push(markdown_cell(&value));
+            }
+            writeln!(writer, "| {} |", cells.join(" | "))?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(resources.len())
+}
+
+/// Writes every resource as one JSON object per line (NDJSON) and returns the
+/// number written. With `flatten`, rows get the uniform shape described in
+/// `flatten_row` for warehouse ingestion; otherwise `details` is kept nested.
+/// With `anonymize`, account ids in the id, ARN, account and details columns
+/// are replaced as described in `AccountAnonymizer`.
+pub fn to_ndjson(db_path: &Path, output_path: &Path, flatten: bool, anonymize: bool) -> Result<usize> {
+    let conn = crate::db::open_read_only(db_path)?;
+    let mut stmt = conn.prepare(
+        "SELECT r.uid, r.arn, r.name, r.resource_type, r.region, r.account_id, r.last_seen, r.created_at, r.source,
+                (SELECT note FROM notes WHERE uid = r.uid),
+                (SELECT json_group_object(key, value) FROM tags WHERE resource_id = r.id),
+                (SELECT json_group_array(json_object('ip', ip_address, 'is_public', is_public))
+                 FROM ip_addresses WHERE resource_id = r.id),
+                r.details
+         FROM resources r
+         ORDER BY r.resource_type, r.uid",
+    )?;
+
+    let mut anonymizer = anonymize.then(AccountAnonymizer::default);
+    let mut rows = Vec::new();
+    let mut query_rows = stmt.query([])?;
+    while let Some(row) = query_rows.next()? {
+        let mut object = Map::new();
+        for (i, column) in ["id", "arn", "name", "resource_type", "region", "account_id", "last_seen", "created_at", "source", "note"]
+            .iter()
+            .enumerate()
+        {
+            object.insert(column.to_string(), row.get::<_, Option<String>>(i)?.map(Value::from).unwrap_or(Value::Null));
+        }
+        let tags: Value = serde_json::from_str(&row.get::<_, Option<String>>(10)?.unwrap_or_else(|| "{}".to_string()))?;
+        let ips: Value = serde_json::from_str(&row.get::<_, Option<String>>(11)?.unwrap_or_else(|| "[]".to_string()))?;
+        let details: Value = serde_json::from_str(&row.get::<_, Option<String>>(12)?.unwrap_or_else(|| "null".to_string()))?;
+        object.insert("tags".to_string(), tags);
+        object.insert("ips".to_string(), ips);
+        object.insert("details".to_string(), details);
+        if let Some(anonymizer) = anonymizer.as_mut() {
+            for column in ["id", "arn", "account_id", "details"] {
+                if let Some(value) = object.get_mut(column) {
+                    anonymizer.scrub_value(value);
+                }
+            }
+        }
+        rows.push(object);
+    }
+
+    let detail_keys: BTreeSet<String> = rows
+        .iter()
+        .filter_map(|row| row.get("details").and_then(Value::as_object))
+        .flat_map(|details| details.keys().cloned())
+        .collect();
+
+    let mut writer = BufWriter::new(File::create(output_path)?);
+    for row in &rows {
+        let row = if flatten { flatten_row(row, &detail_keys) } else { raw_row(row) };
+        writeln!(writer, "{}", Value::Object(row))?;
+    }
+    writer.flush()?;
+    Ok(rows.len())
+}
+
+/// The nested export shape: IP addresses as a plain list, `details` as stored.
+fn raw_row(row: &Map<String, Value>) -> Map<String, Value> {
+    let mut out = row.clone();
+    let ips: Vec<Value> = ip_entries(row).map(|(ip, _)| ip.clone()).collect();
+    out.insert("ips".to_string(), Value::Array(ips));
+    out
+}
+
+/// The warehouse shape. Every row has the same columns, null where absent:
+/// the resource columns, `tags` as a string map, `ips`, `public_ips`,
+/// `private_ips` and `is_public`, and a `details_<key>` column for every
+/// details key seen in the export. Nested detail values are JSON-encoded
+/// strings so each column keeps a scalar type.
+fn flatten_row(row: &Map<String, Value>, detail_keys: &BTreeSet<String>) -> Map<String, Value> {
+    let mut out = Map::new();
+    for (key, value) in row {
+        if key != "ips" && key != "details" {
+            out.insert(key.clone(), value.clone());
+        }
+    }
+
+    let (public_ips, private_ips): (Vec<_>, Vec<_>) = ip_entries(row).partition(|(_, is_public)| *is_public);
+    let all_ips: Vec<Value> = ip_entries(row).map(|(ip, _)| ip.clone()).collect();
+    out.insert("is_public".to_string(), Value::from(!public_ips.is_empty()));
+    out.insert("ips".to_string(), Value::Array(all_ips));
+    out.insert("public_ips".to_string(), Value::Array(public_ips.into_iter().map(|(ip, _)| ip.clone()).collect()));
+    out.insert("private_ips".to_string(), Value::Array(private_ips.into_iter().map(|(ip, _)| ip.clone()).collect()));
+
+    let details = row.get("details").and_then(Value::as_object);
+    for key in detail_keys {
+        let value = match details.and_then(|d| d.get(key)) {
+            Some(value @ (Value::Array(_) | Value::Object(_))) => Value::from(value.to_string()),
+            Some(value) => value.clone(),
+            None => Value::Null,
+        };
+        out.insert(format!("details_{}", key), value);
+    }
+    out
+}
+
+/// The `(ip, is_public)` pairs of an exported row.
+fn ip_entries(row: &Map<String, Value>) -> impl Iterator<Item = (&Value, bool)> {
+    row.get("ips")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| Some((entry.get("ip")?, entry.get("is_public")?.as_i64()? != 0)))
+}
+
+/// Writes the hosts entries for the resources matching `condition`, a `WHERE`
+/// clause over `resources r` (or empty for all resources), formatted with
+/// `template` if given.
+fn write_hosts_file(conn: &Connection, output_path: &Path, condition: &str, params: &[String], template: Option<&str>) -> Result<()> {
+    // Reject a bad template before creating the file.
+    if let Some(template) = template {
+        render_hosts_template(template, &[])?;
+    }
     let file = File::create(output_path)?;
     let mut writer = BufWriter::new(file);
 
@@ -22,21 +473,23 @@ pub fn to_hosts_file_from_db(db_path: &Path, output_path: &Path) -> Result<()> {
         Utc::now()
     )?;
 
-    let mut stmt = conn.prepare(
-        "SELECT r.name, r.resource_type, r.region, r.arn, r.details, i.ip_address
+    let mut stmt = conn.prepare(&format!(
+        "SELECT r.name, r.resource_type, r.region, r.uid, r.details, i.ip_address
          FROM resources r
          JOIN ip_addresses i ON r.id = i.resource_id
+         {}
          ORDER BY r.resource_type, r.name",
-    )?;
+        condition
+    ))?;
 
-    let mut rows = stmt.query([])?;
+    let mut rows = stmt.query(params_from_iter(params))?;
     let mut current_type = String::new();
 
     while let Some(row) = rows.next()? {
         let name: String = row.get(0)?;
         let resource_type: String = row.get(1)?;
         let region: String = row.get(2)?;
-        let arn: String = row.get(3)?;
+        let id: String = row.get(3)?;
         let details_json: String = row.get(4)?;
         let ip: String = row.get(5)?;
 
@@ -50,22 +503,48 @@ pub fn to_hosts_file_from_db(db_path: &Path, output_path: &Path) -> Result<()> {
 
         let details: serde_json::Value = serde_json::from_str(&details_json)?;
 
+        // The default format follows some hostnames with a comment, such as a
+        // load balancer's DNS name; templates only get the hostname.
+        let mut comment = None;
         let hostname = match resource_type.as_str() {
-            "ec2:instance" => format!("ec2.{}.{}.{}", sanitize(&name), sanitize(&arn), sanitize(&region)),
+            "ec2:instance" => format!("ec2.{}.{}.{}", sanitize(&name), sanitize(&id), sanitize(&region)),
             "elbv2:loadbalancer" => {
                 let lb_type = details.get("type").and_then(|v| v.as_str()).unwrap_or("unknown");
-                let dns_name = details.get("dns_name").and_then(|v| v.as_str()).unwrap_or("no-dns");
-                format!("lb.{}.{}.{} # {}", sanitize(&name), sanitize(lb_type), sanitize(&region), dns_name)
+                comment = Some(details.get("dns_name").and_then(|v| v.as_str()).unwrap_or("no-dns"));
+                format!("lb.{}.{}.{}", sanitize(&name), sanitize(lb_type), sanitize(&region))
             }
             "eks:pod" => {
                 let cluster = details.get("cluster").and_then(|v| v.as_str()).unwrap_or("unknown-cluster");
                 let namespace = details.get("namespace").and_then(|v| v.as_str()).unwrap_or("unknown-ns");
                 format!("pod.{}.{}.{}.{}", sanitize(&name), sanitize(namespace), sanitize(cluster), sanitize(&region))
             }
-            _ => format!("unknown.{}.{}.{}", sanitize(&name), sanitize(&arn), sanitize(&region)),
+            "eks:node" => {
+                let cluster = details.get("cluster").and_then(|v| v.as_str()).unwrap_or("unknown-cluster");
+                format!("node.{}.{}.{}", sanitize(&name), sanitize(cluster), sanitize(&region))
+            }
+            "eks:service" => {
+                let cluster = details.get("cluster").and_then(|v| v.as_str()).unwrap_or("unknown-cluster");
+                let namespace = details.get("namespace").and_then(|v| v.as_str()).unwrap_or("unknown-ns");
+                format!("svc.{}.{}.{}.{}", sanitize(&name), sanitize(namespace), sanitize(cluster), sanitize(&region))
+            }
+            _ => format!("unknown.{}.{}.{}", sanitize(&name), sanitize(&id), sanitize(&region)),
         };
 
-        writeln!(writer, "{}\t{}", ip, hostname)?;
+        match (template, comment) {
+            (Some(template), _) => {
+                let fields = [
+                    ("ip", ip.as_str()),
+                    ("hostname", hostname.as_str()),
+                    ("name", name.as_str()),
+                    ("id", id.as_str()),
+                    ("region", region.as_str()),
+                    ("resource_type", resource_type.as_str()),
+                ];
+                writeln!(writer, "{}", render_hosts_template(template, &fields)?)?;
+            }
+            (None, Some(comment)) => writeln!(writer, "{}\t{} # {}", ip, hostname, comment)?,
+            (None, None) => writeln!(writer, "{}\t{}", ip, hostname)?,
+        }
         }
 
     writer.flush()?;
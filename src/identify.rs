@@ -1,14 +1,19 @@
 use anyhow::Result;
-use rusqlite::{params, Connection};
+use ipnet::IpNet;
+use rusqlite::params;
+use serde::Serialize;
+use std::io::BufRead;
 use std::net::IpAddr;
 use std::path::Path;
+use tracing::warn;
 
 pub fn identify_resource_from_db(db_path: &Path, ip_address: IpAddr) -> Result<Option<String>> {
-    let conn = Connection::open(db_path)?;
+    let conn = crate::db::open_read_only(db_path)?;
 
     let mut stmt = conn.prepare(
         "
-        SELECT r.name, r.resource_type, r.region, r.arn
+        SELECT r.name, r.resource_type, r.region, COALESCE(r.arn, r.uid), i.eni_id, i.description,
+               (SELECT note FROM notes WHERE uid = r.uid), r.account_id, r.profile
         FROM resources r
         JOIN ip_addresses i ON r.id = i.resource_id
         WHERE i.ip_address = ?1
@@ -20,11 +25,169 @@ pub fn identify_resource_from_db(db_path: &Path, ip_address: IpAddr) -> Result<O
         let resource_type: String = row.get(1)?;
         let region: String = row.get(2)?;
         let arn: String = row.get(3)?;
-        Ok(format!(
+        let eni_id: Option<String> = row.get(4)?;
+        let description: Option<String> = row.get(5)?;
+        let note: Option<String> = row.get(6)?;
+        let account_id: Option<String> = row.get(7)?;
+        let profile: Option<String> = row.get(8)?;
+
+        let mut line = format!(
             "IP: {} - Type: {}, Name: {}, Region: {}, ARN/ID: {}",
             ip_address, resource_type, name, region, arn
-        ))
+        );
+        if let Some(eni_id) = eni_id {
+            line.push_str(&format!(", ENI: {}", eni_id));
+        }
+        if let Some(description) = description {
+            line.push_str(&format!(" ({})", description));
+        }
+        if let Some(account_id) = account_id {
+            line.push_str(&format!(", Account: {}", account_id));
+        }
+        if let Some(profile) = profile {
+            line.push_str(&format!(", Profile: {}", profile));
+        }
+        if let Some(note) = note {
+            line.push_str(&format!("\n  Note: {}", note));
+        }
+        Ok(line)
     });
 
     Ok(result.ok())
 }
+
+/// Lists every recorded address inside `cidr`, one line per address and
+/// resource in the same format as `identify_resource_from_db`, sorted by address.
+pub fn identify_resources_in_cidr(db_path: &Path, cidr: IpNet) -> Result<Vec<String>> {
+    let conn = crate::db::open_read_only(db_path)?;
+    let mut stmt = conn.prepare(
+        "
+        SELECT i.ip_address, r.name, r.resource_type, r.region, COALESCE(r.arn, r.uid)
+        FROM resources r
+        JOIN ip_addresses i ON r.id = i.resource_id
+        ",
+    )?;
+
+    // Addresses are stored as text, so containment is checked here rather than in SQL.
+    let mut matches: Vec<(IpAddr, String)> = Vec::new();
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, String>(4)?,
+        ))
+    })?;
+    for row in rows {
+        let (ip_str, name, resource_type, region, arn) = row?;
+        let Ok(ip) = ip_str.parse::<IpAddr>() else { continue };
+        if cidr.contains(&ip) {
+            let line = format!(
+                "IP: {} - Type: {}, Name: {}, Region: {}, ARN/ID: {}",
+                ip, resource_type, name, region, arn
+            );
+            matches.push((ip, line));
+        }
+    }
+    matches.sort();
+    Ok(matches.into_iter().map(|(_, line)| line).collect())
+}
+
+/// The resources, if any, holding one of the addresses given to `identify_many`.
+#[derive(Serialize, Debug)]
+pub struct IpMatch {
+    pub ip: IpAddr,
+    pub found: bool,
+    pub resources: Vec<MatchedResource>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MatchedResource {
+    pub name: String,
+    pub resource_type: String,
+    pub region: String,
+    pub arn: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eni_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_id: Option<String>,
+    /// The AWS profile that collected the resource.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+/// Looks up every address in `ips` over a single connection and prepared statement.
+pub fn identify_many(db_path: &Path, ips: &[IpAddr]) -> Result<Vec<IpMatch>> {
+    let conn = crate::db::open_read_only(db_path)?;
+    let mut stmt = conn.prepare(
+        "
+        SELECT r.name, r.resource_type, r.region, COALESCE(r.arn, r.uid), i.eni_id, i.description,
+               (SELECT note FROM notes WHERE uid = r.uid), r.account_id, r.profile
+        FROM resources r
+        JOIN ip_addresses i ON r.id = i.resource_id
+        WHERE i.ip_address = ?1
+        ",
+    )?;
+
+    let mut matches = Vec::with_capacity(ips.len());
+    for ip in ips {
+        let resources = stmt
+            .query_map(params![ip.to_string()], |row| {
+                Ok(MatchedResource {
+                    name: row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                    resource_type: row.get(1)?,
+                    region: row.get(2)?,
+                    arn: row.get(3)?,
+                    eni_id: row.get(4)?,
+                    description: row.get(5)?,
+                    note: row.get(6)?,
+                    account_id: row.get(7)?,
+                    profile: row.get(8)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        matches.push(IpMatch {
+            ip: *ip,
+            found: !resources.is_empty(),
+            resources,
+        });
+    }
+    Ok(matches)
+}
+
+/// Reads one IP address per line, skipping blank lines and `#` comments.
+/// Lines that aren't addresses are reported and skipped.
+pub fn read_ips(reader: impl BufRead) -> Result<Vec<IpAddr>> {
+    let mut ips = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.parse() {
+            Ok(ip) => ips.push(ip),
+            Err(_) => warn!("line {} is not an IP address, skipping: {}", number + 1, line),
+        }
+    }
+    Ok(ips)
+}
+
+/// Prints `matches` as tab-separated values, one row per matched resource or
+/// a `not found` row per unmatched address.
+pub fn print_tsv(matches: &[IpMatch]) {
+    println!("ip\tresource_type\tname\tregion\tarn");
+    for m in matches {
+        if m.resources.is_empty() {
+            println!("{}\tnot found\t\t\t", m.ip);
+        }
+        for r in &m.resources {
+            println!("{}\t{}\t{}\t{}\t{}", m.ip, r.resource_type, r.name, r.region, r.arn);
+        }
+    }
+}
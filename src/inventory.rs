@@ -1,14 +1,15 @@
 use anyhow::Result;
+use crate::audit::AuditLog;
+use crate::throttle::{is_throttling, ThrottleInterceptor};
+use aws_config::retry::RetryConfig;
+use aws_config::sts::AssumeRoleProvider;
 use aws_config::SdkConfig;
-use aws_sdk_ec2::Client as Ec2Client;
+use aws_credential_types::provider::{ProvideCredentials, SharedCredentialsProvider};
+use aws_sdk_ec2::types::InstanceStateName;
 use aws_sdk_eks::Client as EksClient;
-use aws_sdk_elasticloadbalancingv2::Client as ElbClient;
-use aws_sdk_rds::Client as RdsClient;
-use aws_sdk_dynamodb::Client as DynamoDbClient;
-use aws_sdk_elasticache::Client as ElastiCacheClient;
-use aws_sdk_route53::Client as Route53Client;
 use aws_sdk_route53::types::TagResourceType as Route53ResourceType;
-use k8s_openapi::api::core::v1::Pod;
+use aws_sdk_cloudwatch::Client as CloudWatchClient;
+use k8s_openapi::api::core::v1::{Node, Pod, Service};
 use kube::{
     api::{Api, ListParams, ResourceExt},
     Client,
@@ -17,54 +18,739 @@ use kube::{
         NamedCluster, NamedContext,
     },
 };
+use secrecy::SecretString;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::net::IpAddr;
+use std::future::Future;
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn, Level};
 
 /// A standardized representation of a resource to be stored.
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct CollectedResource {
-    pub arn: String,
+    /// Stable unique key for the resource: its ARN where the service has one,
+    /// otherwise an id such as an EC2 instance id or an EKS pod path.
+    pub id: String,
+    /// The real ARN, when it differs from `id` or `id` is not one. Left as
+    /// `None` when `id` is already an ARN; see `arn()`.
+    pub arn: Option<String>,
     pub name: String,
     pub resource_type: String,
     pub region: String,
     pub ips: Vec<IpAddr>,
+    /// Optional per-address metadata for entries in `ips`.
+    pub ip_details: HashMap<IpAddr, IpDetail>,
     pub tags: HashMap<String, String>,
     pub details: Value,
+    /// The SDK object the resource was built from, when `--store-raw` is set.
+    pub raw_response: Option<String>,
+}
+
+impl CollectedResource {
+    /// The resource's real ARN: `arn` if set, else `id` when it is an ARN.
+    pub fn arn(&self) -> Option<&str> {
+        match &self.arn {
+            Some(arn) => Some(arn),
+            None if is_arn(&self.id) => Some(&self.id),
+            None => None,
+        }
+    }
+}
+
+/// Problems `--validate-arns` reports for `resources`: each explicit `arn`
+/// that does not parse as one (a collector bug), then a count per resource
+/// type of those with no real ARN at all.
+pub fn arn_problems(resources: &[CollectedResource]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut without_arn: BTreeMap<&str, usize> = BTreeMap::new();
+    for resource in resources {
+        match (&resource.arn, resource.arn()) {
+            (Some(arn), _) if !is_arn(arn) => {
+                problems.push(format!("{} {} has an invalid ARN '{}'", resource.resource_type, resource.id, arn));
+            }
+            (_, None) => *without_arn.entry(resource.resource_type.as_str()).or_default() += 1,
+            _ => {}
+        }
+    }
+    for (resource_type, count) in without_arn {
+        problems.push(format!("{} {} resource(s) have no ARN, only an id", count, resource_type));
+    }
+    problems
+}
+
+/// Whether `s` has the shape `arn:partition:service:region:account:resource`.
+pub fn is_arn(s: &str) -> bool {
+    let parts: Vec<&str> = s.splitn(6, ':').collect();
+    parts.len() == 6 && parts[0] == "arn" && !parts[1].is_empty() && !parts[2].is_empty() && !parts[5].is_empty()
+}
+
+/// Where an IP address is attached, for collectors whose APIs report it.
+#[derive(Debug, Clone, Default)]
+pub struct IpDetail {
+    pub eni_id: Option<String>,
+    pub description: Option<String>,
 }
 
 #[async_trait::async_trait]
 pub trait AwsResourceCollector {
-    async fn collect(&self, profile: &str, regions: &[String]) -> Result<Vec<CollectedResource>>;
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>>;
+
+    /// Every resource type `collect` can return, main type first. A scan that
+    /// ran the collector covers all of them, even those it found none of.
+    fn resource_types(&self) -> &'static [&'static str];
+
+    /// Approximates how many API calls `collect` would make, using only cheap
+    /// listing calls. The default assumes a single paginated describe per region.
+    async fn estimate_calls(&self, _ctx: &ScanContext, regions: &[String]) -> Result<usize> {
+        Ok(regions.len())
+    }
+
+    /// The IAM actions `collect` depends on, verified by `--check-permissions`.
+    fn required_actions(&self) -> &'static [&'static str] {
+        &[]
+    }
+}
+
+/// Regions scanned at once per collector unless `--region-concurrency` says otherwise.
+pub const DEFAULT_REGION_CONCURRENCY: usize = 4;
+
+/// Per-resource describe calls in flight at once per region unless
+/// `--describe-concurrency` says otherwise.
+pub const DEFAULT_DESCRIBE_CONCURRENCY: usize = 8;
+
+/// Settings shared by every collector for the duration of a scan.
+#[derive(Debug, Clone, Default)]
+pub struct ScanContext {
+    /// The AWS profile to load credentials from. Empty uses the default chain.
+    pub profile: String,
+    /// Stop after this many resources per region, for quick smoke tests.
+    pub sample: Option<usize>,
+    /// Credentials to use instead of the profile's own, e.g. from an assumed role.
+    pub credentials: Option<SharedCredentialsProvider>,
+    /// Records every AWS API operation when `--json-logs-to` is set.
+    pub audit_log: Option<Arc<AuditLog>>,
+    /// The account being scanned, once `caller_account_id` has resolved it.
+    pub account_id: Option<String>,
+    /// Free-form label saved with every resource, from `--source-label`.
+    pub source_label: Option<String>,
+    /// Throttling responses to any AWS call, counted by the `ThrottleInterceptor`
+    /// on every client and shared across targets so the run summary can report them.
+    pub throttle_events: Arc<AtomicUsize>,
+    /// The cost-allocation tag to attribute Cost Explorer costs by, when
+    /// `--with-costs` is set.
+    pub cost_tag: Option<String>,
+    /// Keep each resource's SDK response in `raw_responses`, from `--store-raw`.
+    pub store_raw: bool,
+    /// Merge `details` into an existing row for the same ARN instead of
+    /// replacing it, from `--enrich`.
+    pub merge_details: bool,
+    /// Report resources without a valid ARN before saving, from `--validate-arns`.
+    pub validate_arns: bool,
+    /// How many regions each collector scans at once, from `--region-concurrency`.
+    pub region_concurrency: usize,
+    /// How many per-resource describe calls (e.g. DynamoDB `DescribeTable`,
+    /// load balancer attributes) a collector makes at once in each region,
+    /// from `--describe-concurrency`.
+    pub describe_concurrency: usize,
+    /// Retries per AWS call, with exponential backoff and jitter, on throttling
+    /// and transient errors, from `--max-retries`. `None` keeps the SDK default.
+    pub max_retries: Option<u32>,
+    /// Lookups that failed without failing the collector, such as one zone's
+    /// records, so some of its resources may be missing from the results.
+    pub partial_failures: Arc<Mutex<Vec<String>>>,
+}
+
+impl ScanContext {
+    pub fn new(profile: &str) -> Self {
+        Self {
+            profile: profile.to_string(),
+            region_concurrency: DEFAULT_REGION_CONCURRENCY,
+            describe_concurrency: DEFAULT_DESCRIBE_CONCURRENCY,
+            ..Default::default()
+        }
+    }
+
+    /// The pretty-printed `response` for `CollectedResource::raw_response`, or
+    /// `None` unless `--store-raw` is set. SDK types implement `Debug` but not
+    /// `Serialize`, so this is the debug representation rather than JSON.
+    pub fn raw_response(&self, response: &impl std::fmt::Debug) -> Option<String> {
+        self.store_raw.then(|| format!("{:#?}", response))
+    }
+
+    /// The owner to scope ids of ARN-less resources by: the account, or the
+    /// profile when the account is unknown, or nothing for the default chain.
+    pub fn id_scope(&self) -> Option<&str> {
+        self.account_id.as_deref().or_else(|| Some(self.profile.as_str()).filter(|p| !p.is_empty()))
+    }
+
+    /// Whether `count` resources are enough to satisfy `--sample`.
+    pub fn sample_reached(&self, count: usize) -> bool {
+        self.sample.is_some_and(|n| count >= n)
+    }
+
+    /// Records that a lookup failed and the collector's results are incomplete,
+    /// so that the scan does not prune what it could not see.
+    pub fn record_partial_failure(&self, message: &str) {
+        self.partial_failures.lock().unwrap().push(message.to_string());
+    }
+}
+
+/// Builds a service client from `config`, attaching the throttling counter and,
+/// when `--json-logs-to` is enabled, the API audit interceptor, so every
+/// collector is covered.
+macro_rules! new_client {
+    ($sdk:ident, $ctx:expr, $config:expr) => {{
+        let config: &SdkConfig = $config;
+        let mut builder = $sdk::config::Builder::from(config).interceptor(ThrottleInterceptor::new(Arc::clone(&$ctx.throttle_events)));
+        if let Some(audit_log) = &$ctx.audit_log {
+            builder = builder.interceptor(audit_log.interceptor(&$ctx.profile, config.region()));
+        }
+        $sdk::Client::from_conf(builder.build())
+    }};
+}
+
+/// A copy of `client` that makes a single attempt per call, for calls paced by
+/// an `AdaptiveDelay`, which retries throttled calls itself.
+macro_rules! single_attempt_client {
+    ($sdk:ident, $client:expr) => {
+        $sdk::Client::from_conf($client.config().to_builder().retry_config(RetryConfig::disabled()).build())
+    };
+}
+
+/// `info!` into a `RegionLog`, for collectors scanning regions concurrently.
+macro_rules! progress {
+    ($log:expr, $($arg:tt)*) => {
+        $log.lines.push((Level::INFO, format!($($arg)*)))
+    };
+}
+
+/// `warn!` into a `RegionLog`.
+macro_rules! warning {
+    ($log:expr, $($arg:tt)*) => {
+        $log.lines.push((Level::WARN, format!($($arg)*)))
+    };
+}
+
+/// `warning!` for a failed lookup that leaves the results incomplete; see
+/// `ScanContext::record_partial_failure`. Use it when the failure drops
+/// resources or changes their id; a lookup that only fills in tags or details
+/// just logs a `warning!`, since the resource is still saved under its id.
+macro_rules! partial_failure {
+    ($ctx:expr, $log:expr, $($arg:tt)*) => {{
+        let message = format!($($arg)*);
+        $ctx.record_partial_failure(&message);
+        $log.lines.push((Level::WARN, message))
+    }};
+}
+
+/// Output of one region's scan, held back until the region completes so that
+/// regions scanned concurrently don't interleave their progress lines.
+#[derive(Default)]
+struct RegionLog {
+    /// Each line and the level it is logged at.
+    lines: Vec<(Level, String)>,
+}
+
+impl RegionLog {
+    fn print(&self) {
+        for (level, line) in &self.lines {
+            if *level == Level::WARN {
+                warn!("{}", line);
+            } else {
+                info!("{}", line);
+            }
+        }
+    }
+}
+
+/// Runs `scan` for every region, `ctx.region_concurrency` at a time, printing
+/// each region's log as it completes. As with a sequential loop, the first
+/// region to fail fails the collector.
+async fn scan_regions<'a, F, Fut>(ctx: &ScanContext, regions: &'a [String], scan: F) -> Result<Vec<CollectedResource>>
+where
+    F: FnMut(&'a String) -> Fut,
+    Fut: Future<Output = Result<(Vec<CollectedResource>, RegionLog)>>,
+{
+    use futures::StreamExt;
+
+    let mut scans = futures::stream::iter(regions).map(scan).buffer_unordered(ctx.region_concurrency.max(1));
+    let mut all_resources = Vec::new();
+    while let Some(result) = scans.next().await {
+        let (resources, log) = result?;
+        log.print();
+        all_resources.extend(resources);
+    }
+    Ok(all_resources)
 }
 
-async fn create_config(profile: &str, region: &str) -> SdkConfig {
+async fn create_config(ctx: &ScanContext, region: &str) -> SdkConfig {
     let region_obj = aws_config::Region::new(region.to_string());
     let mut config_builder =
         aws_config::defaults(aws_config::BehaviorVersion::latest()).region(region_obj);
-    if !profile.is_empty() {
-        config_builder = config_builder.profile_name(profile);
+    if !ctx.profile.is_empty() {
+        config_builder = config_builder.profile_name(&ctx.profile);
+    }
+    if let Some(credentials) = &ctx.credentials {
+        config_builder = config_builder.credentials_provider(credentials.clone());
+    }
+    if let Some(max_retries) = ctx.max_retries {
+        config_builder = config_builder.retry_config(RetryConfig::standard().with_max_attempts(max_retries.saturating_add(1)));
     }
     config_builder.load().await
 }
 
-pub struct Ec2Collector;
+const ROLE_SESSION_NAME: &str = "aws-inventory-sdk";
+
+/// Assumes each role in `role_chain` in turn, using the credentials obtained from
+/// the previous hop (starting from the profile's own), and returns the final
+/// credentials. `external_id`, if given, is passed when assuming the last role,
+/// which is the one in the scanned account. Every hop is assumed up front so a
+/// broken chain fails before any collection starts.
+pub async fn assume_role_chain(
+    profile: &str,
+    role_chain: &[String],
+    external_id: Option<&str>,
+) -> Result<SharedCredentialsProvider> {
+    let base_config = create_config(&ScanContext::new(profile), "us-east-1").await;
+    let mut credentials = base_config
+        .credentials_provider()
+        .ok_or_else(|| anyhow::anyhow!("No source credentials found for the assume-role chain"))?;
+
+    for (hop, role_arn) in role_chain.iter().enumerate() {
+        if !role_arn.starts_with("arn:") || !role_arn.contains(":role/") {
+            anyhow::bail!("'{}' (hop {} of the assume-role chain) is not an IAM role ARN", role_arn, hop + 1);
+        }
+
+        let mut builder = AssumeRoleProvider::builder(role_arn)
+            .session_name(ROLE_SESSION_NAME)
+            .configure(&base_config);
+        if let Some(external_id) = external_id.filter(|_| hop + 1 == role_chain.len()) {
+            builder = builder.external_id(external_id);
+        }
+        let provider = builder.build_from_provider(credentials).await;
+        provider.provide_credentials().await.map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to assume role '{}' (hop {} of {}): {}",
+                role_arn,
+                hop + 1,
+                role_chain.len(),
+                e
+            )
+        })?;
+        credentials = SharedCredentialsProvider::new(provider);
+    }
+
+    Ok(credentials)
+}
+
+/// Looks up the account that the context's credentials belong to, so resources
+/// can be grouped by account. A failed lookup is reported and yields `None`
+/// rather than aborting the scan.
+pub async fn caller_account_id(ctx: &ScanContext) -> Option<String> {
+    let config = create_config(ctx, "us-east-1").await;
+    let client = new_client!(aws_sdk_sts, ctx, &config);
+    match client.get_caller_identity().send().await {
+        Ok(identity) => identity.account,
+        Err(e) => {
+            warn!("could not determine the AWS account, resources will be saved without one: {}", e);
+            None
+        }
+    }
+}
+
+/// How long a `--sort-regions-by-latency` probe may take before the region is
+/// treated as unreachable.
+const LATENCY_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Reorders `regions` fastest first, timing one `DescribeAvailabilityZones`
+/// call to each, all probed at once. Regions whose probe fails or times out
+/// keep their given order after the rest; if every probe fails, `regions` is
+/// returned unchanged.
+pub async fn sort_regions_by_latency(ctx: &ScanContext, regions: Vec<String>) -> Vec<String> {
+    let probes = regions.iter().map(|region| async move {
+        let config = create_config(ctx, region).await;
+        let client = new_client!(aws_sdk_ec2, ctx, &config);
+        let started = Instant::now();
+        match tokio::time::timeout(LATENCY_PROBE_TIMEOUT, client.describe_availability_zones().send()).await {
+            Ok(Ok(_)) => Some(started.elapsed()),
+            Ok(Err(e)) => {
+                warn!("latency probe to {} failed: {}", region, e);
+                None
+            }
+            Err(_) => {
+                warn!("latency probe to {} timed out.", region);
+                None
+            }
+        }
+    });
+    let latencies = futures::future::join_all(probes).await;
+    if latencies.iter().all(Option::is_none) {
+        return regions;
+    }
+
+    let mut timed: Vec<(Option<Duration>, String)> = latencies.into_iter().zip(regions).collect();
+    // A stable sort keeps the given order among the failed probes.
+    timed.sort_by_key(|(latency, _)| (latency.is_none(), *latency));
+    for (latency, region) in &timed {
+        if let Some(latency) = latency {
+            info!("  {}: {} ms", region, latency.as_millis());
+        }
+    }
+    timed.into_iter().map(|(_, region)| region).collect()
+}
+
+/// Maps the caller ARN returned by STS to a principal that IAM can simulate.
+/// Assumed-role sessions become their role; roles with a path are not
+/// recoverable from the session ARN and will fail the simulation.
+fn simulation_principal_arn(caller_arn: &str) -> String {
+    match caller_arn.split_once(":assumed-role/") {
+        Some((prefix, rest)) => {
+            let role_name = rest.split('/').next().unwrap_or(rest);
+            format!("{}:role/{}", prefix.replacen(":sts:", ":iam:", 1), role_name)
+        }
+        None => caller_arn.to_string(),
+    }
+}
+
+/// Asks IAM whether the context's principal may call each of `actions`,
+/// returning the action names that would be denied. This needs
+/// `iam:SimulatePrincipalPolicy` and ignores resource-level conditions, so it
+/// is a good indicator rather than a guarantee.
+pub async fn denied_actions(ctx: &ScanContext, actions: &[&str]) -> Result<Vec<String>> {
+    let config = create_config(ctx, "us-east-1").await;
+    let sts = new_client!(aws_sdk_sts, ctx, &config);
+    let identity = sts.get_caller_identity().send().await?;
+    let caller_arn = identity
+        .arn
+        .ok_or_else(|| anyhow::anyhow!("STS did not return the caller's ARN"))?;
+    let principal_arn = simulation_principal_arn(&caller_arn);
+
+    let iam = new_client!(aws_sdk_iam, ctx, &config);
+    let output = iam
+        .simulate_principal_policy()
+        .policy_source_arn(&principal_arn)
+        .set_action_names(Some(actions.iter().map(|a| a.to_string()).collect()))
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to simulate the policies of {}: {}", principal_arn, e))?;
+
+    let mut denied: Vec<String> = output
+        .evaluation_results
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|r| r.eval_decision().as_str() != "allowed")
+        .map(|r| r.eval_action_name().to_string())
+        .collect();
+    denied.sort();
+    Ok(denied)
+}
+
+/// Generates an EKS bearer token in-process, the same way `aws eks get-token`
+/// does: a presigned STS `GetCallerIdentity` URL that also signs the
+/// `x-k8s-aws-id` header naming the cluster, base64url-encoded behind a
+/// `k8s-aws-v1.` prefix. This uses the scan's own credentials, including any
+/// assumed role, and needs no AWS CLI.
+async fn eks_token(config: &SdkConfig, cluster_name: &str, region: &str) -> Result<String> {
+    use aws_sigv4::http_request::{sign, SignableBody, SignableRequest, SignatureLocation, SigningSettings};
+    use aws_sigv4::sign::v4;
+    use base64::Engine;
+    use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+    // RFC 3986 unreserved characters stay as they are.
+    const QUERY_VALUE: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+    let credentials = config
+        .credentials_provider()
+        .ok_or_else(|| anyhow::anyhow!("No credentials available"))?
+        .provide_credentials()
+        .await?;
+    let identity: aws_smithy_runtime_api::client::identity::Identity = credentials.into();
+
+    let mut settings = SigningSettings::default();
+    settings.signature_location = SignatureLocation::QueryParams;
+    settings.expires_in = Some(std::time::Duration::from_secs(60));
+    let params: aws_sigv4::http_request::SigningParams = v4::SigningParams::builder()
+        .identity(&identity)
+        .region(region)
+        .name("sts")
+        .time(std::time::SystemTime::now())
+        .settings(settings)
+        .build()?
+        .into();
+
+    let mut url = format!(
+        "https://sts.{}.amazonaws.com/?Action=GetCallerIdentity&Version=2011-06-15",
+        region
+    );
+    let request = SignableRequest::new(
+        "GET",
+        &url,
+        std::iter::once(("x-k8s-aws-id", cluster_name)),
+        SignableBody::Bytes(&[]),
+    )?;
+    let (instructions, _signature) = sign(request, &params)?.into_parts();
+    for (name, value) in instructions.params() {
+        url.push_str(&format!("&{}={}", name, utf8_percent_encode(value, QUERY_VALUE)));
+    }
+
+    Ok(format!(
+        "k8s-aws-v1.{}",
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(url)
+    ))
+}
+
+/// Paces a loop of control-plane calls: the delay between calls grows when the
+/// API throttles and decays again as calls succeed, so the loop settles just
+/// below the account's rate limit instead of failing on it. Calls running
+/// concurrently share one pacer, so throttling seen by any of them slows all.
+/// Paced calls go through a `single_attempt_client!`, so the pacer sees every
+/// throttling response rather than only those the SDK gave up retrying.
+struct AdaptiveDelay {
+    delay: Mutex<Duration>,
+}
+
+impl AdaptiveDelay {
+    const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+    const MAX_DELAY: Duration = Duration::from_secs(5);
+    /// Attempts per call before a throttling error is returned to the caller.
+    const MAX_ATTEMPTS: usize = 6;
+
+    fn new() -> Self {
+        Self { delay: Mutex::new(Duration::ZERO) }
+    }
+
+    fn delay(&self) -> Duration {
+        *self.delay.lock().unwrap()
+    }
+
+    fn update_delay(&self, update: impl FnOnce(Duration) -> Duration) {
+        let mut delay = self.delay.lock().unwrap();
+        *delay = update(*delay);
+    }
+
+    /// Runs `call` after the current delay, retrying it while it is throttled.
+    /// The client's `ThrottleInterceptor` counts each throttling response.
+    async fn call<T, E, F, Fut>(&self, mut call: F) -> std::result::Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = std::result::Result<T, E>>,
+        E: aws_smithy_types::error::metadata::ProvideErrorMetadata,
+    {
+        let mut attempt = 1;
+        loop {
+            let delay = self.delay();
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            match call().await {
+                Ok(output) => {
+                    // Speed back up gradually; drop to no delay once it is negligible.
+                    self.update_delay(|delay| {
+                        let delay = delay.mul_f64(0.8);
+                        if delay < Duration::from_millis(10) {
+                            Duration::ZERO
+                        } else {
+                            delay
+                        }
+                    });
+                    return Ok(output);
+                }
+                Err(e) if is_throttling(e.code()) && attempt < Self::MAX_ATTEMPTS => {
+                    self.update_delay(|delay| (delay * 2).max(Self::INITIAL_BACKOFF).min(Self::MAX_DELAY));
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Formats an SDK timestamp for the `created_at` field that every collector
+/// puts in `details` when the API exposes a creation time. Seconds precision
+/// in UTC keeps the values comparable as strings.
+fn created_at(time: &aws_smithy_types::DateTime) -> Option<String> {
+    chrono::DateTime::from_timestamp(time.secs(), 0).map(|t| t.format(CREATED_AT_FORMAT).to_string())
+}
+
+/// The format of `details.created_at`.
+pub const CREATED_AT_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// Fetches last calendar month's unblended cost per value of the `tag_key`
+/// cost-allocation tag from Cost Explorer. The tag must be activated for cost
+/// allocation in the billing console, and each call is billed by AWS.
+pub async fn monthly_costs_by_tag(ctx: &ScanContext, tag_key: &str) -> Result<HashMap<String, f64>> {
+    use aws_sdk_costexplorer::types::{DateInterval, Granularity, GroupDefinition, GroupDefinitionType};
+    use chrono::Datelike;
+
+    let this_month = chrono::Utc::now().date_naive().with_day(1).unwrap();
+    let last_month = (this_month - chrono::Duration::days(1)).with_day(1).unwrap();
+    let period = DateInterval::builder()
+        .start(last_month.format("%Y-%m-%d").to_string())
+        .end(this_month.format("%Y-%m-%d").to_string())
+        .build()?;
+
+    // Cost Explorer is served from us-east-1 only.
+    let config = create_config(ctx, "us-east-1").await;
+    let client = new_client!(aws_sdk_costexplorer, ctx, &config);
+
+    let group_prefix = format!("{}$", tag_key);
+    let mut costs = HashMap::new();
+    let mut next_page_token = None;
+    loop {
+        let output = client
+            .get_cost_and_usage()
+            .time_period(period.clone())
+            .granularity(Granularity::Monthly)
+            .metrics("UnblendedCost")
+            .group_by(GroupDefinition::builder().r#type(GroupDefinitionType::Tag).key(tag_key).build())
+            .set_next_page_token(next_page_token)
+            .send()
+            .await?;
+
+        for result in output.results_by_time.unwrap_or_default() {
+            for group in result.groups.unwrap_or_default() {
+                let Some(value) = group
+                    .keys
+                    .unwrap_or_default()
+                    .into_iter()
+                    .next()
+                    .and_then(|k| k.strip_prefix(&group_prefix).map(|v| v.to_string()))
+                else {
+                    continue;
+                };
+                // An empty value is the bucket of untagged spend.
+                if value.is_empty() {
+                    continue;
+                }
+                let amount = group
+                    .metrics
+                    .as_ref()
+                    .and_then(|m| m.get("UnblendedCost"))
+                    .and_then(|m| m.amount.as_deref())
+                    .and_then(|a| a.parse::<f64>().ok())
+                    .unwrap_or(0.0);
+                *costs.entry(value).or_insert(0.0) += amount;
+            }
+        }
+
+        next_page_token = output.next_page_token;
+        if next_page_token.is_none() {
+            break;
+        }
+    }
+    Ok(costs)
+}
+
+/// Stores an estimated `monthly_cost` in the details of every resource whose
+/// `tag_key` tag has a cost. A tag value shared by several resources has its
+/// cost split evenly between them, so the figure is an estimate.
+pub fn apply_costs(resources: &mut [CollectedResource], tag_key: &str, costs: &HashMap<String, f64>) {
+    let mut sharers: HashMap<&str, usize> = HashMap::new();
+    for resource in resources.iter() {
+        if let Some(value) = resource.tags.get(tag_key) {
+            *sharers.entry(value.as_str()).or_insert(0) += 1;
+        }
+    }
+    let shares: HashMap<String, f64> = sharers
+        .into_iter()
+        .filter_map(|(value, count)| costs.get(value).map(|cost| (value.to_string(), cost / count as f64)))
+        .collect();
+
+    for resource in resources.iter_mut() {
+        let Some(share) = resource.tags.get(tag_key).and_then(|v| shares.get(v)) else { continue };
+        if let Some(details) = resource.details.as_object_mut() {
+            details.insert("monthly_cost".to_string(), Value::from((share * 100.0).round() / 100.0));
+            details.insert("cost_tag".to_string(), Value::from(tag_key));
+        }
+    }
+}
+
+/// Resolves an endpoint hostname to its current IP addresses. Failures are
+/// logged to the region's `log` and yield no addresses, so a stale DNS name
+/// never aborts a scan.
+async fn resolve_endpoint(hostname: &str, log: &mut RegionLog) -> Vec<IpAddr> {
+    match tokio::net::lookup_host((hostname, 0)).await {
+        Ok(addrs) => {
+            let mut ips: Vec<IpAddr> = addrs.map(|a| a.ip()).collect();
+            ips.sort();
+            ips.dedup();
+            ips
+        }
+        Err(e) => {
+            warning!(log, "could not resolve endpoint {}: {}", hostname, e);
+            vec![]
+        }
+    }
+}
+
+pub struct Ec2Collector {
+    include_terminated: bool,
+}
+
+impl Ec2Collector {
+    pub fn new(include_terminated: bool) -> Self {
+        Self { include_terminated }
+    }
+}
 
 #[async_trait::async_trait]
 impl AwsResourceCollector for Ec2Collector {
-    async fn collect(&self, profile: &str, regions: &[String]) -> Result<Vec<CollectedResource>> {
-        let mut all_resources = Vec::new();
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["ec2:instance"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["ec2:DescribeInstances", "ec2:DescribeAddresses"]
+    }
+
+    async fn estimate_calls(&self, _ctx: &ScanContext, regions: &[String]) -> Result<usize> {
+        // One describe_addresses call plus at least one describe_instances page per region.
+        Ok(regions.len() * 2)
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching EC2 instances from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_ec2, ctx, &config);
+
+            // Elastic IPs allocated from a BYOIP pool name that pool instead of "amazon".
+            let byoip_addresses: HashSet<String> = match client.describe_addresses().send().await {
+                Ok(output) => output
+                    .addresses
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|a| a.public_ipv4_pool.as_deref().is_some_and(|pool| pool != "amazon"))
+                    .filter_map(|a| a.public_ip)
+                    .collect(),
+                Err(e) => {
+                    warning!(log, "could not describe Elastic IPs in {}, BYOIP addresses will be reported as amazon: {}", region, e);
+                    HashSet::new()
+                }
+            };
 
-        for region in regions {
-            println!("Fetching EC2 instances from {}...", region);
-            let config = create_config(profile, region).await;
-            let client = Ec2Client::new(&config);
             let mut stream = client.describe_instances().into_paginator().send();
 
             let mut count = 0;
-            while let Some(result) = stream.next().await {
+            let mut skipped = 0;
+            'pages: while let Some(result) = stream.next().await {
                 for reservation in result?.reservations.unwrap_or_default() {
                     for instance in reservation.instances.unwrap_or_default() {
+                        // Dead instances keep showing up for a while with their network
+                        // fields cleared; they shouldn't match `identify`.
+                        let state = instance.state.as_ref().and_then(|s| s.name.clone());
+                        if !self.include_terminated
+                            && matches!(state, Some(InstanceStateName::Terminated | InstanceStateName::ShuttingDown))
+                        {
+                            skipped += 1;
+                            continue;
+                        }
+                        let raw_response = ctx.raw_response(&instance);
                         let mut ips = Vec::new();
                         if let Some(ip_str) = &instance.private_ip_address {
                             if let Ok(ip) = ip_str.parse() {
@@ -84,510 +770,4080 @@ impl AwsResourceCollector for Ec2Collector {
                                 .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
                                 .collect();
 
-                        let name = tags.get("Name").cloned().unwrap_or_else(|| instance.instance_id.clone().unwrap_or_default());
+                        let instance_id = instance.instance_id.clone().unwrap_or_default();
+                        let name = tags.get("Name").cloned().unwrap_or_else(|| instance_id.clone());
+
+                        let security_groups: Vec<String> = instance
+                                .security_groups
+                                .unwrap_or_default()
+                                .into_iter()
+                                .filter_map(|g| g.group_id)
+                                .collect();
+
+                        // Collect every address on every network interface, attributed to the
+                        // interface that carries it: secondary private IPs and the addresses
+                        // of additional ENIs, plus public and carrier (Wavelength) addresses,
+                        // which needn't be the instance's primary public IP.
+                        let mut ip_details = HashMap::new();
+                        let mut ip_sources = serde_json::Map::new();
+                        for eni in instance.network_interfaces.unwrap_or_default() {
+                            let detail = IpDetail {
+                                eni_id: eni.network_interface_id.clone(),
+                                description: eni.description.clone().filter(|d| !d.is_empty()),
+                            };
+                            for private_ip in eni.private_ip_addresses.unwrap_or_default() {
+                                if let Some(ip) = private_ip.private_ip_address.as_deref().and_then(|s| s.parse::<IpAddr>().ok()) {
+                                    if !ips.contains(&ip) {
+                                        ips.push(ip);
+                                    }
+                                    ip_details.insert(ip, detail.clone());
+                                }
+
+                                let association = match private_ip.association {
+                                    Some(association) => association,
+                                    None => continue,
+                                };
+                                let public_ip = association.public_ip.map(|ip| {
+                                    let source = if byoip_addresses.contains(&ip) { "byoip" } else { "amazon" };
+                                    (ip, source)
+                                });
+                                let carrier_ip = association.carrier_ip.map(|ip| (ip, "carrier"));
+                                for (ip_str, source) in public_ip.into_iter().chain(carrier_ip) {
+                                    if let Ok(ip) = ip_str.parse::<IpAddr>() {
+                                        if !ips.contains(&ip) {
+                                            ips.push(ip);
+                                        }
+                                        ip_details.insert(ip, detail.clone());
+                                        ip_sources.insert(ip_str, Value::from(source));
+                                    }
+                                }
+                            }
+                        }
+
+                        let placement = instance.placement.as_ref();
 
                         all_resources.push(CollectedResource {
-                            arn: instance.instance_id.clone().unwrap_or_default(), // Note: This is not a real ARN, but it's unique.
+                            id: instance_id.clone(),
+                            // Instance ARNs need the account, which may be unknown.
+                            arn: ctx
+                                .account_id
+                                .as_ref()
+                                .map(|account_id| format!("arn:aws:ec2:{}:{}:instance/{}", region, account_id, instance_id)),
                             name,
                             resource_type: "ec2:instance".to_string(),
                             region: region.to_string(),
                             ips,
+                            ip_details,
                             tags,
-                            details: serde_json::json!({ "instance_type": instance.instance_type.map(|t| t.as_str().to_string()) }),
+                            details: serde_json::json!({
+                                "instance_id": instance_id,
+                                "instance_type": instance.instance_type.map(|t| t.as_str().to_string()),
+                                "state": state.as_ref().map(|s| s.as_str().to_string()),
+                                "security_groups": security_groups,
+                                "ip_sources": ip_sources,
+                                "created_at": instance.launch_time.as_ref().and_then(created_at),
+                                "tenancy": placement.and_then(|p| p.tenancy.as_ref()).map(|t| t.as_str().to_string()),
+                                "host_id": placement.and_then(|p| p.host_id.clone()),
+                                "placement_group": placement.and_then(|p| p.group_name.clone()).filter(|g| !g.is_empty()),
+                                "partition_number": placement.and_then(|p| p.partition_number),
+                            }),
+                            raw_response,
                         });
                         count += 1;
+                        if ctx.sample_reached(count) {
+                            break 'pages;
+                        }
                     }
                 }
             }
-            println!("  -> Found {} instances in {}.", count, region);
-        }
-        Ok(all_resources)
+            if skipped > 0 {
+                progress!(log, "  -> Found {} instances in {} (skipped {} terminated or shutting down).", count, region, skipped);
+            } else {
+                progress!(log, "  -> Found {} instances in {}.", count, region);
+            }
+            Ok((all_resources, log))
+        })
+        .await
     }
 }
 
-pub struct Route53Collector;
-
-#[async_trait::async_trait]
-impl AwsResourceCollector for Route53Collector {
-    async fn collect(&self, profile: &str, _regions: &[String]) -> Result<Vec<CollectedResource>> {
-        // Route 53 is a global service, so we query it once, ignoring the regions list.
-        // We use "us-east-1" for the client, as is standard for global services.
-        println!("\nFetching Route 53 hosted zones (global service)...");
-        let config = create_config(profile, "us-east-1").await;
-        let client = Route53Client::new(&config);
-        let mut all_resources = Vec::new();
-        let mut zones_stream = client.list_hosted_zones().into_paginator().send();
-
-        let mut count = 0;
-        while let Some(result) = zones_stream.next().await {
-            for zone in result?.hosted_zones {
-                let zone_id = zone.id();
-                let resource_id = zone_id.split('/').last().unwrap_or_default();
-
-                let tags = if !resource_id.is_empty() {
-                    match client
-                        .list_tags_for_resource()
-                        .resource_type(Route53ResourceType::Hostedzone)
-                        .resource_id(resource_id)
-                        .send()
-                        .await
-                    {
-                        Ok(tags_output) => tags_output
-                            .resource_tag_set
-                            .and_then(|rts| rts.tags)
-                            .unwrap_or_default()
-                            .into_iter()
-                            .filter_map(|t| Some((t.key()?.to_string(), t.value()?.to_string())))
-                            .collect(),
-                        Err(e) => {
-                            eprintln!("Could not get tags for Route53 zone {}: {}", zone_id, e);
-                            HashMap::new()
-                        }
-                    }
-                } else {
-                    HashMap::new()
-                };
-
-                let (is_private, rr_count) = if let Some(ref config) = zone.config {
-                    (config.private_zone, zone.resource_record_set_count.unwrap_or(0))
-                } else {
-                    (false, 0)
-                };
-
-                all_resources.push(CollectedResource {
-                    arn: zone_id.to_string(),
-                    name: zone.name().to_string(),
-                    resource_type: "route53:hostedzone".to_string(),
-                    region: "global".to_string(),
-                    ips: vec![],
-                    tags,
-                    details: serde_json::json!({
-                        "private_zone": is_private,
-                        "resource_record_set_count": rr_count,
-                    }),
-                });
-                count += 1;
-            }
-        }
-        println!("  -> Found {} hosted zones.", count);
+/// Addresses AWS reserves in every subnet (network, router, DNS, future use, broadcast).
+const SUBNET_RESERVED_ADDRESSES: u64 = 5;
 
-        Ok(all_resources)
+/// Returns the number of addresses in an IPv4 CIDR block such as `10.0.1.0/24`.
+fn ipv4_cidr_size(cidr: &str) -> Option<u64> {
+    let prefix: u32 = cidr.split_once('/')?.1.parse().ok()?;
+    if prefix > 32 {
+        return None;
     }
+    Some(1u64 << (32 - prefix))
 }
 
-pub struct ElbCollector;
+pub struct VolumesCollector;
 
 #[async_trait::async_trait]
-impl AwsResourceCollector for ElbCollector {
-    async fn collect(&self, profile: &str, regions: &[String]) -> Result<Vec<CollectedResource>> {
-        let mut all_resources = Vec::new();
+impl AwsResourceCollector for VolumesCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["ec2:volume"]
+    }
 
-        for region in regions {
-            println!("Fetching Load Balancers from {}...", region);
-            let config = create_config(profile, region).await;
-            let client = ElbClient::new(&config);
-            let mut lbs_stream = client.describe_load_balancers().into_paginator().send();
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["ec2:DescribeVolumes"]
+    }
 
-            let mut region_lbs = vec![];
-            while let Some(result) = lbs_stream.next().await {
-                region_lbs.extend(result?.load_balancers.unwrap_or_default());
-            }
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching EBS volumes from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_ec2, ctx, &config);
+            let mut stream = client.describe_volumes().into_paginator().send();
 
-            if region_lbs.is_empty() {
-                println!("  -> Found 0 load balancers in {}.", region);
-                continue;
+            let mut count = 0;
+            'pages: while let Some(result) = stream.next().await {
+                for volume in result?.volumes.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&volume);
+                    let volume_id = volume.volume_id.clone().unwrap_or_default();
+                    // DescribeVolumes doesn't return an ARN, so build one when the account is known.
+                    let arn = ctx.account_id.as_ref().map(|account_id| format!("arn:aws:ec2:{}:{}:volume/{}", region, account_id, volume_id));
+                    let tags: HashMap<_, _> = volume
+                        .tags
+                        .clone()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
+                        .collect();
+                    let name = tags.get("Name").cloned().unwrap_or_else(|| volume_id.clone());
+                    let attachments = volume.attachments.as_deref().unwrap_or_default();
+
+                    all_resources.push(CollectedResource {
+                        id: volume_id.clone(),
+                        arn,
+                        name,
+                        resource_type: "ec2:volume".to_string(),
+                        region: region.to_string(),
+                        tags,
+                        details: serde_json::json!({
+                            "volume_id": volume_id,
+                            "size": volume.size,
+                            "volume_type": volume.volume_type.as_ref().map(|t| t.as_str().to_string()),
+                            "encrypted": volume.encrypted,
+                            "state": volume.state.as_ref().map(|s| s.as_str().to_string()),
+                            "availability_zone": volume.availability_zone,
+                            "iops": volume.iops,
+                            // Multi-Attach io1/io2 volumes can have several instances.
+                            "attached_instance_ids": attachments.iter().filter_map(|a| a.instance_id.clone()).collect::<Vec<_>>(),
+                            "attached_instance_id": attachments.first().and_then(|a| a.instance_id.clone()),
+                            "created_at": volume.create_time.as_ref().and_then(created_at),
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
+                }
             }
+            progress!(log, "  -> Found {} volumes in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
 
-            let mut tags_map: HashMap<String, HashMap<String, String>> = HashMap::new();
-            for lb_chunk in region_lbs.chunks(20) {
-                let arns: Vec<String> = lb_chunk
-                    .iter()
-                    .filter_map(|lb| lb.load_balancer_arn.clone())
-                    .collect();
-                if arns.is_empty() {
-                    continue;
+pub struct SecurityGroupsCollector;
+
+impl SecurityGroupsCollector {
+    /// One rule as stored in `details.ingress`/`details.egress`. A protocol of
+    /// `-1` means all traffic, in which case the port range is absent.
+    fn rule(permission: &aws_sdk_ec2::types::IpPermission) -> Value {
+        serde_json::json!({
+            "protocol": permission.ip_protocol,
+            "from_port": permission.from_port,
+            "to_port": permission.to_port,
+            "cidrs": permission.ip_ranges.as_deref().unwrap_or_default().iter().filter_map(|r| r.cidr_ip.clone()).collect::<Vec<_>>(),
+            "ipv6_cidrs": permission.ipv6_ranges.as_deref().unwrap_or_default().iter().filter_map(|r| r.cidr_ipv6.clone()).collect::<Vec<_>>(),
+            "security_groups": permission.user_id_group_pairs.as_deref().unwrap_or_default().iter().filter_map(|p| p.group_id.clone()).collect::<Vec<_>>(),
+            "prefix_lists": permission.prefix_list_ids.as_deref().unwrap_or_default().iter().filter_map(|p| p.prefix_list_id.clone()).collect::<Vec<_>>(),
+        })
+    }
+
+    /// `protocol:from-to` for each ingress rule open to the whole internet.
+    fn open_ingress(permissions: &[aws_sdk_ec2::types::IpPermission]) -> Vec<String> {
+        permissions
+            .iter()
+            .filter(|p| {
+                p.ip_ranges.as_deref().unwrap_or_default().iter().any(|r| r.cidr_ip.as_deref() == Some("0.0.0.0/0"))
+                    || p.ipv6_ranges.as_deref().unwrap_or_default().iter().any(|r| r.cidr_ipv6.as_deref() == Some("::/0"))
+            })
+            .map(|p| {
+                let protocol = p.ip_protocol.as_deref().unwrap_or("-1");
+                match (p.from_port, p.to_port) {
+                    (Some(from), Some(to)) if protocol != "-1" => format!("{}:{}-{}", protocol, from, to),
+                    _ => "all".to_string(),
                 }
+            })
+            .collect()
+    }
+}
 
-                let tags_output = client
-                    .describe_tags()
-                    .set_resource_arns(Some(arns))
-                    .send()
-                    .await?;
-                for tag_desc in tags_output.tag_descriptions.unwrap_or_default() {
-                    let arn = tag_desc.resource_arn.unwrap_or_default();
-                    let tags = tag_desc
+#[async_trait::async_trait]
+impl AwsResourceCollector for SecurityGroupsCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["ec2:security_group"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["ec2:DescribeSecurityGroups"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching security groups from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_ec2, ctx, &config);
+            let mut stream = client.describe_security_groups().into_paginator().send();
+
+            let mut count = 0;
+            'pages: while let Some(result) = stream.next().await {
+                for group in result?.security_groups.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&group);
+                    let group_id = group.group_id.clone().unwrap_or_default();
+                    let tags: HashMap<_, _> = group
                         .tags
+                        .clone()
                         .unwrap_or_default()
                         .into_iter()
                         .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
                         .collect();
-                    tags_map.insert(arn, tags);
+                    let name = tags
+                        .get("Name")
+                        .cloned()
+                        .or_else(|| group.group_name.clone())
+                        .unwrap_or_else(|| group_id.clone());
+                    let ingress = group.ip_permissions.as_deref().unwrap_or_default();
+                    let egress = group.ip_permissions_egress.as_deref().unwrap_or_default();
+
+                    all_resources.push(CollectedResource {
+                        id: group_id.clone(),
+                        arn: group.security_group_arn.clone(),
+                        name,
+                        resource_type: "ec2:security_group".to_string(),
+                        region: region.to_string(),
+                        tags,
+                        details: serde_json::json!({
+                            "group_name": group.group_name,
+                            "description": group.description,
+                            "vpc_id": group.vpc_id,
+                            "ingress": ingress.iter().map(Self::rule).collect::<Vec<_>>(),
+                            "egress": egress.iter().map(Self::rule).collect::<Vec<_>>(),
+                            "open_ingress": Self::open_ingress(ingress),
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
                 }
             }
+            progress!(log, "  -> Found {} security groups in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct NetworkAclCollector;
+
+impl NetworkAclCollector {
+    /// One entry as stored in `details.inbound`/`details.outbound`, in rule
+    /// number order. A protocol of `-1` means all traffic and has no port range.
+    fn entry(entry: &aws_sdk_ec2::types::NetworkAclEntry) -> Value {
+        serde_json::json!({
+            "rule_number": entry.rule_number,
+            "protocol": entry.protocol,
+            "from_port": entry.port_range.as_ref().and_then(|r| r.from),
+            "to_port": entry.port_range.as_ref().and_then(|r| r.to),
+            "cidr": entry.cidr_block.clone().or_else(|| entry.ipv6_cidr_block.clone()),
+            "action": entry.rule_action.as_ref().map(|a| a.as_str()),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for NetworkAclCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["ec2:network_acl"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["ec2:DescribeNetworkAcls"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching network ACLs from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_ec2, ctx, &config);
+            let mut stream = client.describe_network_acls().into_paginator().send();
 
             let mut count = 0;
-            for lb in region_lbs {
-                let arn = lb.load_balancer_arn.clone().unwrap_or_default();
-                let name = lb.load_balancer_name.clone().unwrap_or_default();
-                let tags = tags_map.get(&arn).cloned().unwrap_or_default();
+            'pages: while let Some(result) = stream.next().await {
+                for acl in result?.network_acls.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&acl);
+                    let acl_id = acl.network_acl_id.clone().unwrap_or_default();
+                    let tags: HashMap<_, _> = acl
+                        .tags
+                        .clone()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
+                        .collect();
+                    let name = tags.get("Name").cloned().unwrap_or_else(|| acl_id.clone());
+                    let mut entries = acl.entries.clone().unwrap_or_default();
+                    entries.sort_by_key(|e| e.rule_number);
+                    let (outbound, inbound): (Vec<_>, Vec<_>) = entries.iter().partition(|e| e.egress.unwrap_or(false));
+                    let subnet_ids: Vec<_> = acl
+                        .associations
+                        .as_deref()
+                        .unwrap_or_default()
+                        .iter()
+                        .filter_map(|a| a.subnet_id.clone())
+                        .collect();
 
-                let mut ips = vec![];
-                if let Some(azs) = lb.availability_zones {
-                    for az in azs {
-                        if let Some(addrs) = az.load_balancer_addresses {
-                            for addr in addrs {
-                                if let Some(ip_str) = addr.ip_address {
-                                    if let Ok(ip) = ip_str.parse() {
-                                        ips.push(ip);
-                                    }
-                                }
-                            }
-                        }
+                    all_resources.push(CollectedResource {
+                        arn: ctx.account_id.as_ref().map(|account_id| format!("arn:aws:ec2:{}:{}:network-acl/{}", region, account_id, acl_id)),
+                        id: acl_id,
+                        name,
+                        resource_type: "ec2:network_acl".to_string(),
+                        region: region.to_string(),
+                        tags,
+                        details: serde_json::json!({
+                            "vpc_id": acl.vpc_id,
+                            "is_default": acl.is_default,
+                            "subnet_ids": subnet_ids,
+                            "inbound": inbound.into_iter().map(Self::entry).collect::<Vec<_>>(),
+                            "outbound": outbound.into_iter().map(Self::entry).collect::<Vec<_>>(),
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
                     }
                 }
+            }
+            progress!(log, "  -> Found {} network ACLs in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+/// Elastic IPs, attached or not, so an address found during an incident can be
+/// traced to its allocation even when no instance currently reports it.
+pub struct ElasticIpCollector;
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for ElasticIpCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["ec2:eip"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["ec2:DescribeAddresses"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching Elastic IPs from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_ec2, ctx, &config);
+
+            // DescribeAddresses is not paginated; it returns every address at once.
+            let mut addresses = client.describe_addresses().send().await?.addresses.unwrap_or_default();
+            if let Some(n) = ctx.sample {
+                addresses.truncate(n);
+            }
+
+            let mut attached = 0;
+            for address in &addresses {
+                let raw_response = ctx.raw_response(address);
+                let public_ip = address.public_ip.clone().unwrap_or_default();
+                let allocation_id = address.allocation_id.clone().unwrap_or_else(|| public_ip.clone());
+                let tags: HashMap<_, _> = address
+                    .tags
+                    .clone()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
+                    .collect();
+                let name = tags.get("Name").cloned().unwrap_or_else(|| public_ip.clone());
+                let ips: Vec<IpAddr> = [&address.public_ip, &address.carrier_ip, &address.customer_owned_ip]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|ip| ip.parse().ok())
+                    .collect();
+                if address.association_id.is_some() {
+                    attached += 1;
+                }
 
                 all_resources.push(CollectedResource {
-                    arn,
+                    arn: ctx
+                        .account_id
+                        .as_ref()
+                        .map(|account_id| format!("arn:aws:ec2:{}:{}:elastic-ip/{}", region, account_id, allocation_id)),
+                    id: allocation_id,
                     name,
-                    resource_type: "elbv2:loadbalancer".to_string(),
+                    resource_type: "ec2:eip".to_string(),
                     region: region.to_string(),
                     ips,
                     tags,
                     details: serde_json::json!({
-                        "dns_name": lb.dns_name,
-                        "type": lb.r#type.map(|t| t.as_str().to_string()),
-                        "scheme": lb.scheme.map(|s| s.as_str().to_string()),
+                        "public_ip": address.public_ip,
+                        "allocation_id": address.allocation_id,
+                        "association_id": address.association_id,
+                        "instance_id": address.instance_id,
+                        "network_interface_id": address.network_interface_id,
+                        "network_interface_owner_id": address.network_interface_owner_id,
+                        "private_ip_address": address.private_ip_address,
+                        "public_ipv4_pool": address.public_ipv4_pool,
+                        "network_border_group": address.network_border_group,
+                        "domain": address.domain.as_ref().map(|d| d.as_str()),
+                        "attached": address.association_id.is_some(),
                     }),
+                    raw_response,
+                    ..Default::default()
                 });
-                count += 1;
             }
-            println!("  -> Found {} load balancers in {}.", count, region);
-        }
-
-        Ok(all_resources)
+            progress!(log, "  -> Found {} Elastic IPs in {} ({} attached).", addresses.len(), region, attached);
+            Ok((all_resources, log))
+        })
+        .await
     }
 }
 
-pub struct EksCollector {
-    clusters_to_scan: Vec<String>,
-}
+pub struct CapacityReservationCollector;
 
-impl EksCollector {
-    pub fn new(clusters_to_scan: Vec<String>) -> Self {
-        Self {
-            clusters_to_scan,
-        }
+#[async_trait::async_trait]
+impl AwsResourceCollector for CapacityReservationCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["ec2:capacity_reservation"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["ec2:DescribeCapacityReservations"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching capacity reservations from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_ec2, ctx, &config);
+            let mut stream = client.describe_capacity_reservations().into_paginator().send();
+
+            let mut count = 0;
+            'pages: while let Some(result) = stream.next().await {
+                for reservation in result?.capacity_reservations.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&reservation);
+                    let reservation_id = reservation.capacity_reservation_id.clone().unwrap_or_default();
+                    let tags: HashMap<_, _> = reservation
+                        .tags
+                        .clone()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
+                        .collect();
+                    let name = tags.get("Name").cloned().unwrap_or_else(|| reservation_id.clone());
+
+                    all_resources.push(CollectedResource {
+                        id: reservation_id,
+                        arn: reservation.capacity_reservation_arn.clone(),
+                        name,
+                        resource_type: "ec2:capacity_reservation".to_string(),
+                        region: region.to_string(),
+                        tags,
+                        details: serde_json::json!({
+                            "instance_type": reservation.instance_type,
+                            "instance_platform": reservation.instance_platform.as_ref().map(|p| p.as_str()),
+                            "availability_zone": reservation.availability_zone,
+                            "tenancy": reservation.tenancy.as_ref().map(|t| t.as_str()),
+                            "total_instance_count": reservation.total_instance_count,
+                            "available_instance_count": reservation.available_instance_count,
+                            "state": reservation.state.as_ref().map(|s| s.as_str()),
+                            "end_date": reservation.end_date.as_ref().and_then(created_at),
+                            "created_at": reservation.create_date.as_ref().and_then(created_at),
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} capacity reservations in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
     }
 }
 
+pub struct SpotRequestCollector;
+
 #[async_trait::async_trait]
-impl AwsResourceCollector for EksCollector {
-    async fn collect(&self, profile: &str, regions: &[String]) -> Result<Vec<CollectedResource>> {
-        let mut all_resources = Vec::new();
+impl AwsResourceCollector for SpotRequestCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["ec2:spot_request"]
+    }
 
-        for region in regions {
-            let config = create_config(profile, region).await;
-            let eks_client = EksClient::new(&config);
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["ec2:DescribeSpotInstanceRequests"]
+    }
 
-            let clusters_to_process = if self.clusters_to_scan.is_empty() {
-                println!("Discovering EKS clusters in {}...", region);
-                let mut cluster_stream = eks_client.list_clusters().into_paginator().send();
-                let mut discovered_clusters = Vec::new();
-                while let Some(result) = cluster_stream.next().await {
-                    discovered_clusters.extend(result?.clusters.unwrap_or_default());
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching spot instance requests from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_ec2, ctx, &config);
+            let mut stream = client.describe_spot_instance_requests().into_paginator().send();
+
+            let mut count = 0;
+            'pages: while let Some(result) = stream.next().await {
+                for request in result?.spot_instance_requests.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&request);
+                    let request_id = request.spot_instance_request_id.clone().unwrap_or_default();
+                    let tags: HashMap<_, _> = request
+                        .tags
+                        .clone()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
+                        .collect();
+                    let name = tags.get("Name").cloned().unwrap_or_else(|| request_id.clone());
+                    let launch_specification = request.launch_specification.as_ref();
+
+                    all_resources.push(CollectedResource {
+                        arn: ctx
+                            .account_id
+                            .as_ref()
+                            .map(|account_id| format!("arn:aws:ec2:{}:{}:spot-instances-request/{}", region, account_id, request_id)),
+                        id: request_id,
+                        name,
+                        resource_type: "ec2:spot_request".to_string(),
+                        region: region.to_string(),
+                        tags,
+                        details: serde_json::json!({
+                            "state": request.state.as_ref().map(|s| s.as_str()),
+                            "status_code": request.status.as_ref().and_then(|s| s.code.clone()),
+                            "status_message": request.status.as_ref().and_then(|s| s.message.clone()),
+                            "instance_id": request.instance_id,
+                            "request_type": request.r#type.as_ref().map(|t| t.as_str()),
+                            "instance_type": launch_specification.and_then(|l| l.instance_type.as_ref()).map(|t| t.as_str()),
+                            "spot_price": request.spot_price,
+                            "availability_zone": request.launched_availability_zone,
+                            "valid_until": request.valid_until.as_ref().and_then(created_at),
+                            "created_at": request.create_time.as_ref().and_then(created_at),
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
                 }
-                println!("  -> Found {} clusters in {}.", discovered_clusters.len(), region);
-                discovered_clusters
-            } else {
-                self.clusters_to_scan.clone()
-            };
+            }
+            progress!(log, "  -> Found {} spot instance requests in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
 
-            for cluster_name in &clusters_to_process {
-                println!("Connecting to EKS cluster '{}'...", cluster_name);
+pub struct SubnetCollector;
 
-                let cluster_desc = match eks_client.describe_cluster().name(cluster_name).send().await {
-                    Ok(res) => res.cluster.unwrap(),
-                    Err(aws_sdk_eks::error::SdkError::ServiceError(service_error)) => {
-                        let inner_err = service_error.into_err();
-                        if !self.clusters_to_scan.is_empty() {
-                            if inner_err.is_resource_not_found_exception() {
-                                println!("  -> Cluster '{}' not found in region {}, skipping.", cluster_name, region);
-                                continue;
-                            }
+#[async_trait::async_trait]
+impl AwsResourceCollector for SubnetCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["ec2:subnet"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["ec2:DescribeSubnets"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching subnets from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_ec2, ctx, &config);
+            let mut stream = client.describe_subnets().into_paginator().send();
+
+            let mut count = 0;
+            'pages: while let Some(result) = stream.next().await {
+                for subnet in result?.subnets.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&subnet);
+                    let subnet_id = subnet.subnet_id.clone().unwrap_or_default();
+                    let tags: HashMap<_, _> = subnet
+                        .tags
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
+                        .collect();
+                    let name = tags.get("Name").cloned().unwrap_or_else(|| subnet_id.clone());
+
+                    // Utilization is measured against the usable addresses, so a
+                    // subnet at 100% really cannot take another interface.
+                    let total = subnet.cidr_block.as_deref().and_then(ipv4_cidr_size);
+                    let available = subnet.available_ip_address_count.map(|c| c as u64);
+                    let utilization = match (total, available) {
+                        (Some(total), Some(available)) if total > SUBNET_RESERVED_ADDRESSES => {
+                            let usable = total - SUBNET_RESERVED_ADDRESSES;
+                            let used = usable.saturating_sub(available);
+                            Some((used as f64 / usable as f64 * 1000.0).round() / 10.0)
                         }
-                        eprintln!("Failed to describe cluster '{}': {}", cluster_name, inner_err);
-                        continue;
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to describe cluster '{}': {}", cluster_name, e);
-                        continue;
+                        _ => None,
+                    };
+
+                    all_resources.push(CollectedResource {
+                        id: subnet.subnet_arn.clone().unwrap_or(subnet_id),
+                        name,
+                        resource_type: "ec2:subnet".to_string(),
+                        region: region.to_string(),
+                        tags,
+                        details: serde_json::json!({
+                            "vpc_id": subnet.vpc_id,
+                            "availability_zone": subnet.availability_zone,
+                            "cidr_block": subnet.cidr_block,
+                            "total_ip_address_count": total,
+                            "available_ip_address_count": available,
+                            "utilization": utilization,
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
                     }
-                };
+                }
+            }
+            progress!(log, "  -> Found {} subnets in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
 
-                let Some(api_endpoint) = cluster_desc.endpoint else {
-                    eprintln!("Cluster '{}' has no endpoint.", cluster_name);
-                    continue;
-                };
-                let Some(ca_data) = cluster_desc.certificate_authority.and_then(|ca| ca.data) else {
-                    eprintln!("Cluster '{}' has no certificate authority data.", cluster_name);
-                    continue;
-                };
+/// VPCs as `ec2:vpc`, followed by their subnets as collected by
+/// `SubnetCollector`, so a single service gives the whole network topology.
+pub struct VpcCollector;
 
-                let mut exec_args = vec![
-                    "eks".to_string(),
-                    "get-token".to_string(),
-                    "--cluster-name".to_string(),
-                    cluster_name.clone(),
-                    "--region".to_string(),
+#[async_trait::async_trait]
+impl AwsResourceCollector for VpcCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["ec2:vpc", "ec2:subnet"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["ec2:DescribeVpcs", "ec2:DescribeSubnets"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        let mut all_resources = scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching VPCs from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_ec2, ctx, &config);
+            let mut stream = client.describe_vpcs().into_paginator().send();
+
+            let mut count = 0;
+            'pages: while let Some(result) = stream.next().await {
+                for vpc in result?.vpcs.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&vpc);
+                    let vpc_id = vpc.vpc_id.clone().unwrap_or_default();
+                    let tags: HashMap<_, _> = vpc
+                        .tags
+                        .clone()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
+                        .collect();
+                    let name = tags.get("Name").cloned().unwrap_or_else(|| vpc_id.clone());
+                    let cidr_blocks: Vec<_> = vpc
+                        .cidr_block_association_set
+                        .as_deref()
+                        .unwrap_or_default()
+                        .iter()
+                        .filter_map(|a| a.cidr_block.clone())
+                        .collect();
+                    let ipv6_cidr_blocks: Vec<_> = vpc
+                        .ipv6_cidr_block_association_set
+                        .as_deref()
+                        .unwrap_or_default()
+                        .iter()
+                        .filter_map(|a| a.ipv6_cidr_block.clone())
+                        .collect();
+
+                    all_resources.push(CollectedResource {
+                        arn: ctx.account_id.as_ref().map(|account_id| format!("arn:aws:ec2:{}:{}:vpc/{}", region, account_id, vpc_id)),
+                        id: vpc_id,
+                        name,
+                        resource_type: "ec2:vpc".to_string(),
+                        region: region.to_string(),
+                        tags,
+                        details: serde_json::json!({
+                            "cidr_block": vpc.cidr_block,
+                            "cidr_blocks": cidr_blocks,
+                            "ipv6_cidr_blocks": ipv6_cidr_blocks,
+                            "is_default": vpc.is_default,
+                            "state": vpc.state.as_ref().map(|s| s.as_str()),
+                            "instance_tenancy": vpc.instance_tenancy.as_ref().map(|t| t.as_str()),
+                            "dhcp_options_id": vpc.dhcp_options_id,
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} VPCs in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await?;
+        all_resources.extend(SubnetCollector.collect(ctx, regions).await?);
+        Ok(all_resources)
+    }
+}
+
+pub struct InstanceConnectEndpointCollector;
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for InstanceConnectEndpointCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["ec2:instance_connect_endpoint"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["ec2:DescribeInstanceConnectEndpoints", "ec2:DescribeNetworkInterfaces"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching EC2 Instance Connect endpoints from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_ec2, ctx, &config);
+            let mut stream = client.describe_instance_connect_endpoints().into_paginator().send();
+
+            let mut endpoints = Vec::new();
+            while let Some(result) = stream.next().await {
+                endpoints.extend(result?.instance_connect_endpoints.unwrap_or_default());
+                if ctx.sample_reached(endpoints.len()) {
+                    break;
+                }
+            }
+            if let Some(n) = ctx.sample {
+                endpoints.truncate(n);
+            }
+
+            // The endpoint only lists its ENI ids, so look their addresses up in one call.
+            let eni_ids: Vec<String> = endpoints.iter().flat_map(|e| e.network_interface_ids.clone().unwrap_or_default()).collect();
+            let mut eni_ips: HashMap<String, Vec<IpAddr>> = HashMap::new();
+            if !eni_ids.is_empty() {
+                let output = client.describe_network_interfaces().set_network_interface_ids(Some(eni_ids)).send().await?;
+                for eni in output.network_interfaces.unwrap_or_default() {
+                    let ips = eni
+                        .private_ip_addresses
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter_map(|a| a.private_ip_address?.parse().ok())
+                        .collect();
+                    eni_ips.insert(eni.network_interface_id.unwrap_or_default(), ips);
+                }
+            }
+
+            let count = endpoints.len();
+            for endpoint in endpoints {
+                let raw_response = ctx.raw_response(&endpoint);
+                let endpoint_id = endpoint.instance_connect_endpoint_id.clone().unwrap_or_default();
+                let tags: HashMap<_, _> = endpoint
+                    .tags
+                    .clone()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
+                    .collect();
+                let name = tags.get("Name").cloned().unwrap_or_else(|| endpoint_id.clone());
+
+                let mut ips = Vec::new();
+                let mut ip_details = HashMap::new();
+                for eni_id in endpoint.network_interface_ids.clone().unwrap_or_default() {
+                    for ip in eni_ips.get(&eni_id).cloned().unwrap_or_default() {
+                        ips.push(ip);
+                        ip_details.insert(
+                            ip,
+                            IpDetail {
+                                eni_id: Some(eni_id.clone()),
+                                description: None,
+                            },
+                        );
+                    }
+                }
+
+                all_resources.push(CollectedResource {
+                    id: endpoint.instance_connect_endpoint_arn.clone().unwrap_or(endpoint_id),
+                    name,
+                    resource_type: "ec2:instance_connect_endpoint".to_string(),
+                    region: region.to_string(),
+                    ips,
+                    ip_details,
+                    tags,
+                    details: serde_json::json!({
+                        "vpc_id": endpoint.vpc_id,
+                        "subnet_id": endpoint.subnet_id,
+                        "availability_zone": endpoint.availability_zone,
+                        "state": endpoint.state.as_ref().map(|s| s.as_str().to_string()),
+                        "dns_name": endpoint.dns_name,
+                        "preserve_client_ip": endpoint.preserve_client_ip,
+                        "security_groups": endpoint.security_group_ids.clone().unwrap_or_default(),
+                        "network_interface_ids": endpoint.network_interface_ids.clone().unwrap_or_default(),
+                        "created_at": endpoint.created_at.as_ref().and_then(created_at),
+                    }),
+                    raw_response,
+                    ..Default::default()
+                });
+            }
+            progress!(log, "  -> Found {} Instance Connect endpoints in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct FlowLogCollector;
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for FlowLogCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["ec2:flow_log"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["ec2:DescribeFlowLogs"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching VPC Flow Logs from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_ec2, ctx, &config);
+            let mut stream = client.describe_flow_logs().into_paginator().send();
+
+            let mut count = 0;
+            'pages: while let Some(result) = stream.next().await {
+                for flow_log in result?.flow_logs.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&flow_log);
+                    let flow_log_id = flow_log.flow_log_id.clone().unwrap_or_default();
+                    let tags: HashMap<_, _> = flow_log
+                        .tags
+                        .clone()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
+                        .collect();
+                    let name = tags.get("Name").cloned().unwrap_or_else(|| flow_log_id.clone());
+                    let arn = ctx.account_id.as_ref().map(|account_id| format!("arn:aws:ec2:{}:{}:vpc-flow-log/{}", region, account_id, flow_log_id));
+                    // The id prefix tells what is being logged: vpc-, subnet- or eni-.
+                    let logged_resource_kind = flow_log
+                        .resource_id
+                        .as_deref()
+                        .and_then(|id| id.split_once('-'))
+                        .map(|(kind, _)| kind.to_string());
+
+                    all_resources.push(CollectedResource {
+                        id: flow_log_id,
+                        arn,
+                        name,
+                        resource_type: "ec2:flow_log".to_string(),
+                        region: region.to_string(),
+                        ips: vec![],
+                        tags,
+                        details: serde_json::json!({
+                            "resource_id": flow_log.resource_id,
+                            "logged_resource_kind": logged_resource_kind,
+                            "destination_type": flow_log.log_destination_type.as_ref().map(|t| t.as_str().to_string()),
+                            "destination": flow_log.log_destination.clone().or_else(|| flow_log.log_group_name.clone()),
+                            "traffic_type": flow_log.traffic_type.as_ref().map(|t| t.as_str().to_string()),
+                            "log_format": flow_log.log_format,
+                            "status": flow_log.flow_log_status,
+                            "deliver_logs_status": flow_log.deliver_logs_status,
+                            "created_at": flow_log.creation_time.as_ref().and_then(created_at),
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} flow logs in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct Route53Collector;
+
+impl Route53Collector {
+    /// The zone's A and AAAA records, one resource per record set. Literal
+    /// values become `ips`, so `identify` can name the DNS records pointing at
+    /// an address; alias records keep their target in `details` instead.
+    async fn record_sets(
+        ctx: &ScanContext,
+        client: &aws_sdk_route53::Client,
+        zone_id: &str,
+        zone_name: &str,
+        is_private: bool,
+    ) -> Result<Vec<CollectedResource>> {
+        use aws_sdk_route53::types::RrType;
+
+        let mut records = Vec::new();
+        // ListResourceRecordSets pages with a (name, type, identifier) cursor
+        // rather than a token, so it has no SDK paginator.
+        let mut cursor: Option<(Option<String>, Option<RrType>, Option<String>)> = None;
+        loop {
+            let mut request = client.list_resource_record_sets().hosted_zone_id(zone_id);
+            if let Some((name, record_type, identifier)) = cursor.take() {
+                request = request
+                    .set_start_record_name(name)
+                    .set_start_record_type(record_type)
+                    .set_start_record_identifier(identifier);
+            }
+            let output = request.send().await?;
+
+            for record in &output.resource_record_sets {
+                if !matches!(record.r#type, RrType::A | RrType::Aaaa) {
+                    continue;
+                }
+                let raw_response = ctx.raw_response(record);
+                let values: Vec<String> = record
+                    .resource_records
+                    .as_deref()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|r| r.value.clone())
+                    .collect();
+                let ips: Vec<IpAddr> = values.iter().filter_map(|v| v.parse().ok()).collect();
+                let alias_target = record.alias_target.as_ref().map(|alias| {
+                    serde_json::json!({
+                        "dns_name": alias.dns_name,
+                        "hosted_zone_id": alias.hosted_zone_id,
+                        "evaluate_target_health": alias.evaluate_target_health,
+                    })
+                });
+
+                // Record sets have no ARN; weighted, latency and other routing
+                // policies repeat a name and type, told apart by the set identifier.
+                let mut id = format!("{}/{}/{}", zone_id.trim_start_matches('/'), record.name, record.r#type.as_str());
+                if let Some(set_identifier) = &record.set_identifier {
+                    id = format!("{}/{}", id, set_identifier);
+                }
+
+                records.push(CollectedResource {
+                    id,
+                    name: record.name.clone(),
+                    resource_type: "route53:record".to_string(),
+                    region: "global".to_string(),
+                    ips,
+                    details: serde_json::json!({
+                        "zone_id": zone_id,
+                        "zone_name": zone_name,
+                        "private_zone": is_private,
+                        "record_type": record.r#type.as_str(),
+                        "ttl": record.ttl,
+                        "set_identifier": record.set_identifier,
+                        "values": values,
+                        "alias_target": alias_target,
+                    }),
+                    raw_response,
+                    ..Default::default()
+                });
+                if ctx.sample_reached(records.len()) {
+                    return Ok(records);
+                }
+            }
+
+            if !output.is_truncated {
+                break;
+            }
+            cursor = Some((output.next_record_name, output.next_record_type, output.next_record_identifier));
+        }
+        Ok(records)
+    }
+}
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for Route53Collector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["route53:hostedzone", "route53:record"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["route53:ListHostedZones", "route53:ListTagsForResource", "route53:ListResourceRecordSets"]
+    }
+
+    async fn estimate_calls(&self, ctx: &ScanContext, _regions: &[String]) -> Result<usize> {
+        let config = create_config(ctx, "us-east-1").await;
+        let client = new_client!(aws_sdk_route53, ctx, &config);
+        let mut zones_stream = client.list_hosted_zones().into_paginator().send();
+
+        // One listing call per page, plus a tag lookup and at least one
+        // record listing per zone.
+        let mut calls = 0;
+        while let Some(result) = zones_stream.next().await {
+            calls += 1 + 2 * result?.hosted_zones.len();
+        }
+        Ok(calls)
+    }
+
+    async fn collect(&self, ctx: &ScanContext, _regions: &[String]) -> Result<Vec<CollectedResource>> {
+        // Route 53 is a global service, so we query it once, ignoring the regions list.
+        // We use "us-east-1" for the client, as is standard for global services.
+        info!("Fetching Route 53 hosted zones (global service)...");
+        let config = create_config(ctx, "us-east-1").await;
+        let client = new_client!(aws_sdk_route53, ctx, &config);
+        let mut all_resources = Vec::new();
+        let mut zones_stream = client.list_hosted_zones().into_paginator().send();
+
+        let (mut count, mut record_count) = (0, 0);
+        'pages: while let Some(result) = zones_stream.next().await {
+            for zone in result?.hosted_zones {
+                let raw_response = ctx.raw_response(&zone);
+                let zone_id = zone.id();
+                let resource_id = zone_id.split('/').last().unwrap_or_default();
+
+                let tags = if !resource_id.is_empty() {
+                    match client
+                        .list_tags_for_resource()
+                        .resource_type(Route53ResourceType::Hostedzone)
+                        .resource_id(resource_id)
+                        .send()
+                        .await
+                    {
+                        Ok(tags_output) => tags_output
+                            .resource_tag_set
+                            .and_then(|rts| rts.tags)
+                            .unwrap_or_default()
+                            .into_iter()
+                            .filter_map(|t| Some((t.key()?.to_string(), t.value()?.to_string())))
+                            .collect(),
+                        Err(e) => {
+                            warn!("could not get tags for Route53 zone {}: {}", zone_id, e);
+                            HashMap::new()
+                        }
+                    }
+                } else {
+                    HashMap::new()
+                };
+
+                let (is_private, rr_count) = if let Some(ref config) = zone.config {
+                    (config.private_zone, zone.resource_record_set_count.unwrap_or(0))
+                } else {
+                    (false, 0)
+                };
+
+                all_resources.push(CollectedResource {
+                    id: zone_id.to_string(),
+                    arn: Some(format!("arn:aws:route53:::{}", zone_id.trim_start_matches('/'))),
+                    name: zone.name().to_string(),
+                    resource_type: "route53:hostedzone".to_string(),
+                    region: "global".to_string(),
+                    ips: vec![],
+                    tags,
+                    details: serde_json::json!({
+                        "private_zone": is_private,
+                        "resource_record_set_count": rr_count,
+                    }),
+                    raw_response,
+                    ..Default::default()
+                });
+
+                match Self::record_sets(ctx, &client, zone_id, zone.name(), is_private).await {
+                    Ok(records) => {
+                        record_count += records.len();
+                        all_resources.extend(records);
+                    }
+                    Err(e) => {
+                        let message = format!("could not list records of Route53 zone {}: {}", zone_id, e);
+                        warn!("{}", message);
+                        ctx.record_partial_failure(&message);
+                    }
+                }
+
+                count += 1;
+                if ctx.sample_reached(count) {
+                    break 'pages;
+                }
+            }
+        }
+        info!("  -> Found {} hosted zones with {} A/AAAA records.", count, record_count);
+
+        Ok(all_resources)
+    }
+}
+
+pub struct HealthCheckCollector;
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for HealthCheckCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["route53:healthcheck"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["route53:ListHealthChecks", "route53:ListTagsForResource"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, _regions: &[String]) -> Result<Vec<CollectedResource>> {
+        // Health checks are global like hosted zones.
+        info!("Fetching Route 53 health checks (global service)...");
+        let config = create_config(ctx, "us-east-1").await;
+        let client = new_client!(aws_sdk_route53, ctx, &config);
+        let mut all_resources = Vec::new();
+        let mut stream = client.list_health_checks().into_paginator().send();
+
+        let mut count = 0;
+        'pages: while let Some(result) = stream.next().await {
+            for health_check in result?.health_checks {
+                let raw_response = ctx.raw_response(&health_check);
+                let id = health_check.id.clone();
+
+                let tags = match client
+                    .list_tags_for_resource()
+                    .resource_type(Route53ResourceType::Healthcheck)
+                    .resource_id(&id)
+                    .send()
+                    .await
+                {
+                    Ok(tags_output) => tags_output
+                        .resource_tag_set
+                        .and_then(|rts| rts.tags)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter_map(|t| Some((t.key()?.to_string(), t.value()?.to_string())))
+                        .collect(),
+                    Err(e) => {
+                        warn!("could not get tags for Route53 health check {}: {}", id, e);
+                        HashMap::new()
+                    }
+                };
+
+                let check_config = health_check.health_check_config.as_ref();
+                let ips = check_config
+                    .and_then(|c| c.ip_address.as_deref())
+                    .and_then(|s| s.parse().ok())
+                    .into_iter()
+                    .collect();
+                let endpoint = check_config.and_then(|c| c.ip_address.clone().or_else(|| c.fully_qualified_domain_name.clone()));
+                let name = tags.get("Name").cloned().or_else(|| endpoint.clone()).unwrap_or_else(|| id.clone());
+
+                all_resources.push(CollectedResource {
+                    id: format!("arn:aws:route53:::healthcheck/{}", id),
+                    name,
+                    resource_type: "route53:healthcheck".to_string(),
+                    region: "global".to_string(),
+                    ips,
+                    tags,
+                    details: serde_json::json!({
+                        "type": check_config.map(|c| c.r#type.as_str().to_string()),
+                        "endpoint": endpoint,
+                        "fully_qualified_domain_name": check_config.and_then(|c| c.fully_qualified_domain_name.clone()),
+                        "port": check_config.and_then(|c| c.port),
+                        "resource_path": check_config.and_then(|c| c.resource_path.clone()),
+                        "failure_threshold": check_config.and_then(|c| c.failure_threshold),
+                        "child_health_checks": check_config.and_then(|c| c.child_health_checks.clone()).unwrap_or_default(),
+                    }),
+                    raw_response,
+                    ..Default::default()
+                });
+                count += 1;
+                if ctx.sample_reached(count) {
+                    break 'pages;
+                }
+            }
+        }
+        info!("  -> Found {} health checks.", count);
+
+        Ok(all_resources)
+    }
+}
+
+pub struct ElbCollector;
+
+impl ElbCollector {
+    /// Fetches the attributes of every load balancer in `arns` concurrently,
+    /// keyed by ARN, pacing them all with one shared `AdaptiveDelay`. A load
+    /// balancer whose attributes can't be read is left out rather than failing
+    /// the scan; the failed lookups are returned for the caller to log.
+    async fn attributes(
+        ctx: &ScanContext,
+        client: &aws_sdk_elasticloadbalancingv2::Client,
+        arns: Vec<String>,
+    ) -> (HashMap<String, HashMap<String, String>>, Vec<String>) {
+        use futures::StreamExt;
+
+        let client = &single_attempt_client!(aws_sdk_elasticloadbalancingv2, client);
+        let pacer = &AdaptiveDelay::new();
+        let results: Vec<_> = futures::stream::iter(arns)
+            .map(|arn| async move {
+                let result = pacer
+                    .call(|| client.describe_load_balancer_attributes().load_balancer_arn(&arn).send())
+                    .await;
+                (arn, result)
+            })
+            .buffer_unordered(ctx.describe_concurrency.max(1))
+            .collect()
+            .await;
+
+        let mut attributes = HashMap::new();
+        let mut failures = Vec::new();
+        for (arn, result) in results {
+            match result {
+                Ok(output) => {
+                    let values = output
+                        .attributes
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter_map(|a| Some((a.key?, a.value?)))
+                        .collect();
+                    attributes.insert(arn, values);
+                }
+                Err(e) => failures.push(format!("could not get attributes for load balancer {}: {}", arn, e)),
+            }
+        }
+        (attributes, failures)
+    }
+}
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for ElbCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["elbv2:loadbalancer"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &[
+            "elasticloadbalancing:DescribeLoadBalancers",
+            "elasticloadbalancing:DescribeTags",
+            "elasticloadbalancing:DescribeLoadBalancerAttributes",
+        ]
+    }
+
+    async fn estimate_calls(&self, ctx: &ScanContext, regions: &[String]) -> Result<usize> {
+        let mut calls = 0;
+        for region in regions {
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_elasticloadbalancingv2, ctx, &config);
+            let mut lbs_stream = client.describe_load_balancers().into_paginator().send();
+
+            // Tags are fetched in batches of 20 load balancers, attributes one at a time.
+            let mut lb_count = 0;
+            while let Some(result) = lbs_stream.next().await {
+                lb_count += result?.load_balancers.unwrap_or_default().len();
+                calls += 1;
+            }
+            calls += lb_count.div_ceil(20) + lb_count;
+        }
+        Ok(calls)
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching Load Balancers from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_elasticloadbalancingv2, ctx, &config);
+            let mut lbs_stream = client.describe_load_balancers().into_paginator().send();
+
+            let mut region_lbs = vec![];
+            while let Some(result) = lbs_stream.next().await {
+                region_lbs.extend(result?.load_balancers.unwrap_or_default());
+                if ctx.sample_reached(region_lbs.len()) {
+                    break;
+                }
+            }
+            if let Some(n) = ctx.sample {
+                region_lbs.truncate(n);
+            }
+
+            if region_lbs.is_empty() {
+                progress!(log, "  -> Found 0 load balancers in {}.", region);
+                return Ok((all_resources, log));
+            }
+
+            let mut tags_map: HashMap<String, HashMap<String, String>> = HashMap::new();
+            for lb_chunk in region_lbs.chunks(20) {
+                let arns: Vec<String> = lb_chunk
+                    .iter()
+                    .filter_map(|lb| lb.load_balancer_arn.clone())
+                    .collect();
+                if arns.is_empty() {
+                    continue;
+                }
+
+                let tags_output = client
+                    .describe_tags()
+                    .set_resource_arns(Some(arns))
+                    .send()
+                    .await?;
+                for tag_desc in tags_output.tag_descriptions.unwrap_or_default() {
+                    let arn = tag_desc.resource_arn.unwrap_or_default();
+                    let tags = tag_desc
+                        .tags
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
+                        .collect();
+                    tags_map.insert(arn, tags);
+                }
+            }
+
+            let lb_arns = region_lbs.iter().filter_map(|lb| lb.load_balancer_arn.clone()).collect();
+            let (attributes_map, failures) = Self::attributes(ctx, &client, lb_arns).await;
+            for failure in failures {
+                warning!(log, "{}", failure);
+            }
+
+            let mut count = 0;
+            for lb in region_lbs {
+                let raw_response = ctx.raw_response(&lb);
+                let arn = lb.load_balancer_arn.clone().unwrap_or_default();
+                let name = lb.load_balancer_name.clone().unwrap_or_default();
+                let tags = tags_map.get(&arn).cloned().unwrap_or_default();
+                // Attribute values are "true"/"false" strings; absent keys (e.g. HTTP
+                // header settings on a network load balancer) stay null.
+                let attributes = attributes_map.get(&arn);
+                let flag = |key: &str| attributes.and_then(|a| a.get(key)).map(|v| v == "true");
+
+                let mut ips = vec![];
+                if let Some(azs) = lb.availability_zones {
+                    for az in azs {
+                        if let Some(addrs) = az.load_balancer_addresses {
+                            for addr in addrs {
+                                if let Some(ip_str) = addr.ip_address {
+                                    if let Ok(ip) = ip_str.parse() {
+                                        ips.push(ip);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+
+                all_resources.push(CollectedResource {
+                    id: arn,
+                    name,
+                    resource_type: "elbv2:loadbalancer".to_string(),
+                    region: region.to_string(),
+                    ips,
+                    tags,
+                    details: serde_json::json!({
+                        "dns_name": lb.dns_name,
+                        "type": lb.r#type.map(|t| t.as_str().to_string()),
+                        "scheme": lb.scheme.map(|s| s.as_str().to_string()),
+                        "security_groups": lb.security_groups.unwrap_or_default(),
+                        "created_at": lb.created_time.as_ref().and_then(created_at),
+                        "access_logs_enabled": flag("access_logs.s3.enabled"),
+                        "deletion_protection_enabled": flag("deletion_protection.enabled"),
+                        "drop_invalid_header_fields_enabled": flag("routing.http.drop_invalid_header_fields.enabled"),
+                    }),
+                    raw_response,
+                    ..Default::default()
+                });
+                count += 1;
+            }
+            progress!(log, "  -> Found {} load balancers in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct EksCollector {
+    clusters_to_scan: Vec<String>,
+}
+
+impl EksCollector {
+    pub fn new(clusters_to_scan: Vec<String>) -> Self {
+        Self {
+            clusters_to_scan,
+        }
+    }
+
+    /// Collects a cluster's managed nodegroups and Fargate profiles, at most
+    /// `limit` of them. These come from the EKS API alone, so they don't need
+    /// Kubernetes credentials.
+    async fn collect_control_plane(
+        ctx: &ScanContext,
+        eks_client: &EksClient,
+        cluster_name: &str,
+        region: &str,
+        limit: Option<usize>,
+    ) -> Result<Vec<CollectedResource>> {
+        let mut resources = Vec::new();
+
+        let mut nodegroup_names = Vec::new();
+        let mut nodegroup_stream = eks_client.list_nodegroups().cluster_name(cluster_name).into_paginator().send();
+        while let Some(result) = nodegroup_stream.next().await {
+            nodegroup_names.extend(result?.nodegroups.unwrap_or_default());
+        }
+        if let Some(n) = limit {
+            nodegroup_names.truncate(n);
+        }
+        for nodegroup_name in nodegroup_names {
+            let output = eks_client
+                .describe_nodegroup()
+                .cluster_name(cluster_name)
+                .nodegroup_name(&nodegroup_name)
+                .send()
+                .await?;
+            let Some(nodegroup) = output.nodegroup else { continue };
+            let raw_response = ctx.raw_response(&nodegroup);
+            let scaling = nodegroup.scaling_config.as_ref();
+
+            resources.push(CollectedResource {
+                id: nodegroup.nodegroup_arn.clone().unwrap_or_default(),
+                name: nodegroup_name,
+                resource_type: "eks:nodegroup".to_string(),
+                region: region.to_string(),
+                ips: vec![],
+                tags: nodegroup.tags.clone().unwrap_or_default(),
+                details: serde_json::json!({
+                    "cluster": cluster_name,
+                    "created_at": nodegroup.created_at.as_ref().and_then(created_at),
+                    "instance_types": nodegroup.instance_types.clone().unwrap_or_default(),
+                    "ami_type": nodegroup.ami_type.as_ref().map(|t| t.as_str().to_string()),
+                    "capacity_type": nodegroup.capacity_type.as_ref().map(|t| t.as_str().to_string()),
+                    "min_size": scaling.and_then(|s| s.min_size),
+                    "max_size": scaling.and_then(|s| s.max_size),
+                    "desired_size": scaling.and_then(|s| s.desired_size),
+                    "status": nodegroup.status.as_ref().map(|s| s.as_str().to_string()),
+                }),
+                raw_response,
+                ..Default::default()
+            });
+        }
+
+        let mut profile_names = Vec::new();
+        let mut profile_stream = eks_client.list_fargate_profiles().cluster_name(cluster_name).into_paginator().send();
+        while let Some(result) = profile_stream.next().await {
+            profile_names.extend(result?.fargate_profile_names.unwrap_or_default());
+        }
+        if let Some(n) = limit {
+            profile_names.truncate(n.saturating_sub(resources.len()));
+        }
+        for profile_name in profile_names {
+            let output = eks_client
+                .describe_fargate_profile()
+                .cluster_name(cluster_name)
+                .fargate_profile_name(&profile_name)
+                .send()
+                .await?;
+            let Some(profile) = output.fargate_profile else { continue };
+            let raw_response = ctx.raw_response(&profile);
+            let selectors: Vec<Value> = profile
+                .selectors
+                .clone()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|s| serde_json::json!({ "namespace": s.namespace, "labels": s.labels }))
+                .collect();
+
+            resources.push(CollectedResource {
+                id: profile.fargate_profile_arn.clone().unwrap_or_default(),
+                name: profile_name,
+                resource_type: "eks:fargate_profile".to_string(),
+                region: region.to_string(),
+                ips: vec![],
+                tags: profile.tags.clone().unwrap_or_default(),
+                details: serde_json::json!({
+                    "cluster": cluster_name,
+                    "pod_execution_role_arn": profile.pod_execution_role_arn,
+                    "subnets": profile.subnets.clone().unwrap_or_default(),
+                    "selectors": selectors,
+                    "status": profile.status.as_ref().map(|s| s.as_str().to_string()),
+                    "created_at": profile.created_at.as_ref().and_then(created_at),
+                }),
+                raw_response,
+                ..Default::default()
+            });
+        }
+
+        Ok(resources)
+    }
+
+    /// Parameters for listing Kubernetes objects. With `--sample`, the API
+    /// server returns only as many as the region has room for after `count`,
+    /// rather than every object in the cluster. A limit of 0 would mean no
+    /// limit, so at least one is asked for.
+    fn list_params(ctx: &ScanContext, count: usize) -> ListParams {
+        match ctx.sample {
+            Some(n) => ListParams::default().limit(u32::try_from(n.saturating_sub(count)).unwrap_or(u32::MAX).max(1)),
+            None => ListParams::default(),
+        }
+    }
+
+    /// The start of pod, node and service ids: `account/region/cluster`, with
+    /// the account taken from the cluster's ARN, so that clusters of the same
+    /// name in different accounts (prod and staging, say) keep separate rows.
+    pub fn object_id_prefix(cluster_arn: Option<&str>, cluster_name: &str, region: &str) -> String {
+        match cluster_arn.and_then(|arn| arn.split(':').nth(4)).filter(|account_id| !account_id.is_empty()) {
+            Some(account_id) => format!("{}/{}/{}", account_id, region, cluster_name),
+            None => format!("{}/{}", region, cluster_name),
+        }
+    }
+
+    /// Maps a Kubernetes node to an `eks:node` with its InternalIP and
+    /// ExternalIP addresses. Node ids are the `object_id_prefix` and the node
+    /// name, a segment fewer than pod ids, so the two can't collide.
+    pub fn node_resource(ctx: &ScanContext, node: Node, cluster_name: &str, region: &str, id_prefix: &str) -> CollectedResource {
+        let raw_response = ctx.raw_response(&node);
+        let name = node.name_any();
+        let mut ips = Vec::new();
+        let mut ip_details = HashMap::new();
+        let addresses = node.status.as_ref().and_then(|s| s.addresses.clone()).unwrap_or_default();
+        for address in &addresses {
+            if !matches!(address.type_.as_str(), "InternalIP" | "ExternalIP") {
+                continue;
+            }
+            if let Ok(ip) = address.address.parse::<IpAddr>() {
+                if !ips.contains(&ip) {
+                    ips.push(ip);
+                    ip_details.insert(ip, IpDetail { eni_id: None, description: Some(address.type_.clone()) });
+                }
+            }
+        }
+        let addresses_of = |kind: &str| -> Vec<&str> {
+            addresses.iter().filter(|a| a.type_ == kind).map(|a| a.address.as_str()).collect()
+        };
+        // e.g. aws:///us-east-1a/i-0123456789abcdef0
+        let instance_id = node
+            .spec
+            .as_ref()
+            .and_then(|s| s.provider_id.as_deref())
+            .and_then(|id| id.rsplit('/').next())
+            .filter(|id| id.starts_with("i-"))
+            .map(str::to_string);
+        let ready = node
+            .status
+            .as_ref()
+            .and_then(|s| s.conditions.as_ref())
+            .and_then(|conditions| conditions.iter().find(|c| c.type_ == "Ready"))
+            .map(|c| c.status == "True");
+        let labels = node.metadata.labels.clone().unwrap_or_default();
+
+        CollectedResource {
+            id: format!("{}/{}", id_prefix, name),
+            name,
+            resource_type: "eks:node".to_string(),
+            region: region.to_string(),
+            details: serde_json::json!({
+                "cluster": cluster_name,
+                "instance_id": instance_id,
+                "instance_type": labels.get("node.kubernetes.io/instance-type"),
+                "nodegroup": labels.get("eks.amazonaws.com/nodegroup"),
+                "internal_ips": addresses_of("InternalIP"),
+                "external_ips": addresses_of("ExternalIP"),
+                "kubelet_version": node.status.as_ref().and_then(|s| s.node_info.as_ref()).map(|i| i.kubelet_version.clone()),
+                "ready": ready,
+                "created_at": node.metadata.creation_timestamp.as_ref().map(|t| t.0.format(CREATED_AT_FORMAT).to_string()),
+            }),
+            ips,
+            ip_details,
+            tags: labels.into_iter().collect(),
+            raw_response,
+            ..Default::default()
+        }
+    }
+
+    /// Maps a Kubernetes service to an `eks:service` with its ClusterIPs and
+    /// any LoadBalancer ingress IPs. AWS load balancers usually publish a
+    /// hostname instead, which is kept in `details.load_balancer_hostnames`.
+    /// Service ids add a `services` segment to pod ids, so the two can't collide.
+    pub fn service_resource(ctx: &ScanContext, service: Service, cluster_name: &str, region: &str, id_prefix: &str) -> CollectedResource {
+        let raw_response = ctx.raw_response(&service);
+        let name = service.name_any();
+        let namespace = service.namespace().unwrap_or_default();
+        let spec = service.spec.as_ref();
+
+        let mut ips = Vec::new();
+        let mut ip_details = HashMap::new();
+        // Headless services have a ClusterIP of "None", which doesn't parse.
+        let cluster_ips = spec
+            .and_then(|s| s.cluster_ips.clone().or_else(|| s.cluster_ip.clone().map(|ip| vec![ip])))
+            .unwrap_or_default();
+        let ingress = service
+            .status
+            .as_ref()
+            .and_then(|s| s.load_balancer.as_ref())
+            .and_then(|lb| lb.ingress.clone())
+            .unwrap_or_default();
+        let ingress_ips = ingress.iter().filter_map(|i| i.ip.clone());
+        let labelled = cluster_ips
+            .iter()
+            .cloned()
+            .map(|ip| (ip, "ClusterIP"))
+            .chain(ingress_ips.map(|ip| (ip, "LoadBalancer ingress")));
+        for (address, description) in labelled {
+            if let Ok(ip) = address.parse::<IpAddr>() {
+                if !ips.contains(&ip) {
+                    ips.push(ip);
+                    ip_details.insert(ip, IpDetail { eni_id: None, description: Some(description.to_string()) });
+                }
+            }
+        }
+        let hostnames: Vec<String> = ingress.iter().filter_map(|i| i.hostname.clone()).collect();
+        let ports: Vec<String> = spec
+            .and_then(|s| s.ports.as_ref())
+            .map(|ports| {
+                ports
+                    .iter()
+                    .map(|p| format!("{}/{}", p.port, p.protocol.as_deref().unwrap_or("TCP")))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        CollectedResource {
+            id: format!("{}/{}/services/{}", id_prefix, namespace, name),
+            name,
+            resource_type: "eks:service".to_string(),
+            region: region.to_string(),
+            details: serde_json::json!({
+                "cluster": cluster_name,
+                "namespace": namespace,
+                "type": spec.and_then(|s| s.type_.clone()),
+                "cluster_ips": cluster_ips,
+                "load_balancer_hostnames": hostnames,
+                "ports": ports,
+                "created_at": service.metadata.creation_timestamp.as_ref().map(|t| t.0.format(CREATED_AT_FORMAT).to_string()),
+            }),
+            ips,
+            ip_details,
+            tags: service.metadata.labels.clone().unwrap_or_default().into_iter().collect(),
+            raw_response,
+            ..Default::default()
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for EksCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["eks:pod", "eks:nodegroup", "eks:fargate_profile", "eks:node", "eks:service"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["eks:ListClusters", "eks:DescribeCluster", "eks:ListNodegroups", "eks:DescribeNodegroup", "eks:ListFargateProfiles", "eks:DescribeFargateProfile"]
+    }
+
+    async fn estimate_calls(&self, ctx: &ScanContext, regions: &[String]) -> Result<usize> {
+        let mut calls = 0;
+        for region in regions {
+            let cluster_count = if self.clusters_to_scan.is_empty() {
+                let config = create_config(ctx, region).await;
+                let eks_client = new_client!(aws_sdk_eks, ctx, &config);
+                let mut cluster_stream = eks_client.list_clusters().into_paginator().send();
+                let mut discovered = 0;
+                while let Some(result) = cluster_stream.next().await {
+                    discovered += result?.clusters.unwrap_or_default().len();
+                    calls += 1;
+                }
+                discovered
+            } else {
+                self.clusters_to_scan.len()
+            };
+            // Per cluster: describe_cluster, the nodegroup and Fargate profile
+            // listings, and the Kubernetes pod, node and service listings. Each
+            // nodegroup and profile adds a describe call that isn't counted here.
+            calls += cluster_count * 6;
+        }
+        Ok(calls)
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            let config = create_config(ctx, region).await;
+            let eks_client = new_client!(aws_sdk_eks, ctx, &config);
+
+            let clusters_to_process = if self.clusters_to_scan.is_empty() {
+                progress!(log, "Discovering EKS clusters in {}...", region);
+                let mut cluster_stream = eks_client.list_clusters().into_paginator().send();
+                let mut discovered_clusters = Vec::new();
+                while let Some(result) = cluster_stream.next().await {
+                    discovered_clusters.extend(result?.clusters.unwrap_or_default());
+                }
+                progress!(log, "  -> Found {} clusters in {}.", discovered_clusters.len(), region);
+                discovered_clusters
+            } else {
+                self.clusters_to_scan.clone()
+            };
+
+            let mut region_count = 0;
+            for cluster_name in &clusters_to_process {
+                if ctx.sample_reached(region_count) {
+                    break;
+                }
+                progress!(log, "Connecting to EKS cluster '{}'...", cluster_name);
+
+                let cluster_desc = match eks_client.describe_cluster().name(cluster_name).send().await {
+                    Ok(res) => res.cluster.unwrap(),
+                    Err(aws_sdk_eks::error::SdkError::ServiceError(service_error)) => {
+                        let inner_err = service_error.into_err();
+                        if !self.clusters_to_scan.is_empty() {
+                            if inner_err.is_resource_not_found_exception() {
+                                progress!(log, "  -> Cluster '{}' not found in region {}, skipping.", cluster_name, region);
+                                continue;
+                            }
+                        }
+                        partial_failure!(ctx, log, "Failed to describe cluster '{}': {}", cluster_name, inner_err);
+                        continue;
+                    }
+                    Err(e) => {
+                        partial_failure!(ctx, log, "Failed to describe cluster '{}': {}", cluster_name, e);
+                        continue;
+                    }
+                };
+
+                let id_prefix = Self::object_id_prefix(cluster_desc.arn.as_deref(), cluster_name, region);
+
+                let remaining = ctx.sample.map(|n| n.saturating_sub(region_count));
+                match Self::collect_control_plane(ctx, &eks_client, cluster_name, region, remaining).await {
+                    Ok(resources) => {
+                        progress!(
+                            log,
+                            "  -> Found {} nodegroups and Fargate profiles in cluster '{}'.",
+                            resources.len(),
+                            cluster_name
+                        );
+                        region_count += resources.len();
+                        all_resources.extend(resources);
+                    }
+                    Err(e) => partial_failure!(ctx, log, "Failed to list nodegroups or Fargate profiles for cluster '{}': {}", cluster_name, e),
+                }
+                if ctx.sample_reached(region_count) {
+                    break;
+                }
+
+                let Some(api_endpoint) = cluster_desc.endpoint else {
+                    warning!(log, "Cluster '{}' has no endpoint.", cluster_name);
+                    continue;
+                };
+                let Some(ca_data) = cluster_desc.certificate_authority.and_then(|ca| ca.data) else {
+                    warning!(log, "Cluster '{}' has no certificate authority data.", cluster_name);
+                    continue;
+                };
+                debug!("EKS cluster '{}' API endpoint: {}", cluster_name, api_endpoint);
+
+                let mut exec_args = vec![
+                    "eks".to_string(),
+                    "get-token".to_string(),
+                    "--cluster-name".to_string(),
+                    cluster_name.clone(),
+                    "--region".to_string(),
                     region.to_string(),
                 ];
-                if !profile.is_empty() {
+                if !ctx.profile.is_empty() {
                     exec_args.push("--profile".to_string());
-                    exec_args.push(profile.to_string());
+                    exec_args.push(ctx.profile.clone());
+                }
+                let exec_config = ExecConfig {
+                    command: Some("aws".to_string()),
+                    args: Some(exec_args),
+                    api_version: Some("client.authentication.k8s.io/v1beta1".to_string()),
+                    env: None,
+                    cluster: None,
+                    drop_env: None,
+                    interactive_mode: None,
+                    provide_cluster_info: false,
+                };
+
+                // Prefer an in-process token; the AWS CLI is only needed if that fails.
+                let auth_info = match eks_token(&config, cluster_name, region).await {
+                    Ok(token) => {
+                        debug!("Generated an in-process EKS token for cluster '{}'.", cluster_name);
+                        AuthInfo {
+                            token: Some(SecretString::new(token)),
+                            ..Default::default()
+                        }
+                    }
+                    Err(e) => {
+                        warning!(
+                            log,
+                            "could not generate an EKS token for cluster '{}' ({}), falling back to 'aws eks get-token'.",
+                            cluster_name,
+                            e
+                        );
+                        debug!(
+                            "Authenticating to cluster '{}' with: aws {}",
+                            cluster_name,
+                            exec_config.args.as_deref().unwrap_or_default().join(" ")
+                        );
+                        AuthInfo {
+                            exec: Some(exec_config),
+                            ..Default::default()
+                        }
+                    }
+                };
+
+                let kubeconfig = Kubeconfig {
+                    clusters: vec![NamedCluster {
+                        name: cluster_name.clone(),
+                        cluster: Some(Cluster {
+                            server: Some(api_endpoint),
+                            certificate_authority_data: Some(ca_data),
+                            ..Default::default()
+                        }),
+                    }],
+                    auth_infos: vec![NamedAuthInfo {
+                        name: "eks-auth".to_string(),
+                        auth_info: Some(auth_info),
+                    }],
+                    contexts: vec![NamedContext {
+                        name: "eks-context".to_string(),
+                        context: Some(Context {
+                            cluster: cluster_name.clone(),
+                            user: "eks-auth".to_string(),
+                            ..Default::default()
+                        }),
+                    }],
+                    current_context: Some("eks-context".to_string()),
+                    ..Default::default()
+                };
+
+                let kube_config = kube::Config::from_custom_kubeconfig(kubeconfig, &KubeConfigOptions::default()).await
+                    .map_err(|e| anyhow::anyhow!("Failed to create kubeconfig for cluster '{}': {}", cluster_name, e))?;
+                let client = Client::try_from(kube_config)
+                    .map_err(|e| anyhow::anyhow!("Failed to create Kubernetes client for cluster '{}'. Check that the credentials are authorized for the cluster. Error: {}", cluster_name, e))?;
+
+                debug!("Created Kubernetes client for cluster '{}'.", cluster_name);
+                progress!(log, "Fetching pods from cluster '{}'...", cluster_name);
+                let pods: Api<Pod> = Api::all(client.clone());
+                let pod_list = match pods.list(&Self::list_params(ctx, region_count)).await {
+                    Ok(pl) => pl,
+                    Err(e) => {
+                        partial_failure!(ctx, log, "Error fetching pods from cluster '{}': {}", cluster_name, e);
+                        continue;
+                    }
+                };
+
+                let mut count = 0;
+                for pod in pod_list {
+                    let raw_response = ctx.raw_response(&pod);
+                    if let Some(ref status) = pod.status {
+                        if let Some(ip_str) = &status.pod_ip {
+                            if let Ok(ip) = ip_str.parse::<IpAddr>() {
+                                let name = pod.name_any();
+                                let namespace = pod.namespace().unwrap_or_default();
+                                let id = format!("{}/{}/{}", id_prefix, &namespace, &name);
+                                let tags: HashMap<_, _> = pod.metadata.labels.unwrap_or_default().into_iter().collect();
+                                let images: Vec<String> = pod
+                                    .spec
+                                    .as_ref()
+                                    .map(|spec| spec.containers.iter().filter_map(|c| c.image.clone()).collect())
+                                    .unwrap_or_default();
+
+                                all_resources.push(CollectedResource {
+                                    id,
+                                    name,
+                                    resource_type: "eks:pod".to_string(),
+                                    region: region.to_string(),
+                                    ips: vec![ip],
+                                    tags, // Using K8s labels as AWS tags for consistency
+                                    details: serde_json::json!({
+                                        "cluster": cluster_name.clone(),
+                                        "namespace": namespace,
+                                        "images": images,
+                                        "created_at": pod
+                                            .metadata
+                                            .creation_timestamp
+                                            .as_ref()
+                                            .map(|t| t.0.format(CREATED_AT_FORMAT).to_string()),
+                                    }),
+                                    raw_response,
+                                    ..Default::default()
+                                });
+                                count += 1;
+                                region_count += 1;
+                            }
+                        }
+                    }
+                    if ctx.sample_reached(region_count) {
+                        break;
+                    }
+                }
+                progress!(log, "  -> Found {} pods in cluster '{}'.", count, cluster_name);
+
+                // Nodes and services are best effort: RBAC may allow listing pods but not them.
+                let nodes: Api<Node> = Api::all(client.clone());
+                match nodes.list(&Self::list_params(ctx, region_count)).await {
+                    Ok(node_list) => {
+                        let mut node_count = 0;
+                        for node in node_list {
+                            if ctx.sample_reached(region_count) {
+                                break;
+                            }
+                            all_resources.push(Self::node_resource(ctx, node, cluster_name, region, &id_prefix));
+                            node_count += 1;
+                            region_count += 1;
+                        }
+                        progress!(log, "  -> Found {} nodes in cluster '{}'.", node_count, cluster_name);
+                    }
+                    Err(e) => partial_failure!(ctx, log, "Error fetching nodes from cluster '{}': {}", cluster_name, e),
+                }
+
+                let services: Api<Service> = Api::all(client);
+                match services.list(&Self::list_params(ctx, region_count)).await {
+                    Ok(service_list) => {
+                        let mut service_count = 0;
+                        for service in service_list {
+                            if ctx.sample_reached(region_count) {
+                                break;
+                            }
+                            all_resources.push(Self::service_resource(ctx, service, cluster_name, region, &id_prefix));
+                            service_count += 1;
+                            region_count += 1;
+                        }
+                        progress!(log, "  -> Found {} services in cluster '{}'.", service_count, cluster_name);
+                    }
+                    Err(e) => partial_failure!(ctx, log, "Error fetching services from cluster '{}': {}", cluster_name, e),
+                }
+            }
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+
+pub struct RdsCollector {
+    resolve_endpoints: bool,
+}
+
+impl RdsCollector {
+    pub fn new(resolve_endpoints: bool) -> Self {
+        Self { resolve_endpoints }
+    }
+}
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for RdsCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["rds:db_instance"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["rds:DescribeDBInstances"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching RDS instances from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_rds, ctx, &config);
+            let mut stream = client.describe_db_instances().into_paginator().send();
+
+            let mut count = 0;
+            'pages: while let Some(result) = stream.next().await {
+                for db_instance in result?.db_instances.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&db_instance);
+                    let tags: HashMap<_, _> = db_instance
+                        .tag_list
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
+                        .collect();
+
+                    let name = db_instance.db_instance_identifier.clone().unwrap_or_default();
+                    let arn = db_instance.db_instance_arn.clone().unwrap_or_default();
+                    let security_groups: Vec<String> = db_instance
+                        .vpc_security_groups
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter_map(|g| g.vpc_security_group_id)
+                        .collect();
+
+                    let endpoint_address = db_instance.endpoint.as_ref().and_then(|e| e.address.clone());
+                    let endpoint_port = db_instance.endpoint.as_ref().and_then(|e| e.port);
+
+                    // RDS endpoints are hostnames, so IPs are only known when resolution is enabled.
+                    let ips = match (&endpoint_address, self.resolve_endpoints) {
+                        (Some(address), true) => resolve_endpoint(address, &mut log).await,
+                        _ => vec![],
+                    };
+
+                    all_resources.push(CollectedResource {
+                        id: arn,
+                        name,
+                        resource_type: "rds:db_instance".to_string(),
+                        region: region.to_string(),
+                        ips,
+                        tags,
+                        details: serde_json::json!({
+                            "engine": db_instance.engine,
+                            "instance_class": db_instance.db_instance_class,
+                            "publicly_accessible": db_instance.publicly_accessible,
+                            "security_groups": security_groups,
+                            "endpoint_address": endpoint_address,
+                            "endpoint_port": endpoint_port,
+                            "ca_certificate_identifier": db_instance.ca_certificate_identifier,
+                            "ca_certificate_valid_till": db_instance
+                                .certificate_details
+                                .as_ref()
+                                .and_then(|c| c.valid_till.as_ref())
+                                .and_then(|t| t.fmt(aws_sdk_rds::primitives::DateTimeFormat::DateTime).ok()),
+                            "backup_retention_period": db_instance.backup_retention_period,
+                            "storage_encrypted": db_instance.storage_encrypted,
+                            "deletion_protection": db_instance.deletion_protection,
+                            "multi_az": db_instance.multi_az,
+                            "created_at": db_instance.instance_create_time.as_ref().and_then(created_at),
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} instances in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct DynamoDbCollector;
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for DynamoDbCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["dynamodb:table"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["dynamodb:ListTables", "dynamodb:DescribeTable", "dynamodb:ListTagsOfResource"]
+    }
+
+    async fn estimate_calls(&self, ctx: &ScanContext, regions: &[String]) -> Result<usize> {
+        let mut calls = 0;
+        for region in regions {
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_dynamodb, ctx, &config);
+            let mut tables_stream = client.list_tables().into_paginator().send();
+
+            // Each table costs a describe_table and a list_tags_of_resource call.
+            while let Some(result) = tables_stream.next().await {
+                calls += 1 + result?.table_names.unwrap_or_default().len() * 2;
+            }
+        }
+        Ok(calls)
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        use futures::StreamExt;
+
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching DynamoDB tables from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_dynamodb, ctx, &config);
+            let mut tables_stream = client.list_tables().into_paginator().send();
+
+            let mut table_names = Vec::new();
+            while let Some(result) = tables_stream.next().await {
+                table_names.extend(result?.table_names.unwrap_or_default());
+                if ctx.sample_reached(table_names.len()) {
+                    break;
+                }
+            }
+            if let Some(n) = ctx.sample {
+                table_names.truncate(n);
+            }
+
+            // Tables are described `ctx.describe_concurrency` at a time. describe_table
+            // is rate limited per account, so all of the region's calls share one
+            // adaptive pacer rather than hammering it. A table that can't be
+            // described (e.g. deleted since it was listed) is skipped with a warning;
+            // one whose tags can't be read is kept without them.
+            let client = &single_attempt_client!(aws_sdk_dynamodb, client);
+            let pacer = &AdaptiveDelay::new();
+            let tables: Vec<Result<(CollectedResource, Option<String>)>> = futures::stream::iter(table_names)
+                .map(|table_name| async move {
+                    let desc = pacer
+                        .call(|| client.describe_table().table_name(&table_name).send())
+                        .await
+                        .map_err(|e| anyhow::anyhow!("could not describe DynamoDB table {}: {}", table_name, e))?;
+                    let table = desc
+                        .table
+                        .ok_or_else(|| anyhow::anyhow!("DynamoDB returned no description for table {}", table_name))?;
+                    let raw_response = ctx.raw_response(&table);
+
+                    let table_arn = table.table_arn().unwrap_or_default();
+                    let (tags, tags_error): (HashMap<_, _>, _) =
+                        match pacer.call(|| client.list_tags_of_resource().resource_arn(table_arn).send()).await {
+                            Ok(output) => (output.tags.unwrap_or_default().into_iter().map(|t| (t.key, t.value)).collect(), None),
+                            Err(e) => (HashMap::new(), Some(format!("could not get tags for DynamoDB table {}: {}", table_name, e))),
+                        };
+
+                    let resource = CollectedResource {
+                        id: table.table_arn.clone().unwrap_or_default(),
+                        name: table.table_name.clone().unwrap_or_default(),
+                        resource_type: "dynamodb:table".to_string(),
+                        region: region.to_string(),
+                        ips: vec![],
+                        tags,
+                        details: serde_json::json!({
+                            "item_count": table.item_count,
+                            "table_size_bytes": table.table_size_bytes,
+                            "created_at": table.creation_date_time.as_ref().and_then(created_at),
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    };
+                    Ok::<_, anyhow::Error>((resource, tags_error))
+                })
+                .buffer_unordered(ctx.describe_concurrency.max(1))
+                .collect()
+                .await;
+
+            for table in tables {
+                match table {
+                    Ok((resource, tags_error)) => {
+                        if let Some(message) = tags_error {
+                            warning!(log, "{}", message);
+                        }
+                        all_resources.push(resource);
+                    }
+                    Err(e) => partial_failure!(ctx, log, "{}", e),
+                }
+            }
+            progress!(log, "  -> Found {} tables in {}.", all_resources.len(), region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct ElastiCacheCollector {
+    resolve_endpoints: bool,
+}
+
+impl ElastiCacheCollector {
+    pub fn new(resolve_endpoints: bool) -> Self {
+        Self { resolve_endpoints }
+    }
+}
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for ElastiCacheCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["elasticache:cluster", "elasticache:serverless"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &[
+            "elasticache:DescribeCacheClusters",
+            "elasticache:DescribeServerlessCaches",
+            "elasticache:ListTagsForResource",
+        ]
+    }
+
+    async fn estimate_calls(&self, ctx: &ScanContext, regions: &[String]) -> Result<usize> {
+        let mut calls = 0;
+        for region in regions {
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_elasticache, ctx, &config);
+            let mut clusters_stream = client.describe_cache_clusters().into_paginator().send();
+
+            // Each cluster costs an additional list_tags_for_resource call.
+            while let Some(result) = clusters_stream.next().await {
+                calls += 1 + result?.cache_clusters.unwrap_or_default().len();
+            }
+            // Serverless caches are listed separately.
+            calls += 1;
+        }
+        Ok(calls)
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching ElastiCache clusters from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_elasticache, ctx, &config);
+            // Node endpoints are only returned when node info is requested.
+            let mut clusters_stream = client.describe_cache_clusters().show_cache_node_info(true).into_paginator().send();
+
+            let mut count = 0;
+            'pages: while let Some(result) = clusters_stream.next().await {
+                for cluster in result?.cache_clusters.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&cluster);
+                    let arn = cluster.arn.clone().unwrap_or_default();
+                    let tags: HashMap<_, _> = match client.list_tags_for_resource().resource_name(&arn).send().await {
+                        Ok(tags_output) => tags_output
+                            .tag_list
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
+                            .collect(),
+                        Err(e) => {
+                            warning!(log, "could not get tags for ElastiCache cluster {} in {}: {}", arn, region, e);
+                            HashMap::new()
+                        }
+                    };
+
+                    let security_groups: Vec<String> = cluster
+                        .security_groups
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter_map(|g| g.security_group_id)
+                        .collect();
+
+                    // Node endpoints are hostnames, so IPs are only known when resolution is enabled.
+                    let endpoints: Vec<String> = cluster
+                        .cache_nodes
+                        .as_deref()
+                        .unwrap_or_default()
+                        .iter()
+                        .filter_map(|node| node.endpoint.as_ref().and_then(|e| e.address.clone()))
+                        .collect();
+                    let mut ips = Vec::new();
+                    if self.resolve_endpoints {
+                        for endpoint in &endpoints {
+                            ips.extend(resolve_endpoint(endpoint, &mut log).await);
+                        }
+                        ips.sort();
+                        ips.dedup();
+                    }
+
+                    all_resources.push(CollectedResource {
+                        id: arn,
+                        name: cluster.cache_cluster_id.clone().unwrap_or_default(),
+                        resource_type: "elasticache:cluster".to_string(),
+                        region: region.to_string(),
+                        ips,
+                        tags,
+                        details: serde_json::json!({
+                            "engine": cluster.engine,
+                            "engine_version": cluster.engine_version,
+                            "cache_node_type": cluster.cache_node_type,
+                            "security_groups": security_groups,
+                            "endpoints": endpoints,
+                            "configuration_endpoint": cluster.configuration_endpoint.as_ref().and_then(|e| e.address.clone()),
+                            "created_at": cluster.cache_cluster_create_time.as_ref().and_then(created_at),
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} clusters in {}.", count, region);
+
+            // Serverless caches are not returned by describe_cache_clusters, and
+            // their `serverlesscache:` ARNs can't match a cluster's, so the two
+            // listings never overlap and need no deduplication.
+            progress!(log, "Fetching ElastiCache serverless caches from {}...", region);
+            let mut serverless_stream = client.describe_serverless_caches().into_paginator().send();
+
+            let mut count = 0;
+            'serverless: while let Some(result) = serverless_stream.next().await {
+                // A policy without DescribeServerlessCaches, or a region without
+                // serverless support, must not cost the clusters found above.
+                let page = match result {
+                    Ok(page) => page,
+                    Err(e) => {
+                        partial_failure!(ctx, log, "could not list ElastiCache serverless caches in {}: {}", region, e);
+                        break;
+                    }
+                };
+                for cache in page.serverless_caches.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&cache);
+                    let arn = cache.arn.clone().unwrap_or_default();
+                    let tags: HashMap<_, _> = match client.list_tags_for_resource().resource_name(&arn).send().await {
+                        Ok(tags_output) => tags_output
+                            .tag_list
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
+                            .collect(),
+                        Err(e) => {
+                            warning!(log, "could not get tags for ElastiCache serverless cache {} in {}: {}", arn, region, e);
+                            HashMap::new()
+                        }
+                    };
+
+                    let limits = cache.cache_usage_limits.as_ref();
+                    let endpoint_address = cache.endpoint.as_ref().and_then(|e| e.address.clone());
+                    let ips = match (&endpoint_address, self.resolve_endpoints) {
+                        (Some(address), true) => resolve_endpoint(address, &mut log).await,
+                        _ => vec![],
+                    };
+                    all_resources.push(CollectedResource {
+                        id: arn,
+                        name: cache.serverless_cache_name.clone().unwrap_or_default(),
+                        resource_type: "elasticache:serverless".to_string(),
+                        region: region.to_string(),
+                        ips,
+                        tags,
+                        details: serde_json::json!({
+                            "engine": cache.engine,
+                            "engine_version": cache.full_engine_version,
+                            "status": cache.status,
+                            "created_at": cache.create_time.as_ref().and_then(created_at),
+                            "endpoint_address": endpoint_address,
+                            "endpoint_port": cache.endpoint.as_ref().and_then(|e| e.port),
+                            "max_data_storage": limits.and_then(|l| l.data_storage.as_ref()).and_then(|d| d.maximum),
+                            "data_storage_unit": limits
+                                .and_then(|l| l.data_storage.as_ref())
+                                .and_then(|d| d.unit.as_ref())
+                                .map(|u| u.as_str().to_string()),
+                            "max_ecpu_per_second": limits.and_then(|l| l.ecpu_per_second.as_ref()).and_then(|e| e.maximum),
+                            "security_groups": cache.security_group_ids.clone().unwrap_or_default(),
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'serverless;
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} serverless caches in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct BatchCollector;
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for BatchCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["batch:compute_environment", "batch:job_queue"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["batch:DescribeComputeEnvironments", "batch:DescribeJobQueues"]
+    }
+
+    async fn estimate_calls(&self, _ctx: &ScanContext, regions: &[String]) -> Result<usize> {
+        // Compute environments and job queues are listed separately.
+        Ok(regions.len() * 2)
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching Batch compute environments from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_batch, ctx, &config);
+            let mut envs_stream = client.describe_compute_environments().into_paginator().send();
+
+            let mut count = 0;
+            'envs: while let Some(result) = envs_stream.next().await {
+                for env in result?.compute_environments.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&env);
+                    let compute = env.compute_resources();
+
+                    all_resources.push(CollectedResource {
+                        id: env.compute_environment_arn().to_string(),
+                        name: env.compute_environment_name().to_string(),
+                        resource_type: "batch:compute_environment".to_string(),
+                        region: region.to_string(),
+                        ips: vec![],
+                        tags: env.tags.clone().unwrap_or_default(),
+                        details: serde_json::json!({
+                            "type": env.r#type().map(|t| t.as_str().to_string()),
+                            "state": env.state().map(|s| s.as_str().to_string()),
+                            "ecs_cluster_arn": env.ecs_cluster_arn(),
+                            "instance_types": compute.map(|c| c.instance_types().to_vec()).unwrap_or_default(),
+                            "min_vcpus": compute.and_then(|c| c.minv_cpus()),
+                            "max_vcpus": compute.map(|c| c.maxv_cpus()),
+                            "desired_vcpus": compute.and_then(|c| c.desiredv_cpus()),
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'envs;
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} compute environments in {}.", count, region);
+
+            progress!(log, "Fetching Batch job queues from {}...", region);
+            let mut queues_stream = client.describe_job_queues().into_paginator().send();
+
+            let mut count = 0;
+            'queues: while let Some(result) = queues_stream.next().await {
+                for queue in result?.job_queues.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&queue);
+                    let compute_environments: Vec<&str> = queue
+                        .compute_environment_order()
+                        .iter()
+                        .map(|o| o.compute_environment())
+                        .collect();
+
+                    all_resources.push(CollectedResource {
+                        id: queue.job_queue_arn().to_string(),
+                        name: queue.job_queue_name().to_string(),
+                        resource_type: "batch:job_queue".to_string(),
+                        region: region.to_string(),
+                        ips: vec![],
+                        tags: queue.tags.clone().unwrap_or_default(),
+                        details: serde_json::json!({
+                            "state": queue.state().as_str(),
+                            "priority": queue.priority(),
+                            "compute_environments": compute_environments,
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'queues;
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} job queues in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct LaunchTemplateCollector;
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for LaunchTemplateCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["ec2:launch_template"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["ec2:DescribeLaunchTemplates", "ec2:DescribeLaunchTemplateVersions"]
+    }
+
+    async fn estimate_calls(&self, ctx: &ScanContext, regions: &[String]) -> Result<usize> {
+        let mut calls = 0;
+        for region in regions {
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_ec2, ctx, &config);
+            let mut templates_stream = client.describe_launch_templates().into_paginator().send();
+
+            // Each template costs a describe_launch_template_versions call.
+            while let Some(result) = templates_stream.next().await {
+                calls += 1 + result?.launch_templates.unwrap_or_default().len();
+            }
+        }
+        Ok(calls)
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching EC2 launch templates from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_ec2, ctx, &config);
+            let mut templates_stream = client.describe_launch_templates().into_paginator().send();
+
+            let mut count = 0;
+            'pages: while let Some(result) = templates_stream.next().await {
+                for template in result?.launch_templates.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&template);
+                    let template_id = template.launch_template_id.clone().unwrap_or_default();
+                    let tags: HashMap<_, _> = template
+                        .tags
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
+                        .collect();
+
+                    // The default version is what Auto Scaling groups launch unless pinned otherwise.
+                    let data = match client
+                        .describe_launch_template_versions()
+                        .launch_template_id(&template_id)
+                        .versions("$Default")
+                        .send()
+                        .await
+                    {
+                        Ok(versions_output) => versions_output
+                            .launch_template_versions
+                            .unwrap_or_default()
+                            .into_iter()
+                            .next()
+                            .and_then(|v| v.launch_template_data),
+                        Err(e) => {
+                            warning!(log, "could not describe the default version of launch template {} in {}: {}", template_id, region, e);
+                            None
+                        }
+                    };
+
+                    let mut security_groups: Vec<String> = Vec::new();
+                    if let Some(data) = &data {
+                        security_groups.extend(data.security_group_ids.clone().unwrap_or_default());
+                        for eni in data.network_interfaces.clone().unwrap_or_default() {
+                            security_groups.extend(eni.groups.unwrap_or_default());
+                        }
+                    }
+                    security_groups.sort();
+                    security_groups.dedup();
+
+                    let imdsv2_required = data
+                        .as_ref()
+                        .and_then(|d| d.metadata_options.as_ref())
+                        .and_then(|m| m.http_tokens.as_ref())
+                        .map(|t| t.as_str() == "required")
+                        .unwrap_or(false);
+
+                    all_resources.push(CollectedResource {
+                        arn: ctx
+                            .account_id
+                            .as_ref()
+                            .map(|account_id| format!("arn:aws:ec2:{}:{}:launch-template/{}", region, account_id, template_id)),
+                        id: template_id,
+                        name: template.launch_template_name.clone().unwrap_or_default(),
+                        resource_type: "ec2:launch_template".to_string(),
+                        region: region.to_string(),
+                        ips: vec![],
+                        tags,
+                        details: serde_json::json!({
+                            "default_version": template.default_version_number,
+                            "created_at": template.create_time.as_ref().and_then(created_at),
+                            "latest_version": template.latest_version_number,
+                            "instance_type": data.as_ref().and_then(|d| d.instance_type.as_ref()).map(|t| t.as_str().to_string()),
+                            "image_id": data.as_ref().and_then(|d| d.image_id.clone()),
+                            "security_groups": security_groups,
+                            "instance_profile": data.as_ref().and_then(|d| d.iam_instance_profile.as_ref()).and_then(|p| p.arn.clone().or_else(|| p.name.clone())),
+                            "imdsv2_required": imdsv2_required,
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} launch templates in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+/// Services whose quotas are most often hit in practice.
+const QUOTA_SERVICE_CODES: &[&str] = &[
+    "ec2",
+    "vpc",
+    "elasticloadbalancing",
+    "ebs",
+    "rds",
+    "eks",
+    "lambda",
+    "dynamodb",
+    "elasticache",
+];
+
+pub struct ServiceQuotaCollector;
+
+impl ServiceQuotaCollector {
+    /// Looks up the peak value of a quota's usage metric over the last day.
+    /// Returns `None` when the quota has no usage metric or it has no data.
+    async fn current_usage(
+        client: &CloudWatchClient,
+        metric: &aws_sdk_servicequotas::types::MetricInfo,
+    ) -> Result<Option<f64>> {
+        let (Some(namespace), Some(metric_name)) = (metric.metric_namespace(), metric.metric_name()) else {
+            return Ok(None);
+        };
+        let dimensions = metric
+            .metric_dimensions()
+            .map(|dims| {
+                dims.iter()
+                    .filter_map(|(k, v)| aws_sdk_cloudwatch::types::Dimension::builder().name(k).value(v).build().ok())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let statistic = metric.metric_statistic_recommendation().unwrap_or("Maximum");
+
+        let now = chrono::Utc::now().timestamp();
+        let output = client
+            .get_metric_statistics()
+            .namespace(namespace)
+            .metric_name(metric_name)
+            .set_dimensions(Some(dimensions))
+            .start_time(aws_sdk_cloudwatch::primitives::DateTime::from_secs(now - 86_400))
+            .end_time(aws_sdk_cloudwatch::primitives::DateTime::from_secs(now))
+            .period(3600)
+            .statistics(aws_sdk_cloudwatch::types::Statistic::from(statistic))
+            .send()
+            .await?;
+
+        Ok(output
+            .datapoints
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|d| d.maximum.or(d.average).or(d.sum))
+            .fold(None, |max: Option<f64>, v| Some(max.map_or(v, |m| m.max(v)))))
+    }
+}
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for ServiceQuotaCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["servicequotas:quota"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["servicequotas:ListServiceQuotas", "cloudwatch:GetMetricStatistics"]
+    }
+
+    async fn estimate_calls(&self, _ctx: &ScanContext, regions: &[String]) -> Result<usize> {
+        // One listing per service, plus a metric lookup for the quotas that publish usage.
+        Ok(regions.len() * QUOTA_SERVICE_CODES.len() * 2)
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        use futures::StreamExt;
+
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching service quotas from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_servicequotas, ctx, &config);
+            let cloudwatch = new_client!(aws_sdk_cloudwatch, ctx, &config);
+
+            // A service that can't be listed (e.g. not offered in the region, or
+            // denied) is skipped without losing the other services' quotas.
+            let mut quotas = Vec::new();
+            'services: for service_code in QUOTA_SERVICE_CODES {
+                let mut quotas_stream = client
+                    .list_service_quotas()
+                    .service_code(*service_code)
+                    .into_paginator()
+                    .send();
+
+                let mut service_quotas = Vec::new();
+                while let Some(result) = quotas_stream.next().await {
+                    match result {
+                        Ok(output) => service_quotas.extend(output.quotas.unwrap_or_default()),
+                        Err(e) => {
+                            partial_failure!(ctx, log, "could not list {} quotas in {}: {}", service_code, region, e);
+                            continue 'services;
+                        }
+                    }
                 }
-                let exec_config = ExecConfig {
-                    command: Some("aws".to_string()),
-                    args: Some(exec_args),
-                    api_version: Some("client.authentication.k8s.io/v1beta1".to_string()),
-                    env: None,
-                    cluster: None,
-                    drop_env: None,
-                    interactive_mode: None,
-                    provide_cluster_info: false,
+                quotas.extend(service_quotas);
+                if ctx.sample_reached(quotas.len()) {
+                    break;
+                }
+            }
+            if let Some(n) = ctx.sample {
+                quotas.truncate(n);
+            }
+
+            // Usage is one CloudWatch call per quota that publishes a metric, made
+            // `ctx.describe_concurrency` at a time.
+            let cloudwatch = &cloudwatch;
+            let usages: Vec<Option<Result<Option<f64>>>> = futures::stream::iter(&quotas)
+                .map(|quota| async move {
+                    match &quota.usage_metric {
+                        Some(metric) => Some(Self::current_usage(cloudwatch, metric).await),
+                        None => None,
+                    }
+                })
+                .buffered(ctx.describe_concurrency.max(1))
+                .collect()
+                .await;
+
+            for (quota, usage) in quotas.iter().zip(usages) {
+                let raw_response = ctx.raw_response(quota);
+                let usage = match usage {
+                    Some(Ok(usage)) => usage,
+                    Some(Err(e)) => {
+                        warning!(log, "Could not get usage for quota '{}': {}", quota.quota_name.as_deref().unwrap_or_default(), e);
+                        None
+                    }
+                    None => None,
                 };
-                
-                let kubeconfig = Kubeconfig {
-                    clusters: vec![NamedCluster {
-                        name: cluster_name.clone(),
-                        cluster: Some(Cluster {
-                            server: Some(api_endpoint),
-                            certificate_authority_data: Some(ca_data),
-                            ..Default::default()
-                        }),
-                    }],
-                    auth_infos: vec![NamedAuthInfo {
-                        name: "eks-auth".to_string(),
-                        auth_info: Some(AuthInfo {
-                            exec: Some(exec_config),
-                            ..Default::default()
-                        }),
-                    }],
-                    contexts: vec![NamedContext {
-                        name: "eks-context".to_string(),
-                        context: Some(Context {
-                            cluster: cluster_name.clone(),
-                            user: "eks-auth".to_string(),
-                            ..Default::default()
-                        }),
-                    }],
-                    current_context: Some("eks-context".to_string()),
-                    ..Default::default()
+                let utilization_percent = match (usage, quota.value) {
+                    (Some(used), Some(limit)) if limit > 0.0 => Some((used / limit * 1000.0).round() / 10.0),
+                    _ => None,
                 };
 
-                let config = kube::Config::from_custom_kubeconfig(kubeconfig, &KubeConfigOptions::default()).await
-                    .map_err(|e| anyhow::anyhow!("Failed to create kubeconfig for cluster '{}': {}", cluster_name, e))?;
-                let client = Client::try_from(config)
-                    .map_err(|e| anyhow::anyhow!("Failed to create Kubernetes client for cluster '{}'. This may happen if the 'aws' CLI is not in your PATH or not authenticated. Error: {}", cluster_name, e))?;
+                all_resources.push(CollectedResource {
+                    id: quota.quota_arn.clone().unwrap_or_default(),
+                    name: quota.quota_name.clone().unwrap_or_default(),
+                    resource_type: "servicequotas:quota".to_string(),
+                    region: region.to_string(),
+                    ips: vec![],
+                    tags: HashMap::new(),
+                    details: serde_json::json!({
+                        "service_code": quota.service_code,
+                        "quota_code": quota.quota_code,
+                        "value": quota.value,
+                        "unit": quota.unit,
+                        "adjustable": quota.adjustable,
+                        "usage": usage,
+                        "utilization_percent": utilization_percent,
+                    }),
+                    raw_response,
+                    ..Default::default()
+                });
+            }
+            progress!(log, "  -> Found {} quotas in {}.", all_resources.len(), region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
 
-                println!("Fetching pods from cluster '{}'...", cluster_name);
-                let pods: Api<Pod> = Api::all(client);
-                let pod_list = match pods.list(&ListParams::default()).await {
-                    Ok(pl) => pl,
+pub struct CloudTrailCollector;
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for CloudTrailCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["cloudtrail:trail"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["cloudtrail:DescribeTrails", "cloudtrail:GetTrailStatus", "cloudtrail:ListTags"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching CloudTrail trails from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_cloudtrail, ctx, &config);
+            // Shadow trails are multi-region trails seen from outside their home
+            // region; leaving them out reports each trail once, in its home region.
+            let output = client.describe_trails().include_shadow_trails(false).send().await?;
+
+            let mut count = 0;
+            for trail in output.trail_list.unwrap_or_default() {
+                let raw_response = ctx.raw_response(&trail);
+                let trail_arn = trail.trail_arn.clone().unwrap_or_default();
+
+                // A trail shared from another account may deny these lookups; it
+                // is still reported, just without its status or tags.
+                let status = match client.get_trail_status().name(&trail_arn).send().await {
+                    Ok(status) => Some(status),
                     Err(e) => {
-                        eprintln!("Error fetching pods from cluster '{}': {}", cluster_name, e);
-                        continue;
+                        warning!(log, "could not get the status of trail {} in {}: {}", trail_arn, region, e);
+                        None
                     }
                 };
 
-                let mut count = 0;
-                for pod in pod_list {
-                    if let Some(ref status) = pod.status {
-                        if let Some(ip_str) = &status.pod_ip {
-                            if let Ok(ip) = ip_str.parse::<IpAddr>() {
-                                let name = pod.name_any();
-                                let namespace = pod.namespace().unwrap_or_default();
-                                let arn = format!("{}/{}/{}/{}", region, cluster_name, &namespace, &name);
-                                let tags: HashMap<_, _> = pod.metadata.labels.unwrap_or_default().into_iter().collect();
+                let tags: HashMap<_, _> = match client.list_tags().resource_id_list(&trail_arn).send().await {
+                    Ok(tags_output) => tags_output
+                        .resource_tag_list
+                        .unwrap_or_default()
+                        .into_iter()
+                        .flat_map(|r| r.tags_list.unwrap_or_default())
+                        .map(|t| (t.key, t.value.unwrap_or_default()))
+                        .collect(),
+                    Err(e) => {
+                        warning!(log, "could not get tags for trail {} in {}: {}", trail_arn, region, e);
+                        HashMap::new()
+                    }
+                };
 
-                                all_resources.push(CollectedResource {
-                                    arn,
-                                    name,
-                                    resource_type: "eks:pod".to_string(),
-                                    region: region.to_string(),
-                                    ips: vec![ip],
-                                    tags, // Using K8s labels as AWS tags for consistency
-                                    details: serde_json::json!({
-                                        "cluster": cluster_name.clone(),
-                                        "namespace": namespace,
-                                    }),
-                                });
-                                count += 1;
+                all_resources.push(CollectedResource {
+                    id: trail_arn,
+                    name: trail.name.clone().unwrap_or_default(),
+                    resource_type: "cloudtrail:trail".to_string(),
+                    region: region.to_string(),
+                    ips: vec![],
+                    tags,
+                    details: serde_json::json!({
+                        "home_region": trail.home_region,
+                        "is_multi_region": trail.is_multi_region_trail,
+                        "is_organization_trail": trail.is_organization_trail,
+                        "is_logging": status.as_ref().and_then(|s| s.is_logging),
+                        "log_file_validation_enabled": trail.log_file_validation_enabled,
+                        "s3_bucket_name": trail.s3_bucket_name,
+                        "kms_key_id": trail.kms_key_id,
+                        "cloudwatch_logs_log_group_arn": trail.cloud_watch_logs_log_group_arn,
+                        "latest_delivery_error": status.and_then(|s| s.latest_delivery_error),
+                    }),
+                    raw_response,
+                    ..Default::default()
+                });
+                count += 1;
+                if ctx.sample_reached(count) {
+                    break;
+                }
+            }
+            progress!(log, "  -> Found {} trails in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct EfsAccessPointCollector;
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for EfsAccessPointCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["efs:access_point"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["elasticfilesystem:DescribeAccessPoints"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching EFS access points from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_efs, ctx, &config);
+            let mut stream = client.describe_access_points().into_paginator().send();
+
+            let mut count = 0;
+            'pages: while let Some(result) = stream.next().await {
+                for access_point in result?.access_points.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&access_point);
+                    let access_point_id = access_point.access_point_id.clone().unwrap_or_default();
+                    let tags: HashMap<_, _> = access_point
+                        .tags
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|t| (t.key, t.value))
+                        .collect();
+                    let name = access_point.name.clone().filter(|n| !n.is_empty()).unwrap_or_else(|| access_point_id.clone());
+                    let root_directory = access_point.root_directory.as_ref();
+                    let posix_user = access_point.posix_user.as_ref();
+
+                    all_resources.push(CollectedResource {
+                        id: access_point.access_point_arn.clone().unwrap_or(access_point_id),
+                        name,
+                        resource_type: "efs:access_point".to_string(),
+                        region: region.to_string(),
+                        ips: vec![],
+                        tags,
+                        details: serde_json::json!({
+                            "file_system_id": access_point.file_system_id,
+                            "state": access_point.life_cycle_state.as_ref().map(|s| s.as_str().to_string()),
+                            "root_directory": root_directory.and_then(|r| r.path.clone()),
+                            "posix_uid": posix_user.map(|u| u.uid),
+                            "posix_gid": posix_user.map(|u| u.gid),
+                            "posix_secondary_gids": posix_user.and_then(|u| u.secondary_gids.clone()).unwrap_or_default(),
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} EFS access points in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct VpcLatticeCollector {
+    resolve_endpoints: bool,
+}
+
+impl VpcLatticeCollector {
+    pub fn new(resolve_endpoints: bool) -> Self {
+        Self { resolve_endpoints }
+    }
+}
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for VpcLatticeCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["vpclattice:service"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["vpc-lattice:ListServices", "vpc-lattice:GetService", "vpc-lattice:ListTagsForResource"]
+    }
+
+    async fn estimate_calls(&self, ctx: &ScanContext, regions: &[String]) -> Result<usize> {
+        let mut calls = 0;
+        for region in regions {
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_vpclattice, ctx, &config);
+            let mut stream = client.list_services().into_paginator().send();
+
+            // Each service costs a get_service and a list_tags_for_resource call.
+            while let Some(result) = stream.next().await {
+                calls += 1 + 2 * result?.items.unwrap_or_default().len();
+            }
+        }
+        Ok(calls)
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching VPC Lattice services from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_vpclattice, ctx, &config);
+            let mut stream = client.list_services().into_paginator().send();
+
+            let mut count = 0;
+            'pages: while let Some(result) = stream.next().await {
+                for summary in result?.items.unwrap_or_default() {
+                    let service_id = summary.id.clone().unwrap_or_default();
+                    let arn = summary.arn.clone().unwrap_or_else(|| service_id.clone());
+
+                    // The auth type is only returned by get_service, not in the listing.
+                    let service = match client.get_service().service_identifier(&service_id).send().await {
+                        Ok(service) => Some(service),
+                        Err(e) => {
+                            warning!(log, "could not get VPC Lattice service {} in {}: {}", service_id, region, e);
+                            None
+                        }
+                    };
+                    let raw_response = service.as_ref().and_then(|service| ctx.raw_response(service));
+                    let tags = match client.list_tags_for_resource().resource_arn(&arn).send().await {
+                        Ok(output) => output.tags.unwrap_or_default(),
+                        Err(e) => {
+                            warning!(log, "could not get tags for VPC Lattice service {} in {}: {}", service_id, region, e);
+                            HashMap::new()
+                        }
+                    };
+
+                    let dns_name = summary.dns_entry.as_ref().and_then(|d| d.domain_name.clone());
+                    let ips = match (&dns_name, self.resolve_endpoints) {
+                        (Some(hostname), true) => resolve_endpoint(hostname, &mut log).await,
+                        _ => vec![],
+                    };
+
+                    all_resources.push(CollectedResource {
+                        id: arn,
+                        name: summary.name.clone().unwrap_or(service_id),
+                        resource_type: "vpclattice:service".to_string(),
+                        region: region.to_string(),
+                        ips,
+                        tags,
+                        details: serde_json::json!({
+                            "status": summary.status.as_ref().map(|s| s.as_str().to_string()),
+                            "dns_name": dns_name,
+                            "hosted_zone_id": summary.dns_entry.as_ref().and_then(|d| d.hosted_zone_id.clone()),
+                            "custom_domain_name": summary.custom_domain_name,
+                            "auth_type": service.and_then(|s| s.auth_type).map(|a| a.as_str().to_string()),
+                            "created_at": summary.created_at.as_ref().and_then(created_at),
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} VPC Lattice services in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct SecurityFindingsCollector {
+    include_macie: bool,
+}
+
+impl SecurityFindingsCollector {
+    pub fn new(include_macie: bool) -> Self {
+        Self { include_macie }
+    }
+
+    /// Active Inspector findings by severity, or the reason they couldn't be
+    /// read (typically that Inspector isn't enabled in the region).
+    async fn inspector_counts(config: &SdkConfig, ctx: &ScanContext) -> std::result::Result<Value, String> {
+        let client = new_client!(aws_sdk_inspector2, ctx, config);
+        // The account aggregation returns the severity counts in a single call,
+        // rather than paging through every finding.
+        let output = client
+            .list_finding_aggregations()
+            .aggregation_type(aws_sdk_inspector2::types::AggregationType::Account)
+            .send()
+            .await
+            .map_err(|e| aws_sdk_inspector2::error::DisplayErrorContext(e).to_string())?;
+
+        let (mut critical, mut high, mut medium, mut all) = (0, 0, 0, 0);
+        for response in output.responses.unwrap_or_default() {
+            if let Ok(account) = response.as_account_aggregation() {
+                if let Some(counts) = &account.severity_counts {
+                    critical += counts.critical.unwrap_or(0);
+                    high += counts.high.unwrap_or(0);
+                    medium += counts.medium.unwrap_or(0);
+                    all += counts.all.unwrap_or(0);
+                }
+            }
+        }
+        Ok(serde_json::json!({
+            "critical": critical,
+            "high": high,
+            "medium": medium,
+            // The buckets are counted independently and can disagree, so the
+            // remainder is clamped rather than reported as negative.
+            "low_or_informational": (all - critical - high - medium).max(0),
+            "total": all,
+        }))
+    }
+
+    /// Macie findings by severity, or the reason they couldn't be read.
+    async fn macie_counts(config: &SdkConfig, ctx: &ScanContext) -> std::result::Result<Value, String> {
+        let client = new_client!(aws_sdk_macie2, ctx, config);
+        let output = client
+            .get_finding_statistics()
+            .group_by(aws_sdk_macie2::types::GroupBy::SeverityDescription)
+            .send()
+            .await
+            .map_err(|e| aws_sdk_macie2::error::DisplayErrorContext(e).to_string())?;
+
+        let mut counts = serde_json::Map::new();
+        let mut total = 0;
+        for group in output.counts_by_group.unwrap_or_default() {
+            let count = group.count.unwrap_or(0);
+            total += count;
+            counts.insert(group.group_key.unwrap_or_default().to_lowercase(), Value::from(count));
+        }
+        counts.insert("total".to_string(), Value::from(total));
+        Ok(Value::Object(counts))
+    }
+}
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for SecurityFindingsCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["inspector:findings_summary"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        if self.include_macie {
+            &["inspector2:ListFindingAggregations", "macie2:GetFindingStatistics"]
+        } else {
+            &["inspector2:ListFindingAggregations"]
+        }
+    }
+
+    async fn estimate_calls(&self, _ctx: &ScanContext, regions: &[String]) -> Result<usize> {
+        Ok(regions.len() * if self.include_macie { 2 } else { 1 })
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching security findings summary from {}...", region);
+            let config = create_config(ctx, region).await;
+
+            // A region where a scanner is off is still reported, since that gap
+            // is itself a finding.
+            let (inspector_enabled, inspector) = match Self::inspector_counts(&config, ctx).await {
+                Ok(counts) => (true, counts),
+                Err(e) => (false, serde_json::json!({ "error": e })),
+            };
+            let mut details = serde_json::json!({
+                "inspector_enabled": inspector_enabled,
+                "inspector": inspector,
+            });
+            if self.include_macie {
+                let (macie_enabled, macie) = match Self::macie_counts(&config, ctx).await {
+                    Ok(counts) => (true, counts),
+                    Err(e) => (false, serde_json::json!({ "error": e })),
+                };
+                details["macie_enabled"] = Value::from(macie_enabled);
+                details["macie"] = macie;
+            }
+
+            // The summary has no ARN; one per account and region.
+            let id = match ctx.id_scope() {
+                Some(scope) => format!("findings-summary/{}/{}", scope, region),
+                None => format!("findings-summary/{}", region),
+            };
+            all_resources.push(CollectedResource {
+                id,
+                name: format!("findings-summary-{}", region),
+                resource_type: "inspector:findings_summary".to_string(),
+                region: region.to_string(),
+                details,
+                ..Default::default()
+            });
+            progress!(
+                log,
+                "  -> Inspector {} in {}.",
+                if inspector_enabled {
+                    "enabled"
+                } else {
+                    "not enabled"
+                },
+                region
+            );
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct EcsTaskCollector;
+
+impl EcsTaskCollector {
+    /// `describe_tasks` accepts at most this many task ARNs per call.
+    const DESCRIBE_BATCH: usize = 100;
+}
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for EcsTaskCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["ecs:task"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["ecs:ListClusters", "ecs:ListTasks", "ecs:DescribeTasks"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching running ECS tasks from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_ecs, ctx, &config);
+
+            let mut cluster_arns = Vec::new();
+            let mut clusters_stream = client.list_clusters().into_paginator().send();
+            while let Some(result) = clusters_stream.next().await {
+                cluster_arns.extend(result?.cluster_arns.unwrap_or_default());
+            }
+
+            let mut count = 0;
+            'clusters: for cluster_arn in &cluster_arns {
+                let mut task_arns = Vec::new();
+                let mut tasks_stream = client
+                    .list_tasks()
+                    .cluster(cluster_arn)
+                    .desired_status(aws_sdk_ecs::types::DesiredStatus::Running)
+                    .into_paginator()
+                    .send();
+                while let Some(result) = tasks_stream.next().await {
+                    task_arns.extend(result?.task_arns.unwrap_or_default());
+                }
+
+                for batch in task_arns.chunks(Self::DESCRIBE_BATCH) {
+                    let output = client
+                        .describe_tasks()
+                        .cluster(cluster_arn)
+                        .set_tasks(Some(batch.to_vec()))
+                        .include(aws_sdk_ecs::types::TaskField::Tags)
+                        .send()
+                        .await?;
+
+                    for task in output.tasks.unwrap_or_default() {
+                        let raw_response = ctx.raw_response(&task);
+                        let task_arn = task.task_arn.clone().unwrap_or_default();
+                        let task_id = task_arn.rsplit('/').next().unwrap_or_default().to_string();
+                        let tags: HashMap<_, _> = task
+                            .tags
+                            .clone()
+                            .unwrap_or_default()
+                            .into_iter()
+                            .filter_map(|t| Some((t.key?, t.value.unwrap_or_default())))
+                            .collect();
+
+                        let containers = task.containers.clone().unwrap_or_default();
+                        let images: Vec<String> = containers.iter().filter_map(|c| c.image.clone()).collect();
+
+                        // awsvpc tasks have their own interfaces, so their addresses identify the task.
+                        let mut ips = Vec::new();
+                        for eni in containers.iter().flat_map(|c| c.network_interfaces.clone().unwrap_or_default()) {
+                            for ip_str in eni.private_ipv4_address.iter().chain(eni.ipv6_address.iter()) {
+                                if let Ok(ip) = ip_str.parse::<IpAddr>() {
+                                    if !ips.contains(&ip) {
+                                        ips.push(ip);
+                                    }
+                                }
                             }
                         }
+
+                        all_resources.push(CollectedResource {
+                            id: task_arn,
+                            name: task.group.clone().filter(|g| !g.is_empty()).unwrap_or(task_id),
+                            resource_type: "ecs:task".to_string(),
+                            region: region.to_string(),
+                            ips,
+                            tags,
+                            details: serde_json::json!({
+                                "cluster_arn": task.cluster_arn,
+                                "task_definition_arn": task.task_definition_arn,
+                                "launch_type": task.launch_type.as_ref().map(|t| t.as_str().to_string()),
+                                "last_status": task.last_status,
+                                "images": images,
+                                "created_at": task.created_at.as_ref().and_then(created_at),
+                            }),
+                            raw_response,
+                            ..Default::default()
+                        });
+                        count += 1;
+                        if ctx.sample_reached(count) {
+                            break 'clusters;
+                        }
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} running tasks in {} clusters in {}.", count, cluster_arns.len(), region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct WorkSpacesCollector;
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for WorkSpacesCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["workspaces:workspace"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["workspaces:DescribeWorkspaces", "workspaces:DescribeTags"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching WorkSpaces from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_workspaces, ctx, &config);
+            let mut stream = client.describe_workspaces().into_paginator().send();
+
+            let mut count = 0;
+            'pages: while let Some(result) = stream.next().await {
+                for workspace in result?.workspaces.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&workspace);
+                    let workspace_id = workspace.workspace_id.clone().unwrap_or_default();
+                    // DescribeWorkspaces doesn't return an ARN, so build one when the account is known.
+                    let arn = ctx.account_id.as_ref().map(|account_id| format!("arn:aws:workspaces:{}:{}:workspace/{}", region, account_id, workspace_id));
+
+                    let tags: HashMap<_, _> = match client.describe_tags().resource_id(&workspace_id).send().await {
+                        Ok(output) => output
+                            .tag_list
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|t| (t.key, t.value.unwrap_or_default()))
+                            .collect(),
+                        Err(e) => {
+                            warning!(log, "Could not get tags for WorkSpace {}: {}", workspace_id, e);
+                            HashMap::new()
+                        }
+                    };
+
+                    let ips = workspace.ip_address.as_deref().and_then(|s| s.parse().ok()).into_iter().collect();
+                    let name = workspace
+                        .computer_name
+                        .clone()
+                        .or_else(|| tags.get("Name").cloned())
+                        .unwrap_or_else(|| workspace_id.clone());
+
+                    all_resources.push(CollectedResource {
+                        id: workspace_id.clone(),
+                        arn,
+                        name,
+                        resource_type: "workspaces:workspace".to_string(),
+                        region: region.to_string(),
+                        ips,
+                        tags,
+                        details: serde_json::json!({
+                            "workspace_id": workspace_id,
+                            "user_name": workspace.user_name,
+                            "computer_name": workspace.computer_name,
+                            "directory_id": workspace.directory_id,
+                            "bundle_id": workspace.bundle_id,
+                            "subnet_id": workspace.subnet_id,
+                            "state": workspace.state.as_ref().map(|s| s.as_str().to_string()),
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
                     }
                 }
-                println!("  -> Found {} pods in cluster '{}'.", count, cluster_name);
             }
-        }
-
-        Ok(all_resources)
+            progress!(log, "  -> Found {} WorkSpaces in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
     }
 }
 
-
-pub struct RdsCollector;
+pub struct AppStreamCollector;
 
 #[async_trait::async_trait]
-impl AwsResourceCollector for RdsCollector {
-    async fn collect(&self, profile: &str, regions: &[String]) -> Result<Vec<CollectedResource>> {
-        let mut all_resources = Vec::new();
+impl AwsResourceCollector for AppStreamCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["appstream:fleet"]
+    }
 
-        for region in regions {
-            println!("Fetching RDS instances from {}...", region);
-            let config = create_config(profile, region).await;
-            let client = RdsClient::new(&config);
-            let mut stream = client.describe_db_instances().into_paginator().send();
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["appstream:DescribeFleets", "appstream:ListTagsForResource"]
+    }
 
-            let mut count = 0;
-            while let Some(result) = stream.next().await {
-                for db_instance in result?.db_instances.unwrap_or_default() {
-                    let tags: HashMap<_, _> = db_instance
-                        .tag_list
-                        .unwrap_or_default()
-                        .into_iter()
-                        .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
-                        .collect();
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching AppStream fleets from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_appstream, ctx, &config);
 
-                    let name = db_instance.db_instance_identifier.clone().unwrap_or_default();
-                    let arn = db_instance.db_instance_arn.clone().unwrap_or_default();
+            // DescribeFleets has no paginator, so follow the token by hand.
+            let mut count = 0;
+            let mut next_token = None;
+            'pages: loop {
+                let output = client.describe_fleets().set_next_token(next_token).send().await?;
+                for fleet in output.fleets.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&fleet);
+                    let tags = match client.list_tags_for_resource().resource_arn(&fleet.arn).send().await {
+                        Ok(output) => output.tags.unwrap_or_default(),
+                        Err(e) => {
+                            warning!(log, "Could not get tags for AppStream fleet {}: {}", fleet.name, e);
+                            HashMap::new()
+                        }
+                    };
 
                     all_resources.push(CollectedResource {
-                        arn,
-                        name,
-                        resource_type: "rds:db_instance".to_string(),
+                        id: fleet.arn.clone(),
+                        name: fleet.name.clone(),
+                        resource_type: "appstream:fleet".to_string(),
                         region: region.to_string(),
-                        ips: vec![], // RDS endpoints are hostnames, not IPs
+                        ips: vec![],
                         tags,
                         details: serde_json::json!({
-                            "engine": db_instance.engine,
-                            "instance_class": db_instance.db_instance_class,
-                            "publicly_accessible": db_instance.publicly_accessible,
+                            "display_name": fleet.display_name,
+                            "instance_type": fleet.instance_type,
+                            "fleet_type": fleet.fleet_type.as_ref().map(|t| t.as_str().to_string()),
+                            "state": fleet.state.as_str(),
+                            "image_name": fleet.image_name,
+                            "subnet_ids": fleet.vpc_config.as_ref().and_then(|v| v.subnet_ids.clone()).unwrap_or_default(),
+                            "created_at": fleet.created_time.as_ref().and_then(created_at),
                         }),
+                        raw_response,
+                        ..Default::default()
                     });
                     count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
+                }
+                next_token = output.next_token;
+                if next_token.is_none() {
+                    break;
                 }
             }
-            println!("  -> Found {} instances in {}.", count, region);
-        }
-        Ok(all_resources)
+            progress!(log, "  -> Found {} AppStream fleets in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
     }
 }
 
-pub struct DynamoDbCollector;
+pub struct SesCollector;
+
+impl SesCollector {
+    /// SES names are only unique within an account and region, and the API
+    /// returns no ARN to key them by.
+    fn id(ctx: &ScanContext, region: &str, kind: &str, name: &str) -> String {
+        match ctx.id_scope() {
+            Some(scope) => format!("ses/{}/{}/{}/{}", scope, region, kind, name),
+            None => format!("ses/{}/{}/{}", region, kind, name),
+        }
+    }
+
+    fn arn(ctx: &ScanContext, region: &str, kind: &str, name: &str) -> Option<String> {
+        ctx.account_id.as_ref().map(|account_id| format!("arn:aws:ses:{}:{}:{}/{}", region, account_id, kind, name))
+    }
+}
 
 #[async_trait::async_trait]
-impl AwsResourceCollector for DynamoDbCollector {
-    async fn collect(&self, profile: &str, regions: &[String]) -> Result<Vec<CollectedResource>> {
-        let mut all_resources = Vec::new();
+impl AwsResourceCollector for SesCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["ses:identity", "ses:configuration_set"]
+    }
 
-        for region in regions {
-            println!("Fetching DynamoDB tables from {}...", region);
-            let config = create_config(profile, region).await;
-            let client = DynamoDbClient::new(&config);
-            let mut tables_stream = client.list_tables().into_paginator().send();
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["ses:ListEmailIdentities", "ses:GetEmailIdentity", "ses:ListConfigurationSets", "ses:GetConfigurationSet"]
+    }
 
-            let mut table_names = Vec::new();
-            while let Some(result) = tables_stream.next().await {
-                table_names.extend(result?.table_names.unwrap_or_default());
-            }
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching SES identities from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_sesv2, ctx, &config);
+            let mut stream = client.list_email_identities().into_paginator().send();
 
             let mut count = 0;
-            for table_name in table_names {
-                let desc = client.describe_table().table_name(&table_name).send().await?;
-                let table = desc.table.unwrap();
+            'pages: while let Some(result) = stream.next().await {
+                for info in result?.email_identities.unwrap_or_default() {
+                    let identity_name = info.identity_name.clone().unwrap_or_default();
+                    // DKIM and tags are only returned per identity.
+                    let identity = match client.get_email_identity().email_identity(&identity_name).send().await {
+                        Ok(identity) => identity,
+                        Err(e) => {
+                            partial_failure!(ctx, log, "could not get SES identity {} in {}: {}", identity_name, region, e);
+                            continue;
+                        }
+                    };
+                    let raw_response = ctx.raw_response(&identity);
+                    let dkim = identity.dkim_attributes.as_ref();
+                    let tags: HashMap<_, _> = identity
+                        .tags
+                        .clone()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|t| (t.key, t.value))
+                        .collect();
+
+                    all_resources.push(CollectedResource {
+                        id: Self::id(ctx, region, "identity", &identity_name),
+                        arn: Self::arn(ctx, region, "identity", &identity_name),
+                        name: identity_name,
+                        resource_type: "ses:identity".to_string(),
+                        region: region.to_string(),
+                        ips: vec![],
+                        tags,
+                        details: serde_json::json!({
+                            "identity_type": info.identity_type.as_ref().map(|t| t.as_str().to_string()),
+                            "verification_status": info.verification_status.as_ref().map(|s| s.as_str().to_string()),
+                            "sending_enabled": info.sending_enabled,
+                            "dkim_signing_enabled": dkim.is_some_and(|d| d.signing_enabled),
+                            "dkim_status": dkim.and_then(|d| d.status.as_ref()).map(|s| s.as_str().to_string()),
+                            "configuration_set": identity.configuration_set_name,
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} SES identities in {}.", count, region);
 
-                let tags_output = client.list_tags_of_resource().resource_arn(table.table_arn().unwrap()).send().await?;
-                let tags: HashMap<_, _> = tags_output
+            let mut set_names = Vec::new();
+            let mut sets_stream = client.list_configuration_sets().into_paginator().send();
+            while let Some(result) = sets_stream.next().await {
+                set_names.extend(result?.configuration_sets.unwrap_or_default());
+            }
+            if let Some(n) = ctx.sample {
+                set_names.truncate(n);
+            }
+            for set_name in &set_names {
+                let set = match client.get_configuration_set().configuration_set_name(set_name).send().await {
+                    Ok(set) => set,
+                    Err(e) => {
+                        partial_failure!(ctx, log, "could not get SES configuration set {} in {}: {}", set_name, region, e);
+                        continue;
+                    }
+                };
+                let raw_response = ctx.raw_response(&set);
+                let tags: HashMap<_, _> = set
                     .tags
+                    .clone()
                     .unwrap_or_default()
                     .into_iter()
                     .map(|t| (t.key, t.value))
                     .collect();
 
                 all_resources.push(CollectedResource {
-                    arn: table.table_arn.clone().unwrap_or_default(),
-                    name: table.table_name.clone().unwrap_or_default(),
-                    resource_type: "dynamodb:table".to_string(),
+                    id: Self::id(ctx, region, "configuration-set", set_name),
+                    arn: Self::arn(ctx, region, "configuration-set", set_name),
+                    name: set_name.clone(),
+                    resource_type: "ses:configuration_set".to_string(),
                     region: region.to_string(),
                     ips: vec![],
                     tags,
                     details: serde_json::json!({
-                        "item_count": table.item_count,
-                        "table_size_bytes": table.table_size_bytes,
+                        "sending_enabled": set.sending_options.as_ref().map(|o| o.sending_enabled),
+                        "tls_policy": set
+                            .delivery_options
+                            .as_ref()
+                            .and_then(|o| o.tls_policy.as_ref())
+                            .map(|p| p.as_str().to_string()),
+                        "reputation_metrics_enabled": set.reputation_options.as_ref().map(|o| o.reputation_metrics_enabled),
                     }),
+                    raw_response,
+                    ..Default::default()
                 });
-                count += 1;
             }
-            println!("  -> Found {} tables in {}.", count, region);
-        }
-        Ok(all_resources)
+            let set_count = all_resources.len() - count;
+            progress!(log, "  -> Found {} SES configuration sets in {}.", set_count, region);
+            Ok((all_resources, log))
+        })
+        .await
     }
 }
 
-pub struct ElastiCacheCollector;
+pub struct AmazonMqCollector {
+    resolve_endpoints: bool,
+}
+
+impl AmazonMqCollector {
+    pub fn new(resolve_endpoints: bool) -> Self {
+        Self { resolve_endpoints }
+    }
+
+    /// The host part of a broker endpoint URL such as
+    /// `amqps://b-1234.mq.us-east-1.amazonaws.com:5671`.
+    fn endpoint_host(endpoint: &str) -> &str {
+        let rest = endpoint.split_once("://").map_or(endpoint, |(_, rest)| rest);
+        rest.split(['/', ':']).next().unwrap_or(rest)
+    }
+}
 
 #[async_trait::async_trait]
-impl AwsResourceCollector for ElastiCacheCollector {
-    async fn collect(&self, profile: &str, regions: &[String]) -> Result<Vec<CollectedResource>> {
-        let mut all_resources = Vec::new();
+impl AwsResourceCollector for AmazonMqCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["mq:broker"]
+    }
 
-        for region in regions {
-            println!("Fetching ElastiCache clusters from {}...", region);
-            let config = create_config(profile, region).await;
-            let client = ElastiCacheClient::new(&config);
-            let mut clusters_stream = client.describe_cache_clusters().into_paginator().send();
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["mq:ListBrokers", "mq:DescribeBroker"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching Amazon MQ brokers from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_mq, ctx, &config);
+            let mut stream = client.list_brokers().into_paginator().send();
 
             let mut count = 0;
-            while let Some(result) = clusters_stream.next().await {
-                for cluster in result?.cache_clusters.unwrap_or_default() {
-                    let arn = cluster.arn.clone().unwrap_or_default();
-                    let tags_output = client.list_tags_for_resource().resource_name(&arn).send().await?;
-                    let tags: HashMap<_, _> = tags_output
-                        .tag_list
-                        .unwrap_or_default()
-                        .into_iter()
-                        .map(|t| (t.key.unwrap_or_default(), t.value.unwrap_or_default()))
+            'pages: while let Some(result) = stream.next().await {
+                for summary in result?.broker_summaries.unwrap_or_default() {
+                    let broker_id = summary.broker_id.clone().unwrap_or_default();
+                    // Endpoints, instance addresses and tags are only returned by describe_broker.
+                    let broker = match client.describe_broker().broker_id(&broker_id).send().await {
+                        Ok(broker) => broker,
+                        Err(e) => {
+                            partial_failure!(ctx, log, "could not describe Amazon MQ broker {} in {}: {}", broker_id, region, e);
+                            continue;
+                        }
+                    };
+                    let raw_response = ctx.raw_response(&broker);
+                    let instances = broker.broker_instances.clone().unwrap_or_default();
+                    let endpoints: Vec<String> = instances.iter().flat_map(|i| i.endpoints.clone().unwrap_or_default()).collect();
+
+                    // Instance addresses are reported directly; endpoint names are
+                    // resolved only on request, like other services' endpoints.
+                    let mut ips: Vec<IpAddr> = instances
+                        .iter()
+                        .filter_map(|i| i.ip_address.as_deref()?.parse().ok())
                         .collect();
+                    if self.resolve_endpoints {
+                        let hosts: BTreeSet<&str> = endpoints.iter().map(|e| Self::endpoint_host(e)).collect();
+                        for host in hosts {
+                            ips.extend(resolve_endpoint(host, &mut log).await);
+                        }
+                    }
+                    ips.sort();
+                    ips.dedup();
+
+                    all_resources.push(CollectedResource {
+                        id: broker.broker_arn.clone().or_else(|| summary.broker_arn.clone()).unwrap_or(broker_id.clone()),
+                        name: broker.broker_name.clone().or_else(|| summary.broker_name.clone()).unwrap_or(broker_id),
+                        resource_type: "mq:broker".to_string(),
+                        region: region.to_string(),
+                        ips,
+                        tags: broker.tags.clone().unwrap_or_default(),
+                        details: serde_json::json!({
+                            "engine_type": broker.engine_type.as_ref().map(|t| t.as_str().to_string()),
+                            "engine_version": broker.engine_version,
+                            "deployment_mode": broker.deployment_mode.as_ref().map(|m| m.as_str().to_string()),
+                            "host_instance_type": broker.host_instance_type,
+                            "state": broker.broker_state.as_ref().map(|s| s.as_str().to_string()),
+                            "publicly_accessible": broker.publicly_accessible,
+                            "endpoints": endpoints,
+                            "created_at": broker.created.as_ref().and_then(created_at),
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} Amazon MQ brokers in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct StorageGatewayCollector;
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for StorageGatewayCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["storagegateway:gateway"]
+    }
 
-                    let ips = Vec::new();
-                    if let Some(nodes) = cluster.cache_nodes {
-                        for node in nodes {
-                            if let Some(endpoint) = node.endpoint {
-                                if let Some(_address) = endpoint.address {
-                                    // This is a hostname, not an IP. The prompt is wrong.
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["storagegateway:ListGateways", "storagegateway:DescribeGatewayInformation", "storagegateway:ListTagsForResource"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching Storage Gateways from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_storagegateway, ctx, &config);
+            let mut stream = client.list_gateways().into_paginator().send();
+
+            let mut count = 0;
+            'pages: while let Some(result) = stream.next().await {
+                for gateway in result?.gateways.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&gateway);
+                    let gateway_arn = gateway.gateway_arn.clone().unwrap_or_default();
+
+                    // The gateway's addresses are on the on-premises side (or its EC2 host),
+                    // which is what shows up in VPN flow logs.
+                    let info = match client.describe_gateway_information().gateway_arn(&gateway_arn).send().await {
+                        Ok(info) => Some(info),
+                        Err(e) => {
+                            warning!(log, "Could not describe Storage Gateway {}: {}", gateway_arn, e);
+                            None
+                        }
+                    };
+                    let mut ips: Vec<IpAddr> = Vec::new();
+                    for interface in info.as_ref().and_then(|i| i.gateway_network_interfaces.clone()).unwrap_or_default() {
+                        for address in [interface.ipv4_address, interface.ipv6_address].into_iter().flatten() {
+                            if let Ok(ip) = address.parse() {
+                                if !ips.contains(&ip) {
+                                    ips.push(ip);
                                 }
                             }
                         }
                     }
 
+                    let tags: HashMap<_, _> = match client.list_tags_for_resource().resource_arn(&gateway_arn).send().await {
+                        Ok(output) => output.tags.unwrap_or_default().into_iter().map(|t| (t.key, t.value)).collect(),
+                        Err(e) => {
+                            warning!(log, "Could not get tags for Storage Gateway {}: {}", gateway_arn, e);
+                            HashMap::new()
+                        }
+                    };
+
+                    let gateway_id = gateway.gateway_id.clone().unwrap_or_default();
+                    let name = gateway.gateway_name.clone().unwrap_or_else(|| gateway_id.clone());
+                    let info = info.as_ref();
+
                     all_resources.push(CollectedResource {
-                        arn,
-                        name: cluster.cache_cluster_id.clone().unwrap_or_default(),
-                        resource_type: "elasticache:cluster".to_string(),
+                        id: if gateway_arn.is_empty() { gateway_id.clone() } else { gateway_arn },
+                        name,
+                        resource_type: "storagegateway:gateway".to_string(),
                         region: region.to_string(),
                         ips,
                         tags,
                         details: serde_json::json!({
-                            "engine": cluster.engine,
-                            "engine_version": cluster.engine_version,
-                            "cache_node_type": cluster.cache_node_type,
+                            "gateway_id": gateway_id,
+                            "gateway_type": gateway.gateway_type,
+                            "operational_state": gateway.gateway_operational_state,
+                            "software_version": info.and_then(|i| i.software_version.clone()).or_else(|| gateway.software_version.clone()),
+                            "host_environment": info
+                                .and_then(|i| i.host_environment.as_ref())
+                                .or(gateway.host_environment.as_ref())
+                                .map(|h| h.as_str().to_string()),
+                            "endpoint_type": info.and_then(|i| i.endpoint_type.clone()),
+                            "vpc_endpoint": info.and_then(|i| i.vpc_endpoint.clone()),
+                            "ec2_instance_id": gateway.ec2_instance_id,
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} Storage Gateways in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct SnsCollector;
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for SnsCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["sns:topic"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["sns:ListTopics", "sns:GetTopicAttributes", "sns:ListTagsForResource"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching SNS topics from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_sns, ctx, &config);
+            let mut stream = client.list_topics().into_paginator().send();
+
+            let mut count = 0;
+            'pages: while let Some(result) = stream.next().await {
+                for topic in result?.topics.unwrap_or_default() {
+                    let Some(topic_arn) = topic.topic_arn else { continue };
+
+                    let (attributes, raw_response) = match client.get_topic_attributes().topic_arn(&topic_arn).send().await {
+                        Ok(output) => {
+                            let raw_response = ctx.raw_response(&output);
+                            (output.attributes.unwrap_or_default(), raw_response)
+                        }
+                        Err(e) => {
+                            warning!(log, "could not get attributes for SNS topic {}: {}", topic_arn, e);
+                            (HashMap::new(), None)
+                        }
+                    };
+
+                    let tags: HashMap<_, _> = match client.list_tags_for_resource().resource_arn(&topic_arn).send().await {
+                        Ok(output) => output.tags.unwrap_or_default().into_iter().map(|t| (t.key, t.value)).collect(),
+                        Err(e) => {
+                            warning!(log, "could not get tags for SNS topic {}: {}", topic_arn, e);
+                            HashMap::new()
+                        }
+                    };
+
+                    let count_of = |key: &str| attributes.get(key).and_then(|v| v.parse::<u64>().ok());
+                    let kms_key_id = attributes.get("KmsMasterKeyId").filter(|k| !k.is_empty());
+                    let name = topic_arn.rsplit(':').next().unwrap_or_default().to_string();
+
+                    all_resources.push(CollectedResource {
+                        id: topic_arn.clone(),
+                        name,
+                        resource_type: "sns:topic".to_string(),
+                        region: region.to_string(),
+                        ips: vec![],
+                        tags,
+                        details: serde_json::json!({
+                            "display_name": attributes.get("DisplayName").filter(|n| !n.is_empty()),
+                            "fifo": attributes.get("FifoTopic").is_some_and(|v| v == "true"),
+                            "subscriptions_confirmed": count_of("SubscriptionsConfirmed"),
+                            "subscriptions_pending": count_of("SubscriptionsPending"),
+                            "encrypted": kms_key_id.is_some(),
+                            "kms_key_id": kms_key_id,
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} SNS topics in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct SqsCollector;
+
+impl SqsCollector {
+    /// The queue's ARN built from its URL (`https://sqs.<region>.amazonaws.com/<account>/<name>`),
+    /// for a queue whose attributes can't be read, so that it keeps the same id
+    /// as when they can.
+    fn arn_from_url(region: &str, queue_url: &str) -> String {
+        let mut segments = queue_url.trim_end_matches('/').rsplit('/');
+        let name = segments.next().unwrap_or_default();
+        let account_id = segments.next().unwrap_or_default();
+        format!("arn:aws:sqs:{}:{}:{}", region, account_id, name)
+    }
+}
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for SqsCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["sqs:queue"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &["sqs:ListQueues", "sqs:GetQueueAttributes", "sqs:ListQueueTags"]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, regions: &[String]) -> Result<Vec<CollectedResource>> {
+        use aws_sdk_sqs::types::QueueAttributeName;
+
+        scan_regions(ctx, regions, |region| async move {
+            let mut all_resources = Vec::new();
+            let mut log = RegionLog::default();
+            progress!(log, "Fetching SQS queues from {}...", region);
+            let config = create_config(ctx, region).await;
+            let client = new_client!(aws_sdk_sqs, ctx, &config);
+            let mut stream = client.list_queues().into_paginator().send();
+
+            let mut count = 0;
+            'pages: while let Some(result) = stream.next().await {
+                for queue_url in result?.queue_urls.unwrap_or_default() {
+                    let (attributes, raw_response) = match client
+                        .get_queue_attributes()
+                        .queue_url(&queue_url)
+                        .attribute_names(QueueAttributeName::All)
+                        .send()
+                        .await
+                    {
+                        Ok(output) => {
+                            let raw_response = ctx.raw_response(&output);
+                            (output.attributes.unwrap_or_default(), raw_response)
+                        }
+                        Err(e) => {
+                            partial_failure!(ctx, log, "could not get attributes for SQS queue {}: {}", queue_url, e);
+                            (HashMap::new(), None)
+                        }
+                    };
+
+                    let tags = match client.list_queue_tags().queue_url(&queue_url).send().await {
+                        Ok(output) => output.tags.unwrap_or_default(),
+                        Err(e) => {
+                            warning!(log, "could not get tags for SQS queue {}: {}", queue_url, e);
+                            HashMap::new()
+                        }
+                    };
+
+                    let attribute = |name: QueueAttributeName| attributes.get(&name).filter(|v| !v.is_empty());
+                    let kms_key_id = attribute(QueueAttributeName::KmsMasterKeyId);
+                    // SSE-KMS takes precedence; SSE-SQS is the service-managed default.
+                    let encryption = if kms_key_id.is_some() {
+                        Some("sse-kms")
+                    } else if attribute(QueueAttributeName::SqsManagedSseEnabled).is_some_and(|v| v == "true") {
+                        Some("sse-sqs")
+                    } else {
+                        None
+                    };
+                    let fifo = attribute(QueueAttributeName::FifoQueue).is_some_and(|v| v == "true");
+                    let created = attribute(QueueAttributeName::CreatedTimestamp)
+                        .and_then(|secs| secs.parse().ok())
+                        .and_then(|secs| created_at(&aws_smithy_types::DateTime::from_secs(secs)));
+                    let name = queue_url.rsplit('/').next().unwrap_or_default().to_string();
+
+                    all_resources.push(CollectedResource {
+                        id: attribute(QueueAttributeName::QueueArn).cloned().unwrap_or_else(|| Self::arn_from_url(region, &queue_url)),
+                        name,
+                        resource_type: "sqs:queue".to_string(),
+                        region: region.to_string(),
+                        ips: vec![],
+                        tags,
+                        details: serde_json::json!({
+                            "queue_url": queue_url,
+                            "queue_type": if fifo { "fifo" } else { "standard" },
+                            "encryption": encryption,
+                            "kms_key_id": kms_key_id,
+                            "visibility_timeout": attribute(QueueAttributeName::VisibilityTimeout).and_then(|v| v.parse::<u64>().ok()),
+                            "message_retention_period": attribute(QueueAttributeName::MessageRetentionPeriod).and_then(|v| v.parse::<u64>().ok()),
+                            "approximate_messages": attribute(QueueAttributeName::ApproximateNumberOfMessages).and_then(|v| v.parse::<u64>().ok()),
+                            "has_dead_letter_queue": attribute(QueueAttributeName::RedrivePolicy).is_some(),
+                            "created_at": created,
                         }),
+                        raw_response,
+                        ..Default::default()
                     });
                     count += 1;
+                    if ctx.sample_reached(count) {
+                        break 'pages;
+                    }
+                }
+            }
+            progress!(log, "  -> Found {} SQS queues in {}.", count, region);
+            Ok((all_resources, log))
+        })
+        .await
+    }
+}
+
+pub struct OrgStructureCollector;
+
+#[async_trait::async_trait]
+impl AwsResourceCollector for OrgStructureCollector {
+    fn resource_types(&self) -> &'static [&'static str] {
+        &["organizations:account", "organizations:ou"]
+    }
+
+    fn required_actions(&self) -> &'static [&'static str] {
+        &[
+            "organizations:ListRoots",
+            "organizations:ListOrganizationalUnitsForParent",
+            "organizations:ListAccountsForParent",
+        ]
+    }
+
+    async fn collect(&self, ctx: &ScanContext, _regions: &[String]) -> Result<Vec<CollectedResource>> {
+        // Organizations is global and only answers in the management account
+        // (or a delegated administrator).
+        info!("Fetching AWS Organizations structure (global service)...");
+        let config = create_config(ctx, "us-east-1").await;
+        let client = new_client!(aws_sdk_organizations, ctx, &config);
+        let mut all_resources = Vec::new();
+
+        let mut parents = VecDeque::new();
+        let mut roots_stream = client.list_roots().into_paginator().send();
+        while let Some(result) = roots_stream.next().await {
+            match result {
+                Ok(output) => parents.extend(output.roots.unwrap_or_default().into_iter().filter_map(|r| r.id)),
+                // Only a refusal means this isn't the management account. Anything
+                // else (throttling, expired credentials) fails the collector so the
+                // organization isn't pruned as if it were empty.
+                Err(e)
+                    if e.as_service_error().is_some_and(|err| {
+                        err.is_access_denied_exception() || err.is_aws_organizations_not_in_use_exception()
+                    }) =>
+                {
+                    info!(
+                        "  -> Skipping: this account can't read the organization ({}). Scan the management account to collect it.",
+                        aws_sdk_organizations::error::DisplayErrorContext(e)
+                    );
+                    return Ok(all_resources);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        // Walk the OU tree breadth first from the roots.
+        let (mut ou_count, mut account_count) = (0, 0);
+        while let Some(parent_id) = parents.pop_front() {
+            let mut ous_stream = client.list_organizational_units_for_parent().parent_id(&parent_id).into_paginator().send();
+            while let Some(result) = ous_stream.next().await {
+                for ou in result?.organizational_units.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&ou);
+                    let ou_id = ou.id.clone().unwrap_or_default();
+                    parents.push_back(ou_id.clone());
+                    all_resources.push(CollectedResource {
+                        id: ou.arn.clone().unwrap_or_else(|| ou_id.clone()),
+                        name: ou.name.clone().unwrap_or_else(|| ou_id.clone()),
+                        resource_type: "organizations:ou".to_string(),
+                        region: "global".to_string(),
+                        details: serde_json::json!({
+                            "ou_id": ou_id,
+                            "parent_id": parent_id,
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    ou_count += 1;
+                }
+            }
+
+            let mut accounts_stream = client.list_accounts_for_parent().parent_id(&parent_id).into_paginator().send();
+            while let Some(result) = accounts_stream.next().await {
+                for account in result?.accounts.unwrap_or_default() {
+                    let raw_response = ctx.raw_response(&account);
+                    let account_id = account.id.clone().unwrap_or_default();
+                    all_resources.push(CollectedResource {
+                        id: account.arn.clone().unwrap_or_else(|| account_id.clone()),
+                        name: account.name.clone().unwrap_or_else(|| account_id.clone()),
+                        resource_type: "organizations:account".to_string(),
+                        region: "global".to_string(),
+                        details: serde_json::json!({
+                            "account_id": account_id,
+                            "email": account.email,
+                            "status": account.status.as_ref().map(|s| s.as_str().to_string()),
+                            "joined_method": account.joined_method.as_ref().map(|m| m.as_str().to_string()),
+                            "joined_at": account.joined_timestamp.as_ref().and_then(created_at),
+                            "parent_id": parent_id,
+                        }),
+                        raw_response,
+                        ..Default::default()
+                    });
+                    account_count += 1;
                 }
             }
-            println!("  -> Found {} clusters in {}.", count, region);
         }
+        info!("  -> Found {} organizational units and {} accounts.", ou_count, account_count);
+
         Ok(all_resources)
     }
-}
\ No newline at end of file
+}
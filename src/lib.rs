@@ -1,7 +1,12 @@
+pub mod audit;
 pub mod config;
 pub mod db;
+pub mod diff;
 pub mod export;
 pub mod identify;
 pub mod inventory;
 pub mod query;
+pub mod reader;
+pub mod scan;
 pub mod server;
+pub mod throttle;
@@ -1,23 +1,56 @@
 use anyhow::Result;
-use aws_inventory_sdk::{config, export, identify, inventory, server};
+use aws_inventory_sdk::{config, export, identify, inventory, scan, server};
 use std::net::IpAddr;
 use std::env;
 use std::path::PathBuf;
+use std::time::Duration;
 use structopt::StructOpt;
+use tracing::{error, warn};
+use tracing_subscriber::EnvFilter;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "aws-inventory", about = "AWS inventory tool using the SDK")]
+struct Cli {
+    #[structopt(short, long, global = true, parse(from_occurrences), help = "Log more detail to stderr: -v for debug, -vv for trace. RUST_LOG overrides this.")]
+    verbose: u8,
+
+    #[structopt(subcommand)]
+    command: Opt,
+}
+
+#[derive(Debug, StructOpt)]
 enum Opt {
     Inventory {
-        #[structopt(long)]
-        profile: Option<String>,
+        #[structopt(long, use_delimiter = true, help = "AWS profile(s) to scan. Several profiles can be given as a comma-separated list.")]
+        profile: Vec<String>,
 
         #[structopt(long, use_delimiter = true)]
         regions: Vec<String>,
 
-        #[structopt(long, help = "Path to the inventory database file. Defaults to 'aws_inventory.db' next to the executable.")]
+        #[structopt(long, use_delimiter = true, help = "Regions to leave out, applied after --regions (including 'all') and the profile/region matrix.")]
+        exclude_regions: Vec<String>,
+
+        #[structopt(long, help = "JSON file mapping each profile to its own list of regions. Profiles missing from the file use --regions.")]
+        profile_region_matrix: Option<PathBuf>,
+
+        #[structopt(long, use_delimiter = true, help = "Role ARNs to assume in order, each using the credentials from the previous hop (e.g. hub role, then member role).")]
+        assume_role_chain: Vec<String>,
+
+        #[structopt(long, conflicts_with = "assume-role-chain", help = "Role ARN to assume for cross-account scans, using the --profile credentials as the source. Shorthand for a one-role --assume-role-chain.")]
+        assume_role_arn: Option<String>,
+
+        #[structopt(long, help = "External id to pass when assuming the role (the last role of --assume-role-chain).")]
+        external_id: Option<String>,
+
+        #[structopt(long, help = "Path to the inventory database file. Defaults to 'aws_inventory.db' next to the executable. Use ':memory:' for an ephemeral scan together with --export-hosts or --print-results.")]
         output: Option<PathBuf>,
 
+        #[structopt(long, conflicts_with = "watch", help = "After collecting, write a hosts file from the inventory to this path.")]
+        export_hosts: Option<PathBuf>,
+
+        #[structopt(long, conflicts_with = "watch", help = "After collecting, print every resource in the inventory as JSON, like 'query' does.")]
+        print_results: bool,
+
         #[structopt(long, use_delimiter = true, help = "Specific services to inventory (e.g., ec2,elb,rds). Defaults to 'ec2' if --all-services is not used.")]
         services: Vec<String>,
 
@@ -29,6 +62,69 @@ enum Opt {
 
         #[structopt(long, use_delimiter = true, help = "Specific EKS clusters to scan (optional)")]
         eks_clusters: Vec<String>,
+
+        #[structopt(long, help = "Keep EC2 instances that are terminated or shutting down, which are skipped by default")]
+        include_terminated: bool,
+
+        #[structopt(long, alias = "resolve-dns", help = "Resolve endpoint hostnames (RDS, ElastiCache, VPC Lattice, Amazon MQ) to IP addresses so they can be identified.")]
+        resolve_endpoints: bool,
+
+        #[structopt(long, help = "Include Macie finding counts in the 'inspector' findings summary.")]
+        with_macie: bool,
+
+        #[structopt(long, help = "Time a quick API call to each region first and scan the fastest regions first.")]
+        sort_regions_by_latency: bool,
+
+        #[structopt(long, default_value = "4", help = "How many regions each collector scans at once.")]
+        region_concurrency: usize,
+
+        #[structopt(long, default_value = "4", help = "How many collectors run at once for each profile.")]
+        collector_concurrency: usize,
+
+        #[structopt(long, default_value = "8", help = "How many per-resource describe calls (DynamoDB tables, load balancer attributes) each collector makes at once in a region.")]
+        describe_concurrency: usize,
+
+        #[structopt(long, help = "Collect at most N resources per service and region, for quick smoke tests.")]
+        sample: Option<usize>,
+
+        #[structopt(long, help = "Retry each AWS call up to N times on throttling and transient errors, with exponential backoff. Defaults to the SDK's 2.")]
+        max_retries: Option<u32>,
+
+        #[structopt(long, help = "Print an approximate API call count per service instead of scanning.")]
+        estimate: bool,
+
+        #[structopt(long, help = "Check with IAM whether the credentials may call each selected service's APIs, instead of scanning.")]
+        check_permissions: bool,
+
+        #[structopt(long, help = "Add each resource's estimated monthly cost from Cost Explorer, attributed by --cost-tag. Cost Explorer API calls are billed.")]
+        with_costs: bool,
+
+        #[structopt(long, default_value = "Name", help = "Cost-allocation tag used to match Cost Explorer costs to resources")]
+        cost_tag: String,
+
+        #[structopt(long, conflicts_with = "watch", help = "Speed up saving by disabling journaling and syncing during the scan. The database may be corrupted if the process or machine crashes mid-scan, so only use it for databases you can regenerate.")]
+        fast_import: bool,
+
+        #[structopt(long, help = "After a successful scan, delete resources in the scanned regions, services and accounts that were not seen by it. Resources outside the scan are kept. Always on with --watch.")]
+        prune: bool,
+
+        #[structopt(long, alias = "merge-details", help = "Merge collected details into existing rows for the same id (new fields win) instead of replacing them, so enrichment passes keep earlier fields.")]
+        enrich: bool,
+
+        #[structopt(long, help = "Before saving, warn about resources whose ARN is malformed and count those that only have a non-ARN id (e.g. EKS pods).")]
+        validate_arns: bool,
+
+        #[structopt(long, help = "Also store the full SDK response behind each resource in a raw_responses table, for debugging collectors. Makes the database much larger.")]
+        store_raw: bool,
+
+        #[structopt(long, help = "Label stored with every collected resource (e.g. nightly, manual), to tell sources apart in a shared database.")]
+        source_label: Option<String>,
+
+        #[structopt(long, parse(try_from_str = parse_interval), help = "Re-run the inventory on an interval (e.g. 90s, 5m, 1h), pruning resources that disappear.")]
+        watch: Option<Duration>,
+
+        #[structopt(long, help = "Append a JSON line for every AWS API operation (timestamp, profile, region, service, operation) to this file.")]
+        json_logs_to: Option<PathBuf>,
     },
     Query {
         #[structopt(long, help = "Path to the inventory database file. Defaults to 'aws_inventory.db' next to the executable.")]
@@ -40,15 +136,126 @@ enum Opt {
         #[structopt(long, short, use_delimiter = true)]
         regions: Vec<String>,
 
-        #[structopt(long)]
+        #[structopt(long, use_delimiter = true, help = "Only return resources referencing these security group ids (e.g. sg-0abc123)")]
+        security_group: Vec<String>,
+
+        #[structopt(long, use_delimiter = true, help = "Only return resources saved with these --source-label values")]
+        source: Vec<String>,
+
+        #[structopt(long, number_of_values = 1, help = "Only return resources with this tag, as key=value or just key for any value; repeat to require several")]
+        tag: Vec<String>,
+
+        #[structopt(long, help = "Only return ECS tasks and EKS pods running a container image containing this text")]
+        image: Option<String>,
+
+        #[structopt(long, help = "Only return resources whose ARN contains this text, e.g. an account or VPC id")]
+        arn_contains: Option<String>,
+
+        #[structopt(long = "where", number_of_values = 1, help = "Only return resources whose details match, e.g. 'utilization>80'. Supports = != > >= < <=; repeat to combine.")]
+        conditions: Vec<aws_inventory_sdk::query::DetailCondition>,
+
+        #[structopt(long = "or", number_of_values = 1, parse(try_from_str = aws_inventory_sdk::query::parse_filter_group), help = "Alternative filter group such as 'services=ec2 regions=us-east-1'; repeat to match resources satisfying any group. Other filters still apply to every result.")]
+        any_of: Vec<aws_inventory_sdk::query::QueryFilter>,
+
+        #[structopt(long, parse(try_from_str = aws_inventory_sdk::query::parse_created_bound), help = "Only return resources created at or after this date, timestamp or age (e.g. 2024-06-01, 7d)")]
+        created_after: Option<String>,
+
+        #[structopt(long, parse(try_from_str = aws_inventory_sdk::query::parse_created_bound), help = "Only return resources created before this date, timestamp or age (e.g. 2024-06-01, 30d)")]
+        created_before: Option<String>,
+
+        #[structopt(long, help = "Output only the de-duplicated IP addresses of the matching resources")]
+        ips_only: bool,
+
+        #[structopt(long, help = "Only return resources with at least one public IP address; with --ips-only, list only public addresses")]
+        public_only: bool,
+
+        #[structopt(long, conflicts_with = "public-only", help = "Only return resources with at least one private IP address; with --ips-only, list only private addresses")]
+        private_only: bool,
+
+        #[structopt(long, default_value = "json", help = "Output format: json, text or yaml")]
+        format: aws_inventory_sdk::query::OutputFormat,
+
+        #[structopt(long, help = "Same as --format text")]
         text: bool,
     },
     Identify {
         #[structopt(long, help = "Path to the inventory database file. Defaults to 'aws_inventory.db' next to the executable.")]
         inventory: Option<PathBuf>,
 
-        #[structopt(name = "IP_ADDRESS")]
-        ip_address: IpAddr,
+        #[structopt(name = "IP_ADDRESS", required_unless_one = &["from-file", "cidr"])]
+        ip_address: Option<IpAddr>,
+
+        #[structopt(long, conflicts_with_all = &["IP_ADDRESS", "from-file"], help = "List every resource with an address inside this network (e.g. 10.0.1.0/24 or 2600:1f18::/56)")]
+        cidr: Option<ipnet::IpNet>,
+
+        #[structopt(long, conflicts_with = "IP_ADDRESS", help = "Identify every IP address in this file, one per line ('-' reads stdin), and print a JSON array of matches")]
+        from_file: Option<PathBuf>,
+
+        #[structopt(long, requires = "from-file", help = "With --from-file, print tab-separated values instead of JSON")]
+        tsv: bool,
+    },
+    Annotate {
+        #[structopt(long, help = "Path to the inventory database file. Defaults to 'aws_inventory.db' next to the executable.")]
+        inventory: Option<PathBuf>,
+
+        #[structopt(name = "ARN", help = "ARN or id of the resource, as shown by query")]
+        arn: String,
+
+        #[structopt(name = "NOTE", required_unless = "clear", help = "Note to attach to the resource. It is kept across rescans.")]
+        note: Option<String>,
+
+        #[structopt(long, conflicts_with = "NOTE", help = "Remove the note from the resource")]
+        clear: bool,
+    },
+    Duplicates {
+        #[structopt(long, help = "Path to the inventory database file. Defaults to 'aws_inventory.db' next to the executable.")]
+        inventory: Option<PathBuf>,
+
+        #[structopt(long)]
+        text: bool,
+    },
+    CompareAccounts {
+        #[structopt(long, help = "Path to the inventory database file. Defaults to 'aws_inventory.db' next to the executable.")]
+        inventory: Option<PathBuf>,
+
+        #[structopt(long)]
+        text: bool,
+    },
+    Stats {
+        #[structopt(long, help = "Path to the inventory database file. Defaults to 'aws_inventory.db' next to the executable.")]
+        inventory: Option<PathBuf>,
+
+        #[structopt(long, use_delimiter = true, help = "Tag keys every resource should carry (e.g. Owner,CostCenter); reports the share of resources with each, per resource type and account")]
+        required_tags: Vec<String>,
+
+        #[structopt(long, help = "Print the statistics as JSON instead of tables")]
+        json: bool,
+    },
+    Diff {
+        #[structopt(long, help = "The earlier inventory database")]
+        old: PathBuf,
+
+        #[structopt(long, help = "The later inventory database")]
+        new: PathBuf,
+
+        #[structopt(long, help = "Print the differences as JSON")]
+        json: bool,
+
+        #[structopt(long, conflicts_with = "json", help = "Print only changed resources, each with the RFC 6902 JSON Patch from its old to its new state")]
+        patch: bool,
+    },
+    Trends {
+        #[structopt(long, help = "Path to the inventory database file. Defaults to 'aws_inventory.db' next to the executable.")]
+        inventory: Option<PathBuf>,
+
+        #[structopt(long, short, use_delimiter = true)]
+        services: Vec<String>,
+
+        #[structopt(long, short, use_delimiter = true)]
+        regions: Vec<String>,
+
+        #[structopt(long, help = "Print the recorded counts as CSV instead of text charts")]
+        csv: bool,
     },
     ExportHosts {
         #[structopt(long, help = "Path to the inventory database file. Defaults to 'aws_inventory.db' next to the executable.")]
@@ -56,6 +263,60 @@ enum Opt {
 
         #[structopt(long, short, default_value = "hosts.txt")]
         output: PathBuf,
+
+        #[structopt(long, help = "Write one hosts file per value of this tag (e.g. hosts.prod), plus hosts.untagged")]
+        split_by_tag: Option<String>,
+
+        #[structopt(long, short, use_delimiter = true)]
+        services: Vec<String>,
+
+        #[structopt(long, short, use_delimiter = true)]
+        regions: Vec<String>,
+
+        #[structopt(long, number_of_values = 1, help = "Only export resources with this tag, as key=value or just key for any value; repeat to require several")]
+        tag: Vec<String>,
+
+        #[structopt(long, help = "Format of each line, e.g. '{ip} {name}.{region}'. Placeholders: {ip} {hostname} {name} {id} {region} {resource_type}; \\t is a tab. Defaults to '{ip}\\t{hostname}'.")]
+        template: Option<String>,
+    },
+    ExportCsv {
+        #[structopt(long, help = "Path to the inventory database file. Defaults to 'aws_inventory.db' next to the executable.")]
+        inventory: Option<PathBuf>,
+
+        #[structopt(long, short, use_delimiter = true)]
+        services: Vec<String>,
+
+        #[structopt(long, short, use_delimiter = true)]
+        regions: Vec<String>,
+
+        #[structopt(long, short, default_value = "inventory.csv")]
+        output: PathBuf,
+
+        #[structopt(long, help = "Replace account ids, including those inside ARNs, with consistent pseudonyms such as account-1. Names and tags are not scrubbed.")]
+        anonymize_accounts: bool,
+    },
+    ExportRunbook {
+        #[structopt(long, help = "Path to the inventory database file. Defaults to 'aws_inventory.db' next to the executable.")]
+        inventory: Option<PathBuf>,
+
+        #[structopt(long, short, default_value = "inventory-runbook.md")]
+        output: PathBuf,
+
+        #[structopt(long, help = "Replace account ids, including those inside ARNs, with consistent pseudonyms such as account-1. Names and tags are not scrubbed.")]
+        anonymize_accounts: bool,
+    },
+    ExportJsonl {
+        #[structopt(long, help = "Path to the inventory database file. Defaults to 'aws_inventory.db' next to the executable.")]
+        inventory: Option<PathBuf>,
+
+        #[structopt(long, short, default_value = "inventory.ndjson")]
+        output: PathBuf,
+
+        #[structopt(long, help = "Flatten details and IPs into the same typed top-level columns on every row, for data warehouse ingestion")]
+        flatten: bool,
+
+        #[structopt(long, help = "Replace account ids, including those inside ARNs, with consistent pseudonyms such as account-1. Names and tags are not scrubbed.")]
+        anonymize_accounts: bool,
     },
     Serve {
         #[structopt(long, help = "Path to the inventory database file. Defaults to 'aws_inventory.db' next to the executable.")]
@@ -66,6 +327,9 @@ enum Opt {
 
         #[structopt(long, help = "Do not open the web browser automatically")]
         no_browser: bool,
+
+        #[structopt(long, env = "AWS_INVENTORY_TOKEN", hide_env_values = true, help = "Require 'Authorization: Bearer <token>' on /api/* requests")]
+        auth_token: Option<String>,
     },
 }
 
@@ -79,133 +343,487 @@ fn get_default_db_path() -> Result<PathBuf> {
     Ok(path)
 }
 
+/// Parses an interval such as `30s`, `5m` or `1h`. A bare number is taken as seconds.
+fn parse_interval(s: &str) -> Result<Duration> {
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(idx) => s.split_at(idx),
+        None => (s, "s"),
+    };
+    let value: u64 = number
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid interval '{}'", s))?;
+    let secs = match unit {
+        "s" => Some(value),
+        "m" => value.checked_mul(60),
+        "h" => value.checked_mul(3600),
+        other => anyhow::bail!("Unknown interval unit '{}', expected s, m or h", other),
+    }
+    .ok_or_else(|| anyhow::anyhow!("Interval '{}' is too long", s))?;
+    if secs == 0 {
+        anyhow::bail!("Interval must be greater than zero");
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+fn print_throttle_summary(throttle_events: usize) {
+    if throttle_events > 0 {
+        println!("AWS throttled {} requests; they were retried with backoff.", throttle_events);
+    }
+}
+
+/// Sends log lines to stderr, leaving stdout to command output. `RUST_LOG`
+/// takes precedence over `--verbose`; other crates only log warnings unless it
+/// says otherwise.
+fn init_logging(verbose: u8) {
+    let level = match verbose {
+        0 => "info",
+        1 => "debug",
+        _ => "trace",
+    };
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(format!("warn,aws_inventory_sdk={}", level)));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_target(false)
+        .without_time()
+        .init();
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    let opt = Opt::from_args();
+    let cli = Cli::from_args();
+    init_logging(cli.verbose);
+    let opt = cli.command;
+    let file_config = config::load_file_config()?;
 
     match opt {
         Opt::Inventory {
             profile,
             regions,
+            exclude_regions,
+            profile_region_matrix,
+            assume_role_chain,
+            assume_role_arn,
+            external_id,
             output,
+            export_hosts,
+            print_results,
             services,
             all_services,
             no_eks,
             eks_clusters,
+            include_terminated,
+            resolve_endpoints,
+            with_macie,
+            sort_regions_by_latency,
+            region_concurrency,
+            collector_concurrency,
+            describe_concurrency,
+            sample,
+            max_retries,
+            estimate,
+            check_permissions,
+            source_label,
+            with_costs,
+            cost_tag,
+            fast_import,
+            prune,
+            store_raw,
+            enrich,
+            validate_arns,
+            watch,
+            json_logs_to,
         } => {
-            let output = match output {
+            // Flags win over aws-inventory.toml; a list flag counts as given
+            // when it has any values.
+            let profile = if profile.is_empty() { file_config.profile } else { profile };
+            let regions = if regions.is_empty() { file_config.regions } else { regions };
+            let services = if services.is_empty() && !all_services { file_config.services } else { services };
+            let assume_role_chain = if !assume_role_chain.is_empty() {
+                assume_role_chain
+            } else if let Some(role_arn) = assume_role_arn.or(file_config.assume_role_arn) {
+                vec![role_arn]
+            } else {
+                file_config.assume_role_chain
+            };
+            let external_id = external_id.or(file_config.external_id);
+            let output = match output.or(file_config.output) {
                 Some(path) => path,
                 None => get_default_db_path()?,
             };
+            // An in-memory inventory is gone when the process exits, so it is only
+            // useful when consumed by a follow-up step of this same run.
+            let in_memory = output.as_os_str() == ":memory:";
+            // Each --watch run should leave the database matching AWS, so it prunes too.
+            let prune = prune || watch.is_some();
+            if in_memory && export_hosts.is_none() && !print_results && !estimate && !check_permissions {
+                anyhow::bail!("--output :memory: keeps nothing after the scan; combine it with --export-hosts or --print-results");
+            }
 
-            let regions_to_scan = if regions.iter().any(|r| r == "all") {
-                config::get_available_regions()
-                    .iter()
-                    .map(|s| s.to_string())
-                    .collect()
-            } else {
-                regions
+            let options = scan::ScanOptions {
+                profiles: profile,
+                regions,
+                exclude_regions,
+                profile_region_matrix,
+                assume_role_chain,
+                external_id,
+                output: output.clone(),
+                services,
+                all_services,
+                no_eks,
+                eks_clusters,
+                include_terminated,
+                resolve_endpoints,
+                with_macie,
+                sort_regions_by_latency,
+                region_concurrency,
+                collector_concurrency,
+                describe_concurrency,
+                sample,
+                max_retries,
+                cost_tag: if with_costs { Some(cost_tag) } else { None },
+                fast_import,
+                prune,
+                enrich,
+                validate_arns,
+                store_raw,
+                source_label,
+                json_logs_to,
             };
+            let plan = scan::ScanPlan::new(&options).await?;
+            let (collectors, targets) = (&plan.collectors, &plan.targets);
+
+            if check_permissions {
+                println!("\n--- Checking Permissions ---");
+                let mut actions: Vec<&str> = collectors.iter().flat_map(|(_, c)| c.required_actions().iter().copied()).collect();
+                actions.sort();
+                actions.dedup();
+                for (ctx, _) in targets {
+                    if !ctx.profile.is_empty() {
+                        println!("Profile '{}':", ctx.profile);
+                    }
+                    let denied = match inventory::denied_actions(ctx, &actions).await {
+                        Ok(denied) => denied,
+                        Err(e) => {
+                            eprintln!("  Could not check permissions: {}", e);
+                            continue;
+                        }
+                    };
+                    for (service, collector) in collectors {
+                        let missing: Vec<&str> = collector
+                            .required_actions()
+                            .iter()
+                            .copied()
+                            .filter(|a| denied.iter().any(|d| d == a))
+                            .collect();
+                        if missing.is_empty() {
+                            println!("  {:<14} allowed", service);
+                        } else {
+                            println!("  {:<14} DENIED ({})", service, missing.join(", "));
+                        }
+                    }
+                }
+                return Ok(());
+            }
+
+            if estimate {
+                println!("\n--- Estimating API Calls ---");
+                let mut total_calls = 0;
+                for (ctx, regions_to_scan) in targets {
+                    if !ctx.profile.is_empty() {
+                        println!("Profile '{}' ({} region(s)):", ctx.profile, regions_to_scan.len());
+                    }
+                    for (service, collector) in collectors {
+                        match collector.estimate_calls(ctx, regions_to_scan).await {
+                            Ok(calls) => {
+                                println!("  {:<12} ~{} calls", service, calls);
+                                total_calls += calls;
+                            }
+                            Err(e) => eprintln!("  {:<12} could not estimate: {}", service, e),
+                        }
+                    }
+                }
+                println!("Approximately {} API calls in total.", total_calls);
+                return Ok(());
+            }
 
             // Initialize the database
             let mut conn = aws_inventory_sdk::db::init_db(&output)?;
             println!("Using inventory database at: {:?}", output);
-            let profile_name = profile.as_deref().unwrap_or_default();
-            
-            // Dynamically build the list of collectors based on flags
-            let mut collectors: Vec<Box<dyn inventory::AwsResourceCollector>> = Vec::new();
-
-            let mut services_to_run = services;
-            if all_services {
-                // If --all-services is used, populate with all known collectors
-                services_to_run = vec![
-                    "ec2".to_string(), "elb".to_string(), "rds".to_string(),
-                    "dynamodb".to_string(), "elasticache".to_string(), "eks".to_string(),
-                    "route53".to_string()
-                ];
-            } else if services_to_run.is_empty() {
-                // Default to only collecting EC2 if no services are specified
-                services_to_run.push("ec2".to_string());
-            }
-
-            // The --no-eks flag acts as a final override
-            if no_eks {
-                services_to_run.retain(|s| s != "eks");
-            }
-
-            println!("Will collect inventory for: {}", services_to_run.join(", "));
-
-            for service in services_to_run {
-                match service.as_str() {
-                    "ec2" => collectors.push(Box::new(inventory::Ec2Collector)),
-                    "elb" => collectors.push(Box::new(inventory::ElbCollector)),
-                    "rds" => collectors.push(Box::new(inventory::RdsCollector)),
-                    "dynamodb" => collectors.push(Box::new(inventory::DynamoDbCollector)),
-                    "elasticache" => collectors.push(Box::new(inventory::ElastiCacheCollector)),
-                    "eks" => collectors.push(Box::new(inventory::EksCollector::new(eks_clusters.clone()))),
-                    "route53" => collectors.push(Box::new(inventory::Route53Collector)),
-                    other => eprintln!("Warning: Unknown service '{}' specified, skipping.", other),
+
+            if let Some(interval) = watch {
+                // Runs happen inline, so a run that outlasts the interval simply
+                // causes the missed ticks to be skipped rather than overlapping.
+                let mut ticker = tokio::time::interval(interval);
+                ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+                loop {
+                    ticker.tick().await;
+                    println!("\n--- Starting Inventory Collection ({}) ---", aws_inventory_sdk::db::now_timestamp());
+                    match plan.run(&mut conn).await {
+                        Ok(report) => {
+                            if !report.errors.is_empty() {
+                                warn!(
+                                    "Saved {} resources; {} collector(s) failed. Retrying on the next interval.",
+                                    report.resources_saved,
+                                    report.errors.len()
+                                );
+                            } else if prune {
+                                println!("Saved {} resources, pruned {} stale resources.", report.resources_saved, report.resources_pruned);
+                            } else {
+                                println!("Saved {} resources.", report.resources_saved);
+                            }
+                            print_throttle_summary(report.throttle_events);
+                        }
+                        Err(e) => error!("Inventory run failed, will retry on the next interval: {}", e),
+                    }
+                    println!("Next run in {:?}.", interval);
                 }
             }
 
-            let mut total_resources = 0;
             println!("\n--- Starting Inventory Collection ---");
-            for collector in collectors {
-                let resources = collector.collect(profile_name, &regions_to_scan).await?;
-                let count = resources.len();
-                if count > 0 {
-                    println!("  -> Saving {} collected resources to the database...", count);
-                    aws_inventory_sdk::db::save_resources(&mut conn, &resources)?;
-                    total_resources += count;
-                }
-            }
+            let report = plan.run(&mut conn).await?;
 
             println!("\n--- Inventory Complete ---");
-            println!("Discovered and saved a total of {} resources.", total_resources);
-            println!("Inventory database is at {:?}", output);
+            println!("Discovered and saved a total of {} resources.", report.resources_saved);
+            if prune {
+                println!("Pruned {} stale resources.", report.resources_pruned);
+            }
+            print_throttle_summary(report.throttle_events);
+            if !in_memory {
+                println!("Inventory database is at {:?}", output);
+            }
+
+            if let Some(hosts_path) = export_hosts {
+                export::to_hosts_file(&conn, &hosts_path)?;
+                println!("Hosts file exported to {:?}", hosts_path);
+            }
+            if print_results {
+                let results = aws_inventory_sdk::query::run_query_on(&conn, &aws_inventory_sdk::query::QueryFilter::default())?;
+                println!("{}", serde_json::to_string_pretty(&results)?);
+            }
+            if !report.partial_failures.is_empty() {
+                eprintln!("\nCollectors with incomplete results:");
+                for failure in &report.partial_failures {
+                    match failure.profile.as_str() {
+                        "" => eprintln!("  {}: {}", failure.service, failure.message),
+                        profile => eprintln!("  {} (profile '{}'): {}", failure.service, profile, failure.message),
+                    }
+                }
+            }
+            if !report.errors.is_empty() {
+                eprintln!("\nCollectors that failed:");
+                for error in &report.errors {
+                    match error.profile.as_str() {
+                        "" => eprintln!("  {}: {}", error.service, error.message),
+                        profile => eprintln!("  {} (profile '{}'): {}", error.service, profile, error.message),
+                    }
+                }
+                let failed: Vec<&str> = report.errors.iter().map(|e| e.service.as_str()).collect();
+                anyhow::bail!("{} collector(s) failed: {}", failed.len(), failed.join(", "));
+            }
         }
-        Opt::Identify { inventory, ip_address } => {
+        Opt::Identify { inventory, ip_address, cidr, from_file, tsv } => {
             let inventory = match inventory {
                 Some(path) => path,
                 None => get_default_db_path()?,
             };
-            if let Some(result) = identify::identify_resource_from_db(&inventory, ip_address)? {
-                println!("{}", result);
-            } else {
-                println!("IP address not found in inventory.");
+            if let Some(cidr) = cidr {
+                let lines = identify::identify_resources_in_cidr(&inventory, cidr)?;
+                if lines.is_empty() {
+                    println!("No IP addresses in {} found in inventory.", cidr);
+                }
+                for line in lines {
+                    println!("{}", line);
+                }
+            } else if let Some(path) = from_file {
+                let ips = if path.as_os_str() == "-" {
+                    identify::read_ips(std::io::stdin().lock())?
+                } else {
+                    let file = std::fs::File::open(&path)
+                        .map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", path, e))?;
+                    identify::read_ips(std::io::BufReader::new(file))?
+                };
+                let matches = identify::identify_many(&inventory, &ips)?;
+                if tsv {
+                    identify::print_tsv(&matches);
+                } else {
+                    println!("{}", serde_json::to_string_pretty(&matches)?);
+                }
+            } else if let Some(ip_address) = ip_address {
+                if let Some(result) = identify::identify_resource_from_db(&inventory, ip_address)? {
+                    println!("{}", result);
+                } else {
+                    println!("IP address not found in inventory.");
+                }
             }
         }
-        Opt::ExportHosts { inventory, output } => {
+        Opt::Annotate { inventory, arn, note, clear } => {
             let inventory = match inventory {
                 Some(path) => path,
                 None => get_default_db_path()?,
             };
-            export::to_hosts_file_from_db(&inventory, &output)?;
-            println!("Hosts file exported to {:?}", output);
+            let conn = aws_inventory_sdk::db::init_db(&inventory)?;
+            let note = if clear { None } else { note };
+            let known = aws_inventory_sdk::db::set_note(&conn, &arn, note.as_deref())?;
+            if !known {
+                warn!("'{}' is not currently in the inventory; the note will apply once it is collected.", arn);
+            }
+            match note {
+                Some(_) => println!("Note saved for {}", arn),
+                None => println!("Note cleared for {}", arn),
+            }
+        }
+        Opt::ExportHosts { inventory, output, split_by_tag, services, regions, tag, template } => {
+            let inventory = match inventory {
+                Some(path) => path,
+                None => get_default_db_path()?,
+            };
+            let filter = aws_inventory_sdk::query::QueryFilter {
+                services,
+                regions,
+                tags: tag,
+                ..Default::default()
+            };
+            match split_by_tag {
+                Some(tag_key) => {
+                    for path in export::to_hosts_files_split_by_tag(&inventory, &output, &tag_key, &filter, template.as_deref())? {
+                        println!("Hosts file exported to {:?}", path);
+                    }
+                }
+                None => {
+                    export::to_hosts_file_from_db(&inventory, &output, &filter, template.as_deref())?;
+                    println!("Hosts file exported to {:?}", output);
+                }
+            }
+        }
+        Opt::ExportCsv { inventory, services, regions, output, anonymize_accounts } => {
+            let inventory = match inventory {
+                Some(path) => path,
+                None => get_default_db_path()?,
+            };
+            let filter = aws_inventory_sdk::query::QueryFilter {
+                services,
+                regions,
+                ..Default::default()
+            };
+            let count = export::to_csv_from_db(&inventory, &output, &filter, anonymize_accounts)?;
+            println!("Exported {} resources to {:?}", count, output);
+        }
+        Opt::ExportRunbook { inventory, output, anonymize_accounts } => {
+            let inventory = match inventory {
+                Some(path) => path,
+                None => get_default_db_path()?,
+            };
+            let count = export::to_runbook_from_db(&inventory, &output, anonymize_accounts)?;
+            println!("Wrote a runbook of {} resources to {:?}", count, output);
+        }
+        Opt::ExportJsonl { inventory, output, flatten, anonymize_accounts } => {
+            let inventory = match inventory {
+                Some(path) => path,
+                None => get_default_db_path()?,
+            };
+            let count = export::to_ndjson(&inventory, &output, flatten, anonymize_accounts)?;
+            println!("Exported {} resources to {:?}", count, output);
         }
         Opt::Query {
             inventory,
             services,
             regions,
+            security_group,
+            conditions,
+            any_of,
+            created_after,
+            created_before,
+            source,
+            tag,
+            image,
+            arn_contains,
+            ips_only,
+            public_only,
+            private_only,
+            format,
             text,
         } => {
             let inventory = match inventory {
                 Some(path) => path,
                 None => get_default_db_path()?,
             };
-            aws_inventory_sdk::query::query_resources(&inventory, &services, &regions, text)?;
+            let filter = aws_inventory_sdk::query::QueryFilter {
+                services,
+                regions,
+                security_groups: security_group,
+                conditions,
+                created_after,
+                created_before,
+                sources: source,
+                tags: tag,
+                image,
+                arn_contains,
+                any_of,
+                ip_scope: if public_only {
+                    Some(aws_inventory_sdk::query::IpScope::Public)
+                } else if private_only {
+                    Some(aws_inventory_sdk::query::IpScope::Private)
+                } else {
+                    None
+                },
+                ..Default::default()
+            };
+            let format = if text { aws_inventory_sdk::query::OutputFormat::Text } else { format };
+            if ips_only {
+                aws_inventory_sdk::query::query_ips(&inventory, &filter, format)?;
+            } else {
+                aws_inventory_sdk::query::query_resources(&inventory, &filter, format)?;
+            }
+        }
+        Opt::Duplicates { inventory, text } => {
+            let inventory = match inventory {
+                Some(path) => path,
+                None => get_default_db_path()?,
+            };
+            aws_inventory_sdk::query::query_duplicates(&inventory, text)?;
+        }
+        Opt::CompareAccounts { inventory, text } => {
+            let inventory = match inventory {
+                Some(path) => path,
+                None => get_default_db_path()?,
+            };
+            aws_inventory_sdk::query::compare_accounts(&inventory, text)?;
+        }
+        Opt::Stats { inventory, required_tags, json } => {
+            let inventory = match inventory {
+                Some(path) => path,
+                None => get_default_db_path()?,
+            };
+            aws_inventory_sdk::query::print_stats(&inventory, &required_tags, json)?;
+        }
+        Opt::Diff { old, new, json, patch } => {
+            if patch {
+                aws_inventory_sdk::diff::print_patches(&old, &new)?;
+            } else {
+                aws_inventory_sdk::diff::print_diff(&old, &new, json)?;
+            }
+        }
+        Opt::Trends { inventory, services, regions, csv } => {
+            let inventory = match inventory {
+                Some(path) => path,
+                None => get_default_db_path()?,
+            };
+            aws_inventory_sdk::query::print_trends(&inventory, &services, &regions, csv)?;
         }
         Opt::Serve {
             inventory,
             listen,
             no_browser,
+            auth_token,
         } => {
             let inventory = match inventory {
                 Some(path) => path,
                 None => get_default_db_path()?,
             };
             let listen_addr = listen.clone();
-            server::start_server(inventory, listen_addr, no_browser).await?;
+            server::start_server(inventory, listen_addr, no_browser, auth_token).await?;
         }
     }
 
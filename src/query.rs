@@ -2,55 +2,244 @@ use anyhow::Result;
 use rusqlite::{params_from_iter, Connection};
 use serde_json::Value;
 use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::IpAddr;
 use std::path::Path;
 
 #[derive(Serialize, Debug)]
 pub struct Resource {
-    pub arn: String,
+    /// The stable unique key: an ARN, or a service id for resources without one.
+    pub id: String,
+    /// The real ARN, when the resource has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arn: Option<String>,
     pub name: String,
     pub resource_type: String,
     pub region: String,
     pub ips: Vec<String>,
     pub tags: Value,
     pub details: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// The `--source-label` of the scan that last saved the resource.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// The account the resource was collected from, when it was known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_id: Option<String>,
+    /// The AWS profile that collected the resource; `None` for the default
+    /// credential chain.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+/// Filters applied by `run_query`. Each non-empty field narrows the result set;
+/// within a field, any of the listed values may match.
+#[derive(Debug, Default, Clone)]
+pub struct QueryFilter {
+    pub services: Vec<String>,
+    pub regions: Vec<String>,
+    /// Security group ids referenced from a resource's `details.security_groups`.
+    pub security_groups: Vec<String>,
+    /// Comparisons against fields of a resource's `details`; all must hold.
+    pub conditions: Vec<DetailCondition>,
+    /// Exact ARNs or ids.
+    pub arns: Vec<String>,
+    /// Text the resource's ARN must contain, e.g. an account or VPC id.
+    /// Resources without an ARN never match.
+    pub arn_contains: Option<String>,
+    /// Addresses, any of which the resource must hold.
+    pub ips: Vec<IpAddr>,
+    /// Only resources holding at least one address of this kind. With
+    /// `run_ip_query`, only addresses of this kind are listed.
+    pub ip_scope: Option<IpScope>,
+    /// Bounds on `created_at`, in `inventory::CREATED_AT_FORMAT` (see `parse_created_bound`).
+    /// Resources whose creation time is unknown never match.
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    /// Source labels given to `inventory --source-label`.
+    pub sources: Vec<String>,
+    /// Substring of a container image in `details.images` (ECS tasks, EKS pods).
+    pub image: Option<String>,
+    /// Tags the resource must carry, each `key=value` or a bare `key` that
+    /// matches any value; all must hold.
+    pub tags: Vec<String>,
+    /// Alternative filter groups, at least one of which must match in addition
+    /// to the fields above (see `parse_filter_group`).
+    pub any_of: Vec<QueryFilter>,
+}
+
+/// Which kind of address `QueryFilter::ip_scope` selects, as classified when
+/// the address was saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpScope {
+    Public,
+    Private,
+}
+
+impl IpScope {
+    fn is_public_value(self) -> u8 {
+        match self {
+            IpScope::Public => 1,
+            IpScope::Private => 0,
+        }
+    }
+}
+
+/// Parses an `--or` group such as `services=ec2 regions=us-east-1`: space-separated
+/// `key=value` pairs that must all hold, with comma-separated lists for the
+/// list-valued keys. `where` may be repeated, e.g. `where=engine=postgres`.
+pub fn parse_filter_group(s: &str) -> std::result::Result<QueryFilter, String> {
+    let list = |value: &str| value.split(',').map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect::<Vec<_>>();
+    let mut group = QueryFilter::default();
+    for pair in s.split_whitespace() {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("'{}' in filter group '{}' is not key=value", pair, s))?;
+        match key {
+            "services" => group.services.extend(list(value)),
+            "regions" => group.regions.extend(list(value)),
+            "security-groups" => group.security_groups.extend(list(value)),
+            "sources" => group.sources.extend(list(value)),
+            "tag" => group.tags.push(value.to_string()),
+            "image" => group.image = Some(value.to_string()),
+            "where" => group.conditions.push(value.parse()?),
+            "created-after" => group.created_after = Some(parse_created_bound(value)?),
+            "created-before" => group.created_before = Some(parse_created_bound(value)?),
+            other => {
+                return Err(format!(
+                    "unknown key '{}' in filter group (expected services, regions, security-groups, sources, tag, image, where, created-after or created-before)",
+                    other
+                ))
+            }
+        }
+    }
+    Ok(group)
+}
+
+/// Parses a `--created-after`/`--created-before` value: a date (`2024-06-01`),
+/// an RFC 3339 timestamp, or an age such as `7d` or `12h` counted back from now.
+pub fn parse_created_bound(s: &str) -> std::result::Result<String, String> {
+    let s = s.trim();
+    let parsed = if let Ok(date) = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        date.and_hms_opt(0, 0, 0).map(|t| t.and_utc())
+    } else if let Ok(time) = chrono::DateTime::parse_from_rfc3339(s) {
+        Some(time.with_timezone(&chrono::Utc))
+    } else {
+        let invalid = || format!("'{}' is not a date, timestamp or age like 7d", s);
+        let age = if let Some(days) = s.strip_suffix('d') {
+            chrono::TimeDelta::try_days(days.parse().map_err(|_| invalid())?)
+        } else if let Some(hours) = s.strip_suffix('h') {
+            chrono::TimeDelta::try_hours(hours.parse().map_err(|_| invalid())?)
+        } else {
+            return Err(invalid());
+        };
+        // An age too large for a duration or a date is reported as out of range below.
+        age.and_then(|age| chrono::Utc::now().checked_sub_signed(age))
+    };
+    parsed
+        .map(|t| t.format(crate::inventory::CREATED_AT_FORMAT).to_string())
+        .ok_or_else(|| format!("'{}' is out of range", s))
+}
+
+/// A `--where` condition such as `utilization>80` or `engine=postgres`.
+/// Numeric values are compared numerically, anything else as text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetailCondition {
+    pub key: String,
+    pub op: &'static str,
+    pub value: String,
+}
+
+impl std::str::FromStr for DetailCondition {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        // Two-character operators first, so `>=` isn't read as `>` followed by `=value`.
+        const OPERATORS: &[&str] = &[">=", "<=", "!=", "=", ">", "<"];
+        let (pos, op) = s
+            .char_indices()
+            .find_map(|(i, _)| OPERATORS.iter().find(|op| s[i..].starts_with(**op)).map(|op| (i, *op)))
+            .ok_or_else(|| format!("'{}' has no comparison operator (one of {})", s, OPERATORS.join(" ")))?;
+
+        let key = s[..pos].trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+            return Err(format!("'{}' is not a valid details key", key));
+        }
+        Ok(DetailCondition {
+            key: key.to_string(),
+            op,
+            value: s[pos + op.len()..].trim().to_string(),
+        })
+    }
+}
+
+pub fn run_query(db_path: &Path, filter: &QueryFilter) -> Result<Vec<Resource>> {
+    let conn = crate::db::open_read_only(db_path)?;
+    run_query_on(&conn, filter)
+}
+
+/// One page of `run_query_page` results, with the number of matches across
+/// all pages.
+#[derive(Serialize, Debug)]
+pub struct ResourcePage {
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+    pub resources: Vec<Resource>,
+}
+
+/// Like `run_query`, but returns at most `limit` resources starting at
+/// `offset`, in a stable order so consecutive pages don't overlap.
+pub fn run_query_page(db_path: &Path, filter: &QueryFilter, limit: usize, offset: usize) -> Result<ResourcePage> {
+    let conn = crate::db::open_read_only(db_path)?;
+
+    let mut count_query = "SELECT COUNT(*) FROM resources r WHERE 1=1".to_string();
+    let mut params_vec: Vec<String> = Vec::new();
+    push_filter_clauses(filter, &mut count_query, &mut params_vec);
+    let total: i64 = conn.query_row(&count_query, params_from_iter(params_vec), |row| row.get(0))?;
+
+    Ok(ResourcePage {
+        total: total as usize,
+        limit,
+        offset,
+        resources: select_resources(&conn, filter, Some((limit, offset)))?,
+    })
 }
 
-pub fn run_query(db_path: &Path, services: &[String], regions: &[String]) -> Result<Vec<Resource>> {
-    let conn = Connection::open(db_path)?;
+/// Like `run_query`, but against an already open inventory.
+pub fn run_query_on(conn: &Connection, filter: &QueryFilter) -> Result<Vec<Resource>> {
+    select_resources(conn, filter, None)
+}
+
+fn select_resources(conn: &Connection, filter: &QueryFilter, page: Option<(usize, usize)>) -> Result<Vec<Resource>> {
     let mut query = "
         SELECT
-            r.arn,
+            r.uid,
             r.name,
             r.resource_type,
             r.region,
             COALESCE(GROUP_CONCAT(i.ip_address), ''),
             (SELECT json_group_object(key, value) FROM tags WHERE resource_id = r.id),
-            r.details
+            r.details,
+            (SELECT note FROM notes WHERE uid = r.uid),
+            r.source,
+            r.arn,
+            r.account_id,
+            r.profile
         FROM
             resources r
         LEFT JOIN ip_addresses i ON r.id = i.resource_id
         WHERE 1=1"
         .to_string();
     let mut params_vec: Vec<String> = Vec::new();
+    push_filter_clauses(filter, &mut query, &mut params_vec);
 
-    if !services.is_empty() {
-        let service_placeholders = services.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-        query.push_str(&format!(" AND resource_type IN ({})", service_placeholders));
-        for service in services {
-            params_vec.push(map_service_name(service));
-        }
-    }
-
-    if !regions.is_empty() {
-        let region_placeholders = regions.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-        query.push_str(&format!(" AND region IN ({})", region_placeholders));
-        for region in regions {
-            params_vec.push(region.clone());
-        }
+    query.push_str(" GROUP BY r.id, r.uid, r.name, r.resource_type, r.region, r.details");
+    if let Some((limit, offset)) = page {
+        query.push_str(&format!(" ORDER BY r.id LIMIT {} OFFSET {}", limit, offset));
     }
 
-    query.push_str(" GROUP BY r.id, r.arn, r.name, r.resource_type, r.region, r.details");
-
     let mut stmt = conn.prepare(&query)?;
     let resource_iter = stmt.query_map(params_from_iter(params_vec), |row| {
         let ips_str: String = row.get(4)?;
@@ -68,13 +257,18 @@ pub fn run_query(db_path: &Path, services: &[String], regions: &[String]) -> Res
         let details: Value = serde_json::from_str(&details_str).unwrap_or_default();
 
         Ok(Resource {
-            arn: row.get(0)?,
+            id: row.get(0)?,
+            arn: row.get(9)?,
             name: row.get(1)?,
             resource_type: row.get(2)?,
             region: row.get(3)?,
             ips,
             tags,
             details,
+            note: row.get(7)?,
+            source: row.get(8)?,
+            account_id: row.get(10)?,
+            profile: row.get(11)?,
         })
     })?;
 
@@ -86,35 +280,809 @@ pub fn run_query(db_path: &Path, services: &[String], regions: &[String]) -> Res
     Ok(results)
 }
 
-pub fn query_resources(
-    db_path: &Path,
-    services: &[String],
-    regions: &[String],
-    text_output: bool,
-) -> Result<()> {
-    let results = run_query(db_path, services, regions)?;
+/// Returns the sorted, de-duplicated IP addresses of the resources matching
+/// `filter`, limited to public or private addresses by `filter.ip_scope`.
+pub fn run_ip_query(db_path: &Path, filter: &QueryFilter) -> Result<Vec<IpAddr>> {
+    let conn = crate::db::open_read_only(db_path)?;
+    let mut query = "
+        SELECT DISTINCT i.ip_address
+        FROM
+            resources r
+        JOIN ip_addresses i ON r.id = i.resource_id
+        WHERE 1=1"
+        .to_string();
+    let mut params_vec: Vec<String> = Vec::new();
+    push_filter_clauses(filter, &mut query, &mut params_vec);
+    if let Some(scope) = filter.ip_scope {
+        query.push_str(&format!(" AND i.is_public = {}", scope.is_public_value()));
+    }
+
+    let mut stmt = conn.prepare(&query)?;
+    let mut ips: Vec<IpAddr> = stmt
+        .query_map(params_from_iter(params_vec), |row| row.get::<_, String>(0))?
+        .filter_map(|ip| ip.ok()?.parse().ok())
+        .collect();
+    // Sort numerically rather than as text, so 10.0.0.9 comes before 10.0.0.10.
+    ips.sort();
+    ips.dedup();
+    Ok(ips)
+}
+
+/// How `query` prints its results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Json,
+    Text,
+    Yaml,
+}
 
-    if text_output {
-        print_text_output(&results);
-    } else {
-        println!("{}", serde_json::to_string_pretty(&results)?);
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(OutputFormat::Json),
+            "text" => Ok(OutputFormat::Text),
+            "yaml" | "yml" => Ok(OutputFormat::Yaml),
+            _ => Err(format!("unknown format '{}', expected json, text or yaml", s)),
+        }
+    }
+}
+
+pub fn query_resources(db_path: &Path, filter: &QueryFilter, format: OutputFormat) -> Result<()> {
+    let results = run_query(db_path, filter)?;
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&results)?),
+        OutputFormat::Text => print_text_output(&results),
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(&results)?),
     }
 
     Ok(())
 }
 
-fn map_service_name(short_name: &str) -> String {
-    match short_name {
-        "rds" => "rds:db_instance",
-        "dynamodb" => "dynamodb:table",
-        "elasticache" => "elasticache:cluster",
-        "ec2" => "ec2:instance",
-        "elb" => "elbv2:loadbalancer",
-        "eks" => "eks:pod",
-        "route53" => "route53:hostedzone",
-        
-        _ => short_name, // If not a short name, assume it's a full resource_type
-    }.to_string()
+pub fn query_ips(db_path: &Path, filter: &QueryFilter, format: OutputFormat) -> Result<()> {
+    let ips = run_ip_query(db_path, filter)?;
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&ips)?),
+        OutputFormat::Text => {
+            for ip in ips {
+                println!("{}", ip);
+            }
+        }
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(&ips)?),
+    }
+
+    Ok(())
+}
+
+/// Summary counts over a whole inventory.
+#[derive(Serialize, Debug, Default)]
+pub struct StatsReport {
+    pub total_resources: usize,
+    pub by_resource_type: BTreeMap<String, usize>,
+    pub by_region: BTreeMap<String, usize>,
+    /// Counts per resource type, then region.
+    pub by_type_and_region: BTreeMap<String, BTreeMap<String, usize>>,
+    pub total_ips: usize,
+    pub public_ips: usize,
+    pub private_ips: usize,
+    /// When the most recently collected resource was last seen.
+    pub last_seen: Option<String>,
+    /// Required-tag coverage per resource type and account, when requested.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tag_coverage: Vec<TagCoverageRow>,
+}
+
+/// How many resources of one type in one account carry each required tag. A
+/// tag only counts when its value is not blank.
+#[derive(Serialize, Debug)]
+pub struct TagCoverageRow {
+    pub resource_type: String,
+    pub account_id: String,
+    pub total: usize,
+    pub with_tag: BTreeMap<String, usize>,
+    /// Resources carrying every required tag.
+    pub with_all_tags: usize,
+    /// `with_tag` as percentages of `total`.
+    pub coverage: BTreeMap<String, f64>,
+    pub coverage_all: f64,
+}
+
+fn percentage(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    (count as f64 / total as f64 * 1000.0).round() / 10.0
+}
+
+/// Computes `StatsReport` with aggregate queries only, so it stays fast on
+/// inventories too large to load with `run_query`.
+pub fn run_stats(db_path: &Path) -> Result<StatsReport> {
+    let conn = crate::db::open_read_only(db_path)?;
+    run_stats_on(&conn)
+}
+
+pub fn run_stats_on(conn: &Connection) -> Result<StatsReport> {
+    let mut report = StatsReport::default();
+
+    let mut stmt = conn.prepare("SELECT resource_type, region, COUNT(*) FROM resources GROUP BY resource_type, region")?;
+    let groups = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)? as usize))
+    })?;
+    for group in groups {
+        let (resource_type, region, count) = group?;
+        report.total_resources += count;
+        *report.by_resource_type.entry(resource_type.clone()).or_default() += count;
+        *report.by_region.entry(region.clone()).or_default() += count;
+        report.by_type_and_region.entry(resource_type).or_default().insert(region, count);
+    }
+
+    let (total_ips, public_ips, private_ips): (i64, i64, i64) = conn.query_row(
+        "SELECT
+            COUNT(DISTINCT ip_address),
+            COUNT(DISTINCT CASE WHEN is_public THEN ip_address END),
+            COUNT(DISTINCT CASE WHEN NOT is_public THEN ip_address END)
+         FROM ip_addresses",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    report.total_ips = total_ips as usize;
+    report.public_ips = public_ips as usize;
+    report.private_ips = private_ips as usize;
+
+    report.last_seen = conn.query_row("SELECT MAX(last_seen) FROM resources", [], |row| row.get(0))?;
+
+    Ok(report)
+}
+
+/// Computes, per resource type and account, the share of resources that carry
+/// each of `required_tags` (matched by exact key) and all of them at once.
+pub fn run_tag_coverage_on(conn: &Connection, required_tags: &[String]) -> Result<Vec<TagCoverageRow>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT r.id, r.resource_type, COALESCE(r.account_id, ?1), t.key
+        FROM resources r
+        LEFT JOIN tags t ON t.resource_id = r.id AND TRIM(t.value) != ''",
+    )?;
+    let rows = stmt.query_map([UNKNOWN_ACCOUNT], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<String>>(3)?,
+        ))
+    })?;
+
+    let mut resources: HashMap<i64, ((String, String), BTreeSet<String>)> = HashMap::new();
+    for row in rows {
+        let (id, resource_type, account_id, key) = row?;
+        let (_, keys) = resources.entry(id).or_insert_with(|| ((resource_type, account_id), BTreeSet::new()));
+        if let Some(key) = key.filter(|k| required_tags.contains(k)) {
+            keys.insert(key);
+        }
+    }
+
+    let mut groups: BTreeMap<(String, String), TagCoverageRow> = BTreeMap::new();
+    for ((resource_type, account_id), keys) in resources.into_values() {
+        let row = groups.entry((resource_type.clone(), account_id.clone())).or_insert_with(|| TagCoverageRow {
+            resource_type,
+            account_id,
+            total: 0,
+            with_tag: required_tags.iter().map(|t| (t.clone(), 0)).collect(),
+            with_all_tags: 0,
+            coverage: BTreeMap::new(),
+            coverage_all: 0.0,
+        });
+        row.total += 1;
+        for key in &keys {
+            *row.with_tag.entry(key.clone()).or_default() += 1;
+        }
+        if required_tags.iter().all(|t| keys.contains(t)) {
+            row.with_all_tags += 1;
+        }
+    }
+
+    Ok(groups
+        .into_values()
+        .map(|mut row| {
+            row.coverage = row.with_tag.iter().map(|(tag, count)| (tag.clone(), percentage(*count, row.total))).collect();
+            row.coverage_all = percentage(row.with_all_tags, row.total);
+            row
+        })
+        .collect())
+}
+
+/// Prints the inventory's summary counts as a resource type by region table,
+/// plus a required-tag coverage table when `required_tags` is not empty.
+pub fn print_stats(db_path: &Path, required_tags: &[String], json_output: bool) -> Result<()> {
+    let conn = crate::db::open_read_only(db_path)?;
+    let mut report = run_stats_on(&conn)?;
+    if !required_tags.is_empty() {
+        report.tag_coverage = run_tag_coverage_on(&conn, required_tags)?;
+    }
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    if report.total_resources == 0 {
+        println!("No resources found in the inventory.");
+        return Ok(());
+    }
+
+    let type_width = report.by_resource_type.keys().map(|t| t.len()).max().unwrap_or(0).max("Resource Type".len()) + 2;
+    let region_widths: Vec<usize> = report.by_region.keys().map(|r| r.len().max(5) + 2).collect();
+    let total_width = 8;
+
+    print!("{:<width$}", "Resource Type", width = type_width);
+    for (region, width) in report.by_region.keys().zip(&region_widths) {
+        print!("{:>width$}", region, width = width);
+    }
+    println!("{:>width$}", "Total", width = total_width);
+    println!("{}", "-".repeat(type_width + region_widths.iter().sum::<usize>() + total_width));
+    for (resource_type, regions) in &report.by_type_and_region {
+        print!("{:<width$}", resource_type, width = type_width);
+        for (region, width) in report.by_region.keys().zip(&region_widths) {
+            print!("{:>width$}", regions.get(region).copied().unwrap_or(0), width = width);
+        }
+        println!("{:>width$}", report.by_resource_type[resource_type], width = total_width);
+    }
+    print!("{:<width$}", "Total", width = type_width);
+    for (count, width) in report.by_region.values().zip(&region_widths) {
+        print!("{:>width$}", count, width = width);
+    }
+    println!("{:>width$}", report.total_resources, width = total_width);
+
+    println!(
+        "\nIP addresses: {} ({} public, {} private)",
+        report.total_ips, report.public_ips, report.private_ips
+    );
+    if let Some(last_seen) = &report.last_seen {
+        println!("Last seen: {}", last_seen);
+    }
+
+    if required_tags.is_empty() {
+        return Ok(());
+    }
+    println!("\nRequired tag coverage:");
+    let rows = &report.tag_coverage;
+    let type_width = rows.iter().map(|r| r.resource_type.len()).max().unwrap_or(0).max("Resource Type".len()) + 2;
+    let account_width = rows.iter().map(|r| r.account_id.len()).max().unwrap_or(0).max("Account".len()) + 2;
+    let tag_widths: Vec<usize> = required_tags.iter().map(|t| t.len().max("100.0%".len()) + 2).collect();
+
+    print!("{:<tw$}{:<aw$}{:>11}", "Resource Type", "Account", "Resources", tw = type_width, aw = account_width);
+    for (tag, width) in required_tags.iter().zip(&tag_widths) {
+        print!("{:>width$}", tag, width = width);
+    }
+    println!("{:>8}", "All");
+    println!("{}", "-".repeat(type_width + account_width + 11 + tag_widths.iter().sum::<usize>() + 8));
+    for row in rows {
+        print!("{:<tw$}{:<aw$}{:>11}", row.resource_type, row.account_id, row.total, tw = type_width, aw = account_width);
+        for (tag, width) in required_tags.iter().zip(&tag_widths) {
+            print!("{:>width$}", format!("{:.1}%", row.coverage.get(tag).copied().unwrap_or(0.0)), width = width);
+        }
+        println!("{:>8}", format!("{:.1}%", row.coverage_all));
+    }
+
+    let total: usize = rows.iter().map(|r| r.total).sum();
+    let tagged: usize = rows.iter().map(|r| r.with_all_tags).sum();
+    println!("\nOverall: {} of {} resources ({:.1}%) carry every required tag.", tagged, total, percentage(tagged, total));
+    Ok(())
+}
+
+/// A `Name` tag value shared by several resources of the same type and region.
+#[derive(Serialize, Debug)]
+pub struct DuplicateName {
+    pub name: String,
+    pub resource_type: String,
+    pub region: String,
+    /// The ARN of each resource, or its id when it has none.
+    pub arns: Vec<String>,
+}
+
+pub fn run_duplicates_query(db_path: &Path) -> Result<Vec<DuplicateName>> {
+    let conn = crate::db::open_read_only(db_path)?;
+    let mut stmt = conn.prepare(
+        "
+        SELECT
+            t.value,
+            r.resource_type,
+            r.region,
+            GROUP_CONCAT(COALESCE(r.arn, r.uid), char(10))
+        FROM
+            tags t
+        JOIN resources r ON r.id = t.resource_id
+        WHERE t.key = 'Name'
+        GROUP BY t.value, r.resource_type, r.region
+        HAVING COUNT(*) > 1
+        ORDER BY r.resource_type, r.region, t.value",
+    )?;
+    let duplicate_iter = stmt.query_map([], |row| {
+        let arns_str: String = row.get(3)?;
+        let mut arns: Vec<String> = arns_str.split('\n').map(|s| s.to_string()).collect();
+        arns.sort();
+        Ok(DuplicateName {
+            name: row.get(0)?,
+            resource_type: row.get(1)?,
+            region: row.get(2)?,
+            arns,
+        })
+    })?;
+
+    let mut results = Vec::new();
+    for duplicate in duplicate_iter {
+        results.push(duplicate?);
+    }
+    Ok(results)
+}
+
+pub fn query_duplicates(db_path: &Path, text_output: bool) -> Result<()> {
+    let duplicates = run_duplicates_query(db_path)?;
+
+    if !text_output {
+        println!("{}", serde_json::to_string_pretty(&duplicates)?);
+        return Ok(());
+    }
+
+    if duplicates.is_empty() {
+        println!("No duplicate Name tags found.");
+        return Ok(());
+    }
+    for duplicate in &duplicates {
+        println!(
+            "\nName '{}' is used by {} {} resources in {}:",
+            duplicate.name,
+            duplicate.arns.len(),
+            duplicate.resource_type,
+            duplicate.region
+        );
+        for arn in &duplicate.arns {
+            println!("  {}", arn);
+        }
+    }
+    Ok(())
+}
+
+/// Resources without a recorded account are reported under this name.
+const UNKNOWN_ACCOUNT: &str = "unknown";
+
+/// Per resource type and account counts, as produced by `run_account_comparison`.
+#[derive(Serialize, Debug)]
+pub struct AccountComparison {
+    pub accounts: Vec<String>,
+    pub rows: Vec<ComparisonRow>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ComparisonRow {
+    pub resource_type: String,
+    /// One cell per account in `AccountComparison::accounts`, including empty ones.
+    pub cells: BTreeMap<String, ComparisonCell>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ComparisonCell {
+    pub count: usize,
+    pub regions: Vec<String>,
+}
+
+pub fn run_account_comparison(db_path: &Path) -> Result<AccountComparison> {
+    let conn = crate::db::open_read_only(db_path)?;
+    let mut stmt = conn.prepare(
+        "
+        SELECT
+            COALESCE(account_id, ?1),
+            resource_type,
+            COUNT(*),
+            GROUP_CONCAT(DISTINCT region)
+        FROM resources
+        GROUP BY account_id, resource_type",
+    )?;
+    let groups = stmt.query_map([UNKNOWN_ACCOUNT], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, i64>(2)? as usize,
+            row.get::<_, String>(3)?,
+        ))
+    })?;
+
+    let mut accounts = BTreeSet::new();
+    let mut rows: BTreeMap<String, BTreeMap<String, ComparisonCell>> = BTreeMap::new();
+    for group in groups {
+        let (account_id, resource_type, count, regions_str) = group?;
+        let mut regions: Vec<String> = regions_str.split(',').map(|s| s.to_string()).collect();
+        regions.sort();
+        accounts.insert(account_id.clone());
+        rows.entry(resource_type).or_default().insert(account_id, ComparisonCell { count, regions });
+    }
+
+    // Fill in the zeros: an account missing a resource type is the interesting case.
+    let rows = rows
+        .into_iter()
+        .map(|(resource_type, mut cells)| {
+            for account_id in &accounts {
+                cells.entry(account_id.clone()).or_default();
+            }
+            ComparisonRow { resource_type, cells }
+        })
+        .collect();
+
+    Ok(AccountComparison {
+        accounts: accounts.into_iter().collect(),
+        rows,
+    })
+}
+
+pub fn compare_accounts(db_path: &Path, text_output: bool) -> Result<()> {
+    let comparison = run_account_comparison(db_path)?;
+
+    if !text_output {
+        println!("{}", serde_json::to_string_pretty(&comparison)?);
+        return Ok(());
+    }
+
+    if comparison.rows.is_empty() {
+        println!("No resources found in the inventory.");
+        return Ok(());
+    }
+
+    let type_width = comparison.rows.iter().map(|r| r.resource_type.len()).max().unwrap_or(0).max("Resource Type".len()) + 2;
+    let account_width = comparison.accounts.iter().map(|a| a.len()).max().unwrap_or(0) + 2;
+
+    print!("{:<width$}", "Resource Type", width = type_width);
+    for account_id in &comparison.accounts {
+        print!("{:>width$}", account_id, width = account_width);
+    }
+    println!();
+    println!("{}", "-".repeat(type_width + account_width * comparison.accounts.len()));
+    for row in &comparison.rows {
+        print!("{:<width$}", row.resource_type, width = type_width);
+        for account_id in &comparison.accounts {
+            let count = row.cells.get(account_id).map(|c| c.count).unwrap_or(0);
+            print!("{:>width$}", count, width = account_width);
+        }
+        println!();
+    }
+
+    println!("\nRegions in use:");
+    for account_id in &comparison.accounts {
+        let regions: BTreeSet<&String> = comparison
+            .rows
+            .iter()
+            .filter_map(|r| r.cells.get(account_id))
+            .flat_map(|c| c.regions.iter())
+            .collect();
+        let regions: Vec<&str> = regions.into_iter().map(|r| r.as_str()).collect();
+        println!("  {}: {}", account_id, regions.join(", "));
+    }
+    Ok(())
+}
+
+/// One `resource_counts_history` sample: how many resources of a type an
+/// account had in a region when a scan finished.
+#[derive(Serialize, Debug)]
+pub struct TrendPoint {
+    pub recorded_at: String,
+    pub resource_type: String,
+    pub region: String,
+    pub account_id: String,
+    pub count: usize,
+}
+
+/// Returns the recorded counts, oldest first, optionally limited to some
+/// services and regions.
+pub fn run_trends_query(db_path: &Path, services: &[String], regions: &[String]) -> Result<Vec<TrendPoint>> {
+    let conn = crate::db::open_read_only(db_path)?;
+    let mut query = "
+        SELECT recorded_at, resource_type, region, COALESCE(account_id, ?), count
+        FROM resource_counts_history
+        WHERE 1=1"
+        .to_string();
+    let mut params_vec = vec![UNKNOWN_ACCOUNT.to_string()];
+    if !services.is_empty() {
+        let resource_types: Vec<String> = services.iter().flat_map(|s| service_resource_types(s)).collect();
+        query.push_str(&format!(" AND resource_type IN ({})", resource_types.iter().map(|_| "?").collect::<Vec<_>>().join(",")));
+        params_vec.extend(resource_types);
+    }
+    if !regions.is_empty() {
+        query.push_str(&format!(" AND region IN ({})", regions.iter().map(|_| "?").collect::<Vec<_>>().join(",")));
+        params_vec.extend(regions.iter().cloned());
+    }
+    query.push_str(" ORDER BY resource_type, region, account_id, recorded_at");
+
+    let mut stmt = conn.prepare(&query)?;
+    let points = stmt
+        .query_map(params_from_iter(params_vec), |row| {
+            Ok(TrendPoint {
+                recorded_at: row.get(0)?,
+                resource_type: row.get(1)?,
+                region: row.get(2)?,
+                account_id: row.get(3)?,
+                count: row.get::<_, i64>(4)? as usize,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(points)
+}
+
+/// Prints the recorded counts as CSV, or as one text chart per
+/// (resource type, region, account) with the change since the previous scan.
+pub fn print_trends(db_path: &Path, services: &[String], regions: &[String], csv_output: bool) -> Result<()> {
+    let points = run_trends_query(db_path, services, regions)?;
+
+    if csv_output {
+        println!("recorded_at,resource_type,region,account_id,count");
+        for p in &points {
+            println!("{},{},{},{},{}", p.recorded_at, p.resource_type, p.region, p.account_id, p.count);
+        }
+        return Ok(());
+    }
+
+    if points.is_empty() {
+        println!("No resource counts recorded yet; they are added at the end of every inventory run.");
+        return Ok(());
+    }
+
+    const BAR_WIDTH: usize = 40;
+    for series in points.chunk_by(|a, b| (&a.resource_type, &a.region, &a.account_id) == (&b.resource_type, &b.region, &b.account_id)) {
+        let first = &series[0];
+        println!("\n{} in {} (account {})", first.resource_type, first.region, first.account_id);
+        let max = series.iter().map(|p| p.count).max().unwrap_or(0).max(1);
+        let mut previous: Option<usize> = None;
+        for p in series {
+            let change = match previous {
+                Some(prev) => format!("{:+}", p.count as i64 - prev as i64),
+                None => String::new(),
+            };
+            let bar = "#".repeat(p.count * BAR_WIDTH / max);
+            println!("  {}  {:>6} {:>6}  {}", p.recorded_at, p.count, change, bar);
+            previous = Some(p.count);
+        }
+    }
+    Ok(())
+}
+
+/// One account in the navigation tree returned by `run_tree_query`. Resources
+/// collected without a known account are grouped under `account_id: null`.
+#[derive(Serialize, Debug)]
+pub struct TreeAccount {
+    pub account_id: Option<String>,
+    pub count: usize,
+    pub regions: Vec<TreeRegion>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TreeRegion {
+    pub region: String,
+    pub count: usize,
+    pub services: Vec<TreeService>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TreeService {
+    pub resource_type: String,
+    pub count: usize,
+    pub resources: Vec<TreeLeaf>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TreeLeaf {
+    /// The resource's ARN, or its id when it has none.
+    pub arn: String,
+    pub name: String,
+}
+
+/// Returns the resources matching `filter` as an account → region → service
+/// tree with per-node counts. Leaves carry only the ARN and name, which keeps
+/// the response far smaller than `run_query`.
+pub fn run_tree_query(db_path: &Path, filter: &QueryFilter) -> Result<Vec<TreeAccount>> {
+    let conn = crate::db::open_read_only(db_path)?;
+    let mut where_clause = " WHERE 1=1".to_string();
+    let mut params_vec: Vec<String> = Vec::new();
+    push_filter_clauses(filter, &mut where_clause, &mut params_vec);
+
+    // Build the skeleton, with counts, from the grouped query.
+    let mut tree: Vec<TreeAccount> = Vec::new();
+    let mut index: HashMap<(Option<String>, String, String), (usize, usize, usize)> = HashMap::new();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT r.account_id, r.region, r.resource_type, COUNT(*) FROM resources r{}
+         GROUP BY r.account_id, r.region, r.resource_type
+         ORDER BY r.account_id, r.region, r.resource_type",
+        where_clause
+    ))?;
+    let groups = stmt.query_map(params_from_iter(&params_vec), |row| {
+        Ok((
+            row.get::<_, Option<String>>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, i64>(3)? as usize,
+        ))
+    })?;
+    for group in groups {
+        let (account_id, region, resource_type, count) = group?;
+
+        if tree.last().map(|a| &a.account_id) != Some(&account_id) {
+            tree.push(TreeAccount { account_id: account_id.clone(), count: 0, regions: Vec::new() });
+        }
+        let account_idx = tree.len() - 1;
+        let account = &mut tree[account_idx];
+        account.count += count;
+
+        if account.regions.last().map(|r| &r.region) != Some(&region) {
+            account.regions.push(TreeRegion { region: region.clone(), count: 0, services: Vec::new() });
+        }
+        let region_idx = account.regions.len() - 1;
+        let region_node = &mut account.regions[region_idx];
+        region_node.count += count;
+
+        region_node.services.push(TreeService { resource_type: resource_type.clone(), count, resources: Vec::new() });
+        index.insert((account_id, region, resource_type), (account_idx, region_idx, region_node.services.len() - 1));
+    }
+
+    // Then attach the leaves.
+    let mut stmt = conn.prepare(&format!(
+        "SELECT r.account_id, r.region, r.resource_type, COALESCE(r.arn, r.uid), r.name FROM resources r{} ORDER BY r.name, r.uid",
+        where_clause
+    ))?;
+    let leaves = stmt.query_map(params_from_iter(&params_vec), |row| {
+        Ok((
+            row.get::<_, Option<String>>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            TreeLeaf {
+                arn: row.get(3)?,
+                name: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+            },
+        ))
+    })?;
+    for leaf in leaves {
+        let (account_id, region, resource_type, leaf) = leaf?;
+        if let Some(&(a, r, s)) = index.get(&(account_id, region, resource_type)) {
+            tree[a].regions[r].services[s].resources.push(leaf);
+        }
+    }
+
+    Ok(tree)
+}
+
+/// Appends the `WHERE` conditions for `filter` to `query`, which must select
+/// from `resources r`, pushing the matching bind parameters.
+pub(crate) fn push_filter_clauses(filter: &QueryFilter, query: &mut String, params_vec: &mut Vec<String>) {
+    if !filter.services.is_empty() {
+        let resource_types: Vec<String> = filter.services.iter().flat_map(|s| service_resource_types(s)).collect();
+        let service_placeholders = resource_types.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        query.push_str(&format!(" AND r.resource_type IN ({})", service_placeholders));
+        params_vec.extend(resource_types);
+    }
+
+    if !filter.regions.is_empty() {
+        let region_placeholders = filter.regions.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        query.push_str(&format!(" AND r.region IN ({})", region_placeholders));
+        for region in &filter.regions {
+            params_vec.push(region.clone());
+        }
+    }
+
+    if !filter.security_groups.is_empty() {
+        let sg_placeholders = filter.security_groups.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        query.push_str(&format!(
+            " AND EXISTS (SELECT 1 FROM json_each(r.details, '$.security_groups') WHERE value IN ({}))",
+            sg_placeholders
+        ));
+        for sg in &filter.security_groups {
+            params_vec.push(sg.clone());
+        }
+    }
+
+    if !filter.arns.is_empty() {
+        let arn_placeholders = filter.arns.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        query.push_str(&format!(" AND (r.uid IN ({0}) OR r.arn IN ({0}))", arn_placeholders));
+        params_vec.extend(filter.arns.iter().cloned());
+        params_vec.extend(filter.arns.iter().cloned());
+    }
+
+    if !filter.ips.is_empty() {
+        let ip_placeholders = filter.ips.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        query.push_str(&format!(
+            " AND EXISTS (SELECT 1 FROM ip_addresses ia WHERE ia.resource_id = r.id AND ia.ip_address IN ({}))",
+            ip_placeholders
+        ));
+        params_vec.extend(filter.ips.iter().map(|ip| ip.to_string()));
+    }
+
+    if let Some(scope) = filter.ip_scope {
+        query.push_str(&format!(
+            " AND EXISTS (SELECT 1 FROM ip_addresses ia WHERE ia.resource_id = r.id AND ia.is_public = {})",
+            scope.is_public_value()
+        ));
+    }
+
+    if !filter.sources.is_empty() {
+        let source_placeholders = filter.sources.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        query.push_str(&format!(" AND r.source IN ({})", source_placeholders));
+        params_vec.extend(filter.sources.iter().cloned());
+    }
+
+    for tag in &filter.tags {
+        match tag.split_once('=') {
+            Some((key, value)) => {
+                query.push_str(" AND EXISTS (SELECT 1 FROM tags t WHERE t.resource_id = r.id AND t.key = ? AND t.value = ?)");
+                params_vec.push(key.to_string());
+                params_vec.push(value.to_string());
+            }
+            None => {
+                query.push_str(" AND EXISTS (SELECT 1 FROM tags t WHERE t.resource_id = r.id AND t.key = ?)");
+                params_vec.push(tag.clone());
+            }
+        }
+    }
+
+    if let Some(text) = &filter.arn_contains {
+        query.push_str(" AND instr(r.arn, ?) > 0");
+        params_vec.push(text.clone());
+    }
+
+    if let Some(image) = &filter.image {
+        query.push_str(" AND EXISTS (SELECT 1 FROM json_each(r.details, '$.images') WHERE instr(value, ?) > 0)");
+        params_vec.push(image.clone());
+    }
+
+    if let Some(after) = &filter.created_after {
+        query.push_str(" AND r.created_at >= ?");
+        params_vec.push(after.clone());
+    }
+
+    if let Some(before) = &filter.created_before {
+        query.push_str(" AND r.created_at < ?");
+        params_vec.push(before.clone());
+    }
+
+    for condition in &filter.conditions {
+        // json_extract returns JSON booleans as 1 and 0.
+        let value = match condition.value.as_str() {
+            "true" => "1".to_string(),
+            "false" => "0".to_string(),
+            other => other.to_string(),
+        };
+        if value.parse::<f64>().is_ok() {
+            query.push_str(&format!(
+                " AND CAST(json_extract(r.details, ?) AS REAL) {} CAST(? AS REAL)",
+                condition.op
+            ));
+        } else {
+            query.push_str(&format!(" AND json_extract(r.details, ?) {} ?", condition.op));
+        }
+        params_vec.push(format!("$.{}", condition.key));
+        params_vec.push(value);
+    }
+
+    if !filter.any_of.is_empty() {
+        query.push_str(" AND (");
+        for (i, group) in filter.any_of.iter().enumerate() {
+            if i > 0 {
+                query.push_str(" OR ");
+            }
+            query.push_str("(1=1");
+            push_filter_clauses(group, query, params_vec);
+            query.push(')');
+        }
+        query.push(')');
+    }
+}
+
+/// The resource types a service name in `--services` matches: every type its
+/// collector returns, the same ones pruning covers. Anything else is taken to
+/// be a full resource type.
+fn service_resource_types(short_name: &str) -> Vec<String> {
+    match crate::scan::service_resource_types(short_name) {
+        Some(types) => types.iter().map(|t| t.to_string()).collect(),
+        None => vec![short_name.to_string()],
+    }
 }
 
 fn print_text_output(resources: &[Resource]) {
@@ -134,14 +1102,18 @@ fn print_text_output(resources: &[Resource]) {
         println!("Region: {}", region);
         
         let (max_name, max_arn) = res.iter().fold((0, 0), |(max_name, max_arn), r| {
-            (max_name.max(r.name.len()), max_arn.max(r.arn.len()))
+            (max_name.max(r.name.len()), max_arn.max(r.arn.as_ref().unwrap_or(&r.id).len()))
         });
 
         println!("{:<width_name$} {:<width_arn$} IPs", "Name", "ARN", width_name = max_name + 2, width_arn = max_arn + 2);
         println!("{:-<width_name$} {:-<width_arn$} ----", "", "", width_name = max_name + 2, width_arn = max_arn + 2);
 
         for r in res {
-            println!("{:<width_name$} {:<width_arn$} {}", r.name, r.arn, r.ips.join(", "), width_name = max_name + 2, width_arn = max_arn + 2);
+            println!("{:<width_name$} {:<width_arn$} {}", r.name, r.arn.as_ref().unwrap_or(&r.id), r.ips.join(", "), width_name = max_name + 2, width_arn = max_arn + 2);
+            if let Some(note) = &r.note {
+                println!("  Note: {}", note);
+            }
         }
     }
 }
+
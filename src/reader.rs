@@ -0,0 +1,53 @@
+use crate::db;
+use crate::query::{self, QueryFilter, Resource, StatsReport};
+use anyhow::Result;
+use rusqlite::Connection;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// A read-only handle on an inventory database for crates embedding this SDK.
+/// Unlike the path-based functions in `query`, it keeps one connection open
+/// across calls.
+pub struct Inventory {
+    conn: Connection,
+}
+
+impl Inventory {
+    /// Opens the inventory at `path` read-only.
+    pub fn open(path: &Path) -> Result<Self> {
+        Ok(Self { conn: db::open_read_only(path)? })
+    }
+
+    /// Wraps an existing connection, such as the in-memory database a scan
+    /// was just written to.
+    pub fn from_connection(conn: Connection) -> Self {
+        Self { conn }
+    }
+
+    pub fn query(&self, filter: &QueryFilter) -> Result<Vec<Resource>> {
+        query::run_query_on(&self.conn, filter)
+    }
+
+    /// Looks up a single resource by its ARN (or id, for resources stored without one).
+    pub fn by_arn(&self, arn: &str) -> Result<Option<Resource>> {
+        let filter = QueryFilter {
+            arns: vec![arn.to_string()],
+            ..Default::default()
+        };
+        Ok(self.query(&filter)?.into_iter().next())
+    }
+
+    /// Returns every resource holding `ip`. Usually one, but e.g. an ENI
+    /// address may be recorded against both an instance and its load balancer.
+    pub fn by_ip(&self, ip: IpAddr) -> Result<Vec<Resource>> {
+        let filter = QueryFilter {
+            ips: vec![ip],
+            ..Default::default()
+        };
+        self.query(&filter)
+    }
+
+    pub fn stats(&self) -> Result<StatsReport> {
+        query::run_stats_on(&self.conn)
+    }
+}
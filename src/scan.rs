@@ -0,0 +1,494 @@
+use crate::audit::AuditLog;
+use crate::config;
+use crate::db;
+use crate::inventory::{self, AwsResourceCollector, CollectedResource, ScanContext};
+use anyhow::Result;
+use futures::StreamExt;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+/// Every service `all_services` selects, in the order they are scanned.
+pub const ALL_SERVICES: &[&str] = &[
+    "ec2", "eip", "elb", "rds", "dynamodb", "elasticache", "eks", "route53", "batch",
+    "launchtemplate", "quotas", "vpc", "nacl", "cloudtrail", "efs-ap", "lattice", "inspector", "ecs",
+    "workspaces", "appstream", "ses", "healthcheck", "flowlogs", "mq", "org", "eice", "storagegateway",
+    "ebs", "sg", "capacity-reservation", "spot-request", "sns", "sqs",
+];
+
+/// Collectors run at once per profile unless `--collector-concurrency` says otherwise.
+pub const DEFAULT_COLLECTOR_CONCURRENCY: usize = 4;
+
+/// Everything that controls an inventory scan; the `inventory` subcommand's
+/// flags map onto these fields one to one.
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Profiles to scan. Empty falls back to the profiles named in
+    /// `profile_region_matrix`, then `AWS_PROFILE`, then the default chain.
+    pub profiles: Vec<String>,
+    /// Regions to scan; `all` expands to every known region.
+    pub regions: Vec<String>,
+    pub exclude_regions: Vec<String>,
+    pub profile_region_matrix: Option<PathBuf>,
+    /// Roles to assume in order; a single role for plain cross-account access.
+    pub assume_role_chain: Vec<String>,
+    /// External id passed when assuming the last role of `assume_role_chain`.
+    pub external_id: Option<String>,
+    /// The inventory database; `:memory:` for one that is dropped afterwards.
+    pub output: PathBuf,
+    /// Services to collect. Empty means `ec2` unless `all_services` is set.
+    pub services: Vec<String>,
+    pub all_services: bool,
+    pub no_eks: bool,
+    pub eks_clusters: Vec<String>,
+    pub include_terminated: bool,
+    pub resolve_endpoints: bool,
+    pub with_macie: bool,
+    pub sort_regions_by_latency: bool,
+    pub region_concurrency: usize,
+    /// How many collectors run at once for each profile.
+    pub collector_concurrency: usize,
+    pub sample: Option<usize>,
+    /// Retries per AWS call on throttling and transient errors; `None` keeps
+    /// the SDK default.
+    pub max_retries: Option<u32>,
+    /// Per-resource describe calls in flight at once per collector and region.
+    pub describe_concurrency: usize,
+    /// Cost-allocation tag to attribute Cost Explorer costs by, if any.
+    pub cost_tag: Option<String>,
+    pub fast_import: bool,
+    /// After a successful scan, delete resources it covered but did not see.
+    pub prune: bool,
+    pub enrich: bool,
+    pub validate_arns: bool,
+    pub store_raw: bool,
+    pub source_label: Option<String>,
+    pub json_logs_to: Option<PathBuf>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            profiles: Vec::new(),
+            regions: Vec::new(),
+            exclude_regions: Vec::new(),
+            profile_region_matrix: None,
+            assume_role_chain: Vec::new(),
+            external_id: None,
+            output: PathBuf::from("aws_inventory.db"),
+            services: Vec::new(),
+            all_services: false,
+            no_eks: false,
+            eks_clusters: Vec::new(),
+            include_terminated: false,
+            resolve_endpoints: false,
+            with_macie: false,
+            sort_regions_by_latency: false,
+            region_concurrency: inventory::DEFAULT_REGION_CONCURRENCY,
+            collector_concurrency: DEFAULT_COLLECTOR_CONCURRENCY,
+            sample: None,
+            max_retries: None,
+            describe_concurrency: inventory::DEFAULT_DESCRIBE_CONCURRENCY,
+            cost_tag: None,
+            fast_import: false,
+            prune: false,
+            enrich: false,
+            validate_arns: false,
+            store_raw: false,
+            source_label: None,
+            json_logs_to: None,
+        }
+    }
+}
+
+/// The outcome of one pass over every target.
+#[derive(Debug, Default, Serialize)]
+pub struct ScanReport {
+    pub resources_saved: usize,
+    /// Resources collected per service, summed over profiles.
+    pub resources_by_service: BTreeMap<String, usize>,
+    /// Requested services that do not name a collector; they were skipped.
+    pub unknown_services: Vec<String>,
+    /// Collectors that failed. The other collectors still ran and saved.
+    pub errors: Vec<ScanError>,
+    /// Lookups that failed inside collectors that otherwise finished, so their
+    /// results may be incomplete. What they found was still saved.
+    pub partial_failures: Vec<ScanError>,
+    /// Requests AWS throttled during the pass.
+    pub throttle_events: usize,
+    /// Stale resources deleted afterwards, with `prune`.
+    pub resources_pruned: usize,
+    /// The resource types, and the regions of each account, the pass covered.
+    #[serde(skip)]
+    scope: db::PruneScope,
+    /// Profiles whose account could not be determined; their resources cannot
+    /// be told apart from other accounts', so nothing is pruned.
+    #[serde(skip)]
+    unidentified_profiles: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScanError {
+    pub profile: String,
+    pub service: String,
+    pub message: String,
+}
+
+/// The collectors and (profile, regions) targets resolved from `ScanOptions`,
+/// ready to be run once or, with `--watch`, repeatedly.
+pub struct ScanPlan {
+    pub collectors: Vec<(String, Box<dyn AwsResourceCollector>)>,
+    pub targets: Vec<(ScanContext, Vec<String>)>,
+    pub unknown_services: Vec<String>,
+    fast_import: bool,
+    prune: bool,
+    /// Why only part of the scope is scanned, when `sample` or `eks_clusters`
+    /// limit it; everything left out would look stale to pruning.
+    partial_scan: Option<&'static str>,
+    collector_concurrency: usize,
+    throttle_events: Arc<AtomicUsize>,
+}
+
+impl ScanPlan {
+    /// Resolves profiles, regions and services. This assumes any role chain
+    /// and, with `sort_regions_by_latency`, probes each region, but collects
+    /// nothing yet.
+    pub async fn new(options: &ScanOptions) -> Result<Self> {
+        if options.external_id.is_some() && options.assume_role_chain.is_empty() {
+            anyhow::bail!("--external-id needs a role to assume; pass --assume-role-arn or --assume-role-chain");
+        }
+
+        let matrix = match &options.profile_region_matrix {
+            Some(path) => config::load_profile_region_matrix(path)?,
+            None => HashMap::new(),
+        };
+
+        // Without profiles, every profile named in the matrix is scanned;
+        // failing that, AWS_PROFILE is used like the AWS CLI does, and
+        // finally the default credential chain.
+        let (profiles, profile_source) = if !options.profiles.is_empty() {
+            (options.profiles.clone(), "--profile")
+        } else if !matrix.is_empty() {
+            let mut names: Vec<String> = matrix.keys().cloned().collect();
+            names.sort();
+            (names, "--profile-region-matrix")
+        } else {
+            match std::env::var("AWS_PROFILE").ok().filter(|p| !p.is_empty()) {
+                Some(env_profile) => (vec![env_profile], "AWS_PROFILE"),
+                None => (vec![String::new()], "default credential chain"),
+            }
+        };
+        if profiles.iter().all(|p| p.is_empty()) {
+            info!("Using the default credential chain (no --profile or AWS_PROFILE set).");
+        } else {
+            info!("Using profile(s) {} (from {}).", profiles.join(", "), profile_source);
+        }
+
+        let audit_log = match &options.json_logs_to {
+            Some(path) => Some(AuditLog::open(path)?),
+            None => None,
+        };
+
+        for region in config::unknown_regions(&options.exclude_regions) {
+            warn!("--exclude-regions contains unknown region '{}'.", region);
+        }
+
+        let throttle_events = Arc::new(AtomicUsize::new(0));
+
+        let mut targets = Vec::new();
+        for p in profiles {
+            let regions_to_scan = match matrix.get(&p) {
+                Some(profile_regions) => config::expand_regions(profile_regions),
+                None => config::expand_regions(&options.regions),
+            };
+            let requested_regions = regions_to_scan.len();
+            let regions_to_scan = config::exclude_regions(regions_to_scan, &options.exclude_regions);
+            let credentials = if options.assume_role_chain.is_empty() {
+                None
+            } else {
+                info!("Assuming role chain: {}", options.assume_role_chain.join(" -> "));
+                Some(inventory::assume_role_chain(&p, &options.assume_role_chain, options.external_id.as_deref()).await?)
+            };
+            let ctx = ScanContext {
+                sample: options.sample,
+                credentials,
+                audit_log: audit_log.clone(),
+                source_label: options.source_label.clone(),
+                throttle_events: Arc::clone(&throttle_events),
+                cost_tag: options.cost_tag.clone(),
+                store_raw: options.store_raw,
+                merge_details: options.enrich,
+                validate_arns: options.validate_arns,
+                region_concurrency: options.region_concurrency,
+                max_retries: options.max_retries,
+                describe_concurrency: options.describe_concurrency,
+                ..ScanContext::new(&p)
+            };
+            let regions_to_scan = if options.sort_regions_by_latency && regions_to_scan.len() > 1 {
+                info!("Measuring region latency...");
+                inventory::sort_regions_by_latency(&ctx, regions_to_scan).await
+            } else {
+                regions_to_scan
+            };
+            let profile_label = if p.is_empty() { String::new() } else { format!(" for profile '{}'", p) };
+            if regions_to_scan.is_empty() && requested_regions > 0 {
+                warn!("No regions left to scan{} after --exclude-regions.", profile_label);
+            } else if regions_to_scan.is_empty() {
+                warn!("No regions were given to scan{}.", profile_label);
+            } else {
+                info!("Regions to scan{}: {}", profile_label, regions_to_scan.join(", "));
+            }
+            targets.push((ctx, regions_to_scan));
+        }
+
+        let mut services_to_run = if options.all_services {
+            ALL_SERVICES.iter().map(|s| s.to_string()).collect()
+        } else if options.services.is_empty() {
+            // Default to only collecting EC2 if no services are specified
+            vec!["ec2".to_string()]
+        } else {
+            options.services.clone()
+        };
+        // no_eks acts as a final override
+        if options.no_eks {
+            services_to_run.retain(|s| s != "eks");
+        }
+
+        info!("Will collect inventory for: {}", services_to_run.join(", "));
+
+        let mut collectors = Vec::new();
+        let mut unknown_services = Vec::new();
+        for service in services_to_run {
+            match collector_for(&service, options) {
+                Some(collector) => collectors.push((service, collector)),
+                None => {
+                    warn!("unknown service '{}' specified, skipping.", service);
+                    unknown_services.push(service);
+                }
+            }
+        }
+
+        let partial_scan = if options.sample.is_some() {
+            Some("--sample collects only some resources")
+        } else if !options.eks_clusters.is_empty() && collectors.iter().any(|(service, _)| service == "eks") {
+            Some("--eks-clusters scans only some EKS clusters")
+        } else {
+            None
+        };
+
+        Ok(Self {
+            collectors,
+            targets,
+            unknown_services,
+            fast_import: options.fast_import,
+            prune: options.prune,
+            partial_scan,
+            collector_concurrency: options.collector_concurrency,
+            throttle_events,
+        })
+    }
+
+    /// Runs every collector once for each target and saves the results. The
+    /// collectors of a target run concurrently, and each one's resources are
+    /// saved as soon as it finishes. A collector that fails is recorded in the
+    /// report and the rest carry on; only database errors end the pass early.
+    pub async fn collect(&self, conn: &mut rusqlite::Connection) -> Result<ScanReport> {
+        let mut report = ScanReport { unknown_services: self.unknown_services.clone(), ..Default::default() };
+        if self.collectors.is_empty() {
+            return Ok(report);
+        }
+        let mut types_covered = BTreeSet::new();
+        let mut regions_by_account: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for (ctx, regions) in &self.targets {
+            if !ctx.profile.is_empty() {
+                info!("=== Profile '{}' ({}) ===", ctx.profile, regions.join(", "));
+            }
+            let ctx = &ScanContext {
+                account_id: inventory::caller_account_id(ctx).await,
+                ..ctx.clone()
+            };
+            match &ctx.account_id {
+                Some(account_id) => {
+                    let covered = regions_by_account.entry(account_id.clone()).or_default();
+                    covered.extend(regions.iter().cloned());
+                    // Global services record their resources under the "global" region.
+                    covered.insert("global".to_string());
+                }
+                None => report.unidentified_profiles.push(ctx.profile.clone()),
+            }
+            // Costs are fetched once per target; a failure only loses the enrichment.
+            let costs = match &ctx.cost_tag {
+                Some(tag_key) => match inventory::monthly_costs_by_tag(ctx, tag_key).await {
+                    Ok(costs) => Some((tag_key, costs)),
+                    Err(e) => {
+                        warn!("could not fetch costs from Cost Explorer: {}", e);
+                        None
+                    }
+                },
+                None => None,
+            };
+            // With costs, a tag value's cost is split across every resource of the
+            // target carrying it, so saving waits until all collectors have run.
+            let mut pending = Vec::new();
+            let mut finished = futures::stream::iter(&self.collectors)
+                .map(|(service, collector)| async move {
+                    // Each collector gets its own record of partial failures.
+                    let ctx = ScanContext { partial_failures: Default::default(), ..ctx.clone() };
+                    let result = collector.collect(&ctx, regions).await;
+                    let partial_failures = std::mem::take(&mut *ctx.partial_failures.lock().unwrap());
+                    (service, collector, result, partial_failures)
+                })
+                .buffer_unordered(self.collector_concurrency.max(1));
+            while let Some((service, collector, result, partial_failures)) = finished.next().await {
+                report.partial_failures.extend(partial_failures.into_iter().map(|message| ScanError {
+                    profile: ctx.profile.clone(),
+                    service: service.clone(),
+                    message,
+                }));
+                let resources = match result {
+                    Ok(resources) => resources,
+                    Err(e) => {
+                        error!("the {} collector failed: {}", service, e);
+                        report.errors.push(ScanError {
+                            profile: ctx.profile.clone(),
+                            service: service.clone(),
+                            message: format!("{:#}", e),
+                        });
+                        continue;
+                    }
+                };
+                *report.resources_by_service.entry(service.clone()).or_default() += resources.len();
+                // Every type the collector returns counts as covered, even if none were found.
+                types_covered.extend(collector.resource_types().iter().map(|t| t.to_string()));
+                if costs.is_some() {
+                    pending.extend(resources);
+                    continue;
+                }
+                report.resources_saved += save_collected(conn, &resources, ctx)?;
+            }
+            if let Some((tag_key, costs)) = &costs {
+                inventory::apply_costs(&mut pending, tag_key, costs);
+                report.resources_saved += save_collected(conn, &pending, ctx)?;
+            }
+        }
+        report.throttle_events = self.throttle_events.swap(0, Ordering::Relaxed);
+        report.scope = db::PruneScope {
+            resource_types: types_covered.into_iter().collect(),
+            regions_by_account: regions_by_account
+                .into_iter()
+                .map(|(account_id, regions)| (account_id, regions.into_iter().collect()))
+                .collect(),
+        };
+        Ok(report)
+    }
+
+    /// A single scan: `collect`, bracketed by fast-import mode when requested,
+    /// then pruning with `prune`, and finally recording the resource counts for
+    /// `trends`. Nothing is pruned if a collector failed, since its resources
+    /// would all look stale, if a lookup inside a collector failed, if the
+    /// account of a profile is unknown, or if `sample` or `eks_clusters`
+    /// narrowed the scan.
+    pub async fn run(&self, conn: &mut rusqlite::Connection) -> Result<ScanReport> {
+        let run_started = db::now_timestamp();
+        if self.fast_import {
+            db::set_fast_import(conn, true)?;
+        }
+        let collected = self.collect(conn).await;
+        // Restore journaling and syncing even if the pass failed.
+        if self.fast_import {
+            db::set_fast_import(conn, false)?;
+        }
+        let mut report = collected?;
+        if self.prune {
+            if let Some(reason) = self.partial_scan {
+                warn!("{}, so no resources were pruned.", reason);
+            } else if !report.errors.is_empty() {
+                warn!("{} collector(s) failed, so no resources were pruned.", report.errors.len());
+            } else if !report.partial_failures.is_empty() {
+                warn!("{} lookup(s) failed, so results may be incomplete and no resources were pruned.", report.partial_failures.len());
+            } else if !report.unidentified_profiles.is_empty() {
+                let profiles: Vec<&str> =
+                    report.unidentified_profiles.iter().map(|p| if p.is_empty() { "default" } else { p.as_str() }).collect();
+                warn!("Could not determine the account of profile(s) {}, so no resources were pruned.", profiles.join(", "));
+            } else {
+                report.resources_pruned = db::prune_resources_in_scope(conn, &run_started, &report.scope)?;
+            }
+        }
+        db::record_resource_counts(conn)?;
+        Ok(report)
+    }
+}
+
+/// The collector for a service name, or `None` if there is no such service.
+fn collector_for(service: &str, options: &ScanOptions) -> Option<Box<dyn AwsResourceCollector>> {
+    let collector: Box<dyn AwsResourceCollector> = match service {
+        "ec2" => Box::new(inventory::Ec2Collector::new(options.include_terminated)),
+        "eip" => Box::new(inventory::ElasticIpCollector),
+        "capacity-reservation" => Box::new(inventory::CapacityReservationCollector),
+        "spot-request" => Box::new(inventory::SpotRequestCollector),
+        "elb" => Box::new(inventory::ElbCollector),
+        "rds" => Box::new(inventory::RdsCollector::new(options.resolve_endpoints)),
+        "dynamodb" => Box::new(inventory::DynamoDbCollector),
+        "elasticache" => Box::new(inventory::ElastiCacheCollector::new(options.resolve_endpoints)),
+        "eks" => Box::new(inventory::EksCollector::new(options.eks_clusters.clone())),
+        "route53" => Box::new(inventory::Route53Collector),
+        "healthcheck" => Box::new(inventory::HealthCheckCollector),
+        "batch" => Box::new(inventory::BatchCollector),
+        "launchtemplate" => Box::new(inventory::LaunchTemplateCollector),
+        "subnet" => Box::new(inventory::SubnetCollector),
+        "vpc" => Box::new(inventory::VpcCollector),
+        "nacl" => Box::new(inventory::NetworkAclCollector),
+        "sg" | "securitygroups" => Box::new(inventory::SecurityGroupsCollector),
+        "ebs" | "volumes" => Box::new(inventory::VolumesCollector),
+        "flowlogs" => Box::new(inventory::FlowLogCollector),
+        "eice" => Box::new(inventory::InstanceConnectEndpointCollector),
+        "cloudtrail" => Box::new(inventory::CloudTrailCollector),
+        "efs-ap" => Box::new(inventory::EfsAccessPointCollector),
+        "lattice" => Box::new(inventory::VpcLatticeCollector::new(options.resolve_endpoints)),
+        "inspector" => Box::new(inventory::SecurityFindingsCollector::new(options.with_macie)),
+        "ecs" => Box::new(inventory::EcsTaskCollector),
+        "workspaces" => Box::new(inventory::WorkSpacesCollector),
+        "appstream" => Box::new(inventory::AppStreamCollector),
+        "ses" => Box::new(inventory::SesCollector),
+        "mq" => Box::new(inventory::AmazonMqCollector::new(options.resolve_endpoints)),
+        "org" => Box::new(inventory::OrgStructureCollector),
+        "storagegateway" => Box::new(inventory::StorageGatewayCollector),
+        "sns" => Box::new(inventory::SnsCollector),
+        "sqs" => Box::new(inventory::SqsCollector),
+        "quotas" => Box::new(inventory::ServiceQuotaCollector),
+        _ => return None,
+    };
+    Some(collector)
+}
+
+/// The resource types a service's collector returns, or `None` if there is no
+/// such service.
+pub(crate) fn service_resource_types(service: &str) -> Option<&'static [&'static str]> {
+    collector_for(service, &ScanOptions::default()).map(|collector| collector.resource_types())
+}
+
+fn save_collected(conn: &mut rusqlite::Connection, resources: &[CollectedResource], ctx: &ScanContext) -> Result<usize> {
+    if ctx.validate_arns {
+        for problem in inventory::arn_problems(resources) {
+            warn!("{}", problem);
+        }
+    }
+    if resources.is_empty() {
+        return Ok(0);
+    }
+    let saved = db::save_resources(conn, resources, ctx)?;
+    info!("  -> Saved {} collected resources to the database.", saved);
+    Ok(saved)
+}
+
+/// Runs a complete inventory scan into `options.output` and reports what was
+/// saved. This is what the `inventory` subcommand does without `--watch`,
+/// `--estimate` or `--check-permissions`, for programs embedding the crate.
+pub async fn run_inventory(options: &ScanOptions) -> Result<ScanReport> {
+    let plan = ScanPlan::new(options).await?;
+    let mut conn = db::init_db(&options.output)?;
+    plan.run(&mut conn).await
+}
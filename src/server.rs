@@ -1,20 +1,29 @@
 use anyhow::Result;
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    response::{IntoResponse, Json},
+    extract::{Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
     routing::get,
     Router,
 };
 use serde::Deserialize;
 use tower_http::services::ServeDir;
 use std::{path::PathBuf, sync::Arc};
+use tracing::{info, warn};
 
 use crate::query;
 
+/// Page size for `/api/query` when the request gives no `limit`.
+const DEFAULT_PAGE_LIMIT: usize = 100;
+/// Largest `limit` `/api/query` will honour; bigger requests are clamped.
+const MAX_PAGE_LIMIT: usize = 1000;
+
 #[derive(Clone)]
 struct AppState {
     db_path: Arc<PathBuf>,
+    /// When set, `/api/*` requests must carry `Authorization: Bearer <token>`.
+    auth_token: Option<Arc<str>>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -23,6 +32,27 @@ pub struct ApiQueryParams {
     services: Vec<String>,
     #[serde(default, deserialize_with = "deserialize_vec_from_str")]
     regions: Vec<String>,
+    #[serde(default, deserialize_with = "deserialize_vec_from_str")]
+    security_groups: Vec<String>,
+    created_after: Option<String>,
+    created_before: Option<String>,
+    /// Only used by `/api/query`; `/api/tree` always covers every match.
+    limit: Option<usize>,
+    #[serde(default)]
+    offset: usize,
+}
+
+impl ApiQueryParams {
+    fn into_filter(self) -> Result<query::QueryFilter, String> {
+        Ok(query::QueryFilter {
+            services: self.services,
+            regions: self.regions,
+            security_groups: self.security_groups,
+            created_after: self.created_after.as_deref().map(query::parse_created_bound).transpose()?,
+            created_before: self.created_before.as_deref().map(query::parse_created_bound).transpose()?,
+            ..Default::default()
+        })
+    }
 }
 
 fn deserialize_vec_from_str<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
@@ -37,22 +67,33 @@ where
     }
 }
 
-pub async fn start_server(db_path: PathBuf, listen_addr: String, no_browser: bool) -> Result<()> {
+/// Builds the HTTP routes served by `start_server`. With `auth_token`, the
+/// `/api/*` routes answer 401 unless the request carries it as a bearer token;
+/// the static files stay open.
+pub fn router(db_path: PathBuf, auth_token: Option<String>) -> Router {
     let state = AppState {
         db_path: Arc::new(db_path),
+        auth_token: auth_token.map(Arc::from),
     };
 
-    let app = Router::new()
+    let api = Router::new()
         .route("/api/query", get(query_handler))
-        .nest_service("/", ServeDir::new("static"))
-        .with_state(state);
+        .route("/api/tree", get(tree_handler))
+        .route("/api/stats", get(stats_handler))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_token));
+
+    api.nest_service("/", ServeDir::new("static")).with_state(state)
+}
+
+pub async fn start_server(db_path: PathBuf, listen_addr: String, no_browser: bool, auth_token: Option<String>) -> Result<()> {
+    let app = router(db_path, auth_token);
 
     let server_url = format!("http://{}", listen_addr);
-    println!("Starting server, listening on {}", server_url);
+    info!("Starting server, listening on {}", server_url);
 
     if !no_browser {
         if let Err(e) = webbrowser::open(&server_url) {
-            eprintln!("Warning: could not open browser: {}", e);
+            warn!("could not open browser: {}", e);
         }
     }
     let listener = tokio::net::TcpListener::bind(listen_addr).await?;
@@ -61,13 +102,70 @@ pub async fn start_server(db_path: PathBuf, listen_addr: String, no_browser: boo
     Ok(())
 }
 
+async fn require_token(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(expected) = state.auth_token.as_deref() else {
+        return next.run(request).await;
+    };
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match presented {
+        Some(token) if tokens_match(token, expected) => next.run(request).await,
+        _ => (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")]).into_response(),
+    }
+}
+
+/// Compares without stopping at the first differing byte, so response timing
+/// doesn't reveal how much of a guessed token was right.
+fn tokens_match(presented: &str, expected: &str) -> bool {
+    presented.len() == expected.len()
+        && presented.bytes().zip(expected.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
 async fn query_handler(
     State(state): State<AppState>,
     Query(params): Query<ApiQueryParams>,
 ) -> impl IntoResponse {
     let db_path = Arc::clone(&state.db_path);
-    match tokio::task::spawn_blocking(move || query::run_query(&db_path, &params.services, &params.regions)).await {
-        Ok(Ok(resources)) => (StatusCode::OK, Json(resources)).into_response(),
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT);
+    let offset = params.offset;
+    let filter = match params.into_filter() {
+        Ok(filter) => filter,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    match tokio::task::spawn_blocking(move || query::run_query_page(&db_path, &filter, limit, offset)).await {
+        Ok(Ok(page)) => (StatusCode::OK, Json(page)).into_response(),
+        Ok(Err(e)) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Serves the account → region → service navigation tree, accepting the same
+/// filters as `/api/query`.
+async fn tree_handler(
+    State(state): State<AppState>,
+    Query(params): Query<ApiQueryParams>,
+) -> impl IntoResponse {
+    let db_path = Arc::clone(&state.db_path);
+    let filter = match params.into_filter() {
+        Ok(filter) => filter,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    match tokio::task::spawn_blocking(move || query::run_tree_query(&db_path, &filter)).await {
+        Ok(Ok(tree)) => (StatusCode::OK, Json(tree)).into_response(),
+        Ok(Err(e)) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Serves the `stats` summary: totals by resource type and region, and public
+/// and private IP counts.
+async fn stats_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let db_path = Arc::clone(&state.db_path);
+    match tokio::task::spawn_blocking(move || query::run_stats(&db_path)).await {
+        Ok(Ok(stats)) => (StatusCode::OK, Json(stats)).into_response(),
         Ok(Err(e)) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     }
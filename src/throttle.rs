@@ -0,0 +1,86 @@
+use aws_smithy_runtime_api::box_error::BoxError;
+use aws_smithy_runtime_api::client::interceptors::context::FinalizerInterceptorContextRef;
+use aws_smithy_runtime_api::client::interceptors::Intercept;
+use aws_smithy_runtime_api::client::runtime_components::RuntimeComponents;
+use aws_smithy_types::config_bag::ConfigBag;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// The error codes AWS uses to say a request was throttled, as recognised by
+/// the SDK's own retry classifier.
+const THROTTLING_CODES: &[&str] = &[
+    "Throttling",
+    "ThrottlingException",
+    "ThrottledException",
+    "RequestThrottledException",
+    "TooManyRequestsException",
+    "ProvisionedThroughputExceededException",
+    "TransactionInProgressException",
+    "RequestLimitExceeded",
+    "BandwidthLimitExceeded",
+    "LimitExceededException",
+    "RequestThrottled",
+    "SlowDown",
+    "PriorRequestNotComplete",
+    "EC2ThrottledException",
+];
+
+/// Whether `code` is one of AWS's throttling error codes.
+pub fn is_throttling(code: Option<&str>) -> bool {
+    code.is_some_and(|code| THROTTLING_CODES.contains(&code))
+}
+
+/// Counts every throttled attempt of one SDK client's calls, including the
+/// attempts the SDK retries itself, into a counter shared by the whole scan.
+#[derive(Debug)]
+pub struct ThrottleInterceptor {
+    events: Arc<AtomicUsize>,
+}
+
+impl ThrottleInterceptor {
+    pub fn new(events: Arc<AtomicUsize>) -> Self {
+        Self { events }
+    }
+
+    /// The error code of a failed response: the `x-amzn-ErrorType` header of
+    /// the JSON protocols, or else the `__type`/`code` field of a JSON body or
+    /// the `<Code>` element of an XML one.
+    fn error_code(headers: &aws_smithy_runtime_api::http::Headers, body: Option<&[u8]>) -> Option<String> {
+        if let Some(error_type) = headers.get("x-amzn-errortype") {
+            return error_type.split(':').next().map(str::to_string);
+        }
+        let body = std::str::from_utf8(body?).ok()?;
+        if let Ok(json) = serde_json::from_str::<serde_json::Value>(body) {
+            let code = json.get("__type").or_else(|| json.get("code")).and_then(|c| c.as_str())?;
+            // `__type` may be qualified, e.g. `com.amazonaws.dynamodb.v20120810#ThrottlingException`.
+            return code.rsplit('#').next().map(str::to_string);
+        }
+        let start = body.find("<Code>")? + "<Code>".len();
+        let end = body[start..].find("</Code>")? + start;
+        Some(body[start..end].trim().to_string())
+    }
+}
+
+impl Intercept for ThrottleInterceptor {
+    fn name(&self) -> &'static str {
+        "ThrottleInterceptor"
+    }
+
+    fn read_after_attempt(
+        &self,
+        context: &FinalizerInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let Some(response) = context.response() else {
+            return Ok(());
+        };
+        let status = response.status().as_u16();
+        let throttled = status == 429
+            || (status >= 400 && is_throttling(Self::error_code(response.headers(), response.body().bytes()).as_deref()));
+        if throttled {
+            self.events.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+}
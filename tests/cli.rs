@@ -1,15 +1,67 @@
 #[cfg(test)]
 mod tests {
     use assert_cmd::prelude::*;
+    use aws_inventory_sdk::inventory::CollectedResource;
     use predicates::prelude::*;
+    use std::collections::HashMap;
+    use std::net::IpAddr;
+    use std::path::Path;
     use std::process::Command;
 
+    /// A bare resource for database fixtures; tests set any other fields
+    /// with struct update syntax.
+    fn resource(id: &str, resource_type: &str, region: &str) -> CollectedResource {
+        CollectedResource {
+            id: id.to_string(),
+            name: id.to_string(),
+            resource_type: resource_type.to_string(),
+            region: region.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn tags(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn ips(addresses: &[&str]) -> Vec<IpAddr> {
+        addresses.iter().map(|ip| ip.parse().unwrap()).collect()
+    }
+
+    /// Answers GetCallerIdentity, so scans against `server` run as account 123456789012.
+    async fn mock_caller_identity(server: &mut mockito::Server) -> mockito::Mock {
+        server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex("Action=GetCallerIdentity".into()))
+            .with_status(200)
+            .with_header("content-type", "text/xml")
+            .with_body("
+                <GetCallerIdentityResponse xmlns=\"https://sts.amazonaws.com/doc/2011-06-15/\">
+                    <GetCallerIdentityResult>
+                        <Arn>arn:aws:iam::123456789012:user/inventory</Arn>
+                        <UserId>AIDAEXAMPLE</UserId>
+                        <Account>123456789012</Account>
+                    </GetCallerIdentityResult>
+                </GetCallerIdentityResponse>
+            ")
+            .create_async()
+            .await
+    }
+
+    /// Removes a test database along with its WAL and shared-memory files.
+    fn remove_db(path: &Path) {
+        for suffix in ["", "-wal", "-shm"] {
+            std::fs::remove_file(format!("{}{}", path.display(), suffix)).ok();
+        }
+    }
+
     #[tokio::test]
     async fn test_cli() -> Result<(), Box<dyn std::error::Error>> {
         let mut server = mockito::Server::new_async().await;
 
         let ec2_mock = server
             .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex("Action=DescribeInstances".into()))
             .with_status(200)
             .with_header("content-type", "application/xml")
             .with_body("
@@ -38,6 +90,8 @@ mod tests {
             .create_async()
             .await;
 
+        let _sts_mock = mock_caller_identity(&mut server).await;
+
         let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
         cmd.env("AWS_ENDPOINT_URL", server.url());
         cmd.arg("inventory").arg("--regions").arg("us-east-1");
@@ -49,6 +103,1538 @@ mod tests {
         cmd.arg("identify").arg("10.0.0.1");
         cmd.assert().success().stdout(predicate::str::contains("MyInstance"));
 
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.arg("query").arg("--services").arg("ec2");
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("\"id\": \"i-1234567890abcdef0\""))
+            .stdout(predicate::str::contains("arn:aws:ec2:us-east-1:123456789012:instance/i-1234567890abcdef0"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_carrier_ip_is_identifiable() -> Result<(), Box<dyn std::error::Error>> {
+        let mut server = mockito::Server::new_async().await;
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_carrier_{}.db", std::process::id()));
+
+        let addresses_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex("Action=DescribeAddresses".into()))
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body("
+                <DescribeAddressesResponse>
+                    <addressesSet/>
+                </DescribeAddressesResponse>
+            ")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let ec2_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex("Action=DescribeInstances".into()))
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body("
+                <DescribeInstancesResponse>
+                    <reservationSet>
+                        <item>
+                            <reservationId>r-0abcdef1234567890</reservationId>
+                            <instancesSet>
+                                <item>
+                                    <instanceId>i-0abcdef1234567890</instanceId>
+                                    <instanceType>t3.medium</instanceType>
+                                    <privateIpAddress>10.0.0.2</privateIpAddress>
+                                    <networkInterfaceSet>
+                                        <item>
+                                            <networkInterfaceId>eni-0abcdef1234567890</networkInterfaceId>
+                                            <privateIpAddressesSet>
+                                                <item>
+                                                    <privateIpAddress>10.0.0.2</privateIpAddress>
+                                                    <association>
+                                                        <carrierIp>155.146.10.20</carrierIp>
+                                                    </association>
+                                                </item>
+                                            </privateIpAddressesSet>
+                                        </item>
+                                    </networkInterfaceSet>
+                                    <tagSet>
+                                        <item>
+                                            <key>Name</key>
+                                            <value>WavelengthInstance</value>
+                                        </item>
+                                    </tagSet>
+                                </item>
+                            </instancesSet>
+                        </item>
+                    </reservationSet>
+                </DescribeInstancesResponse>
+            ")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.env("AWS_ENDPOINT_URL", server.url());
+        cmd.arg("inventory").arg("--regions").arg("us-east-1").arg("--output").arg(&db_path);
+        cmd.assert().success();
+
+        addresses_mock.assert_async().await;
+        ec2_mock.assert_async().await;
+
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.arg("identify").arg("155.146.10.20").arg("--inventory").arg(&db_path);
+        cmd.assert().success().stdout(predicate::str::contains("WavelengthInstance"));
+
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.arg("query").arg("--inventory").arg(&db_path);
+        cmd.assert().success().stdout(predicate::str::contains("\"155.146.10.20\": \"carrier\""));
+
+        remove_db(&db_path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_secondary_eni_ips_are_identifiable() -> Result<(), Box<dyn std::error::Error>> {
+        let mut server = mockito::Server::new_async().await;
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_enis_{}.db", std::process::id()));
+
+        let _addresses_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex("Action=DescribeAddresses".into()))
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body("<DescribeAddressesResponse><addressesSet/></DescribeAddressesResponse>")
+            .create_async()
+            .await;
+
+        let ec2_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex("Action=DescribeInstances".into()))
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body("
+                <DescribeInstancesResponse>
+                    <reservationSet>
+                        <item>
+                            <reservationId>r-0abcdef1234567891</reservationId>
+                            <instancesSet>
+                                <item>
+                                    <instanceId>i-0abcdef1234567891</instanceId>
+                                    <instanceType>m5.large</instanceType>
+                                    <privateIpAddress>10.0.1.10</privateIpAddress>
+                                    <networkInterfaceSet>
+                                        <item>
+                                            <networkInterfaceId>eni-0000000000000000a</networkInterfaceId>
+                                            <privateIpAddressesSet>
+                                                <item><privateIpAddress>10.0.1.10</privateIpAddress></item>
+                                                <item><privateIpAddress>10.0.1.11</privateIpAddress></item>
+                                            </privateIpAddressesSet>
+                                        </item>
+                                        <item>
+                                            <networkInterfaceId>eni-0000000000000000b</networkInterfaceId>
+                                            <privateIpAddressesSet>
+                                                <item><privateIpAddress>10.0.2.20</privateIpAddress></item>
+                                            </privateIpAddressesSet>
+                                        </item>
+                                    </networkInterfaceSet>
+                                    <tagSet>
+                                        <item>
+                                            <key>Name</key>
+                                            <value>MultiHomedInstance</value>
+                                        </item>
+                                    </tagSet>
+                                </item>
+                            </instancesSet>
+                        </item>
+                    </reservationSet>
+                </DescribeInstancesResponse>
+            ")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.env("AWS_ENDPOINT_URL", server.url());
+        cmd.arg("inventory").arg("--regions").arg("us-east-1").arg("--output").arg(&db_path);
+        cmd.assert().success();
+        ec2_mock.assert_async().await;
+
+        for ip in ["10.0.1.11", "10.0.2.20"] {
+            let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+            cmd.arg("identify").arg(ip).arg("--inventory").arg(&db_path);
+            cmd.assert().success().stdout(predicate::str::contains("MultiHomedInstance"));
+        }
+
+        // The primary address appears on the instance and its first ENI but is stored once.
+        let conn = rusqlite::Connection::open(&db_path)?;
+        let primary_rows: i64 = conn.query_row("SELECT COUNT(*) FROM ip_addresses WHERE ip_address = '10.0.1.10'", [], |row| row.get(0))?;
+        assert_eq!(primary_rows, 1);
+
+        remove_db(&db_path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_terminated_instances_are_skipped() -> Result<(), Box<dyn std::error::Error>> {
+        let mut server = mockito::Server::new_async().await;
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_terminated_{}.db", std::process::id()));
+
+        let _addresses_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex("Action=DescribeAddresses".into()))
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body("<DescribeAddressesResponse><addressesSet/></DescribeAddressesResponse>")
+            .create_async()
+            .await;
+
+        let ec2_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex("Action=DescribeInstances".into()))
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body("
+                <DescribeInstancesResponse>
+                    <reservationSet>
+                        <item>
+                            <reservationId>r-0abcdef1234567892</reservationId>
+                            <instancesSet>
+                                <item>
+                                    <instanceId>i-0000000000000running</instanceId>
+                                    <instanceState><code>16</code><name>running</name></instanceState>
+                                    <privateIpAddress>10.0.3.10</privateIpAddress>
+                                    <tagSet><item><key>Name</key><value>LiveInstance</value></item></tagSet>
+                                </item>
+                                <item>
+                                    <instanceId>i-0000000000terminated</instanceId>
+                                    <instanceState><code>48</code><name>terminated</name></instanceState>
+                                    <tagSet><item><key>Name</key><value>DeadInstance</value></item></tagSet>
+                                </item>
+                            </instancesSet>
+                        </item>
+                    </reservationSet>
+                </DescribeInstancesResponse>
+            ")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.env("AWS_ENDPOINT_URL", server.url());
+        cmd.arg("inventory").arg("--regions").arg("us-east-1").arg("--output").arg(&db_path);
+        cmd.assert().success();
+        ec2_mock.assert_async().await;
+
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.arg("query").arg("--inventory").arg(&db_path);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("LiveInstance"))
+            .stdout(predicate::str::contains("DeadInstance").not());
+
+        remove_db(&db_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_arn_is_skipped_not_merged() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+
+        let db_instance = |id: &str, name: &str| CollectedResource { name: name.to_string(), ..resource(id, "rds:db_instance", "us-east-1") };
+
+        let mut conn = aws_inventory_sdk::db::init_db(std::path::Path::new(":memory:"))?;
+        let saved = aws_inventory_sdk::db::save_resources(
+            &mut conn,
+            &[db_instance("", "first"), db_instance("arn:aws:rds:us-east-1:123456789012:db:orders", "orders"), db_instance("", "second")],
+            &ScanContext::default(),
+        )?;
+        assert_eq!(saved, 1);
+
+        let names = conn
+            .prepare("SELECT name FROM resources")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(names, vec!["orders".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_or_groups_match_any_group() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let mut conn = aws_inventory_sdk::db::init_db(std::path::Path::new(":memory:"))?;
+        aws_inventory_sdk::db::save_resources(
+            &mut conn,
+            &[
+                resource("i-east", "ec2:instance", "us-east-1"),
+                resource("i-west", "ec2:instance", "eu-west-1"),
+                resource("db-east", "rds:db_instance", "us-east-1"),
+                resource("db-west", "rds:db_instance", "eu-west-1"),
+            ],
+            &ScanContext::default(),
+        )?;
+
+        let filter = QueryFilter {
+            any_of: vec![
+                query::parse_filter_group("services=ec2 regions=us-east-1")?,
+                query::parse_filter_group("services=rds regions=eu-west-1")?,
+            ],
+            ..Default::default()
+        };
+        let mut arns: Vec<String> = query::run_query_on(&conn, &filter)?.into_iter().map(|r| r.id).collect();
+        arns.sort();
+        assert_eq!(arns, vec!["db-west".to_string(), "i-east".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_filters_by_tag() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let instance = |id: &str, pairs: &[(&str, &str)]| CollectedResource { tags: tags(pairs), ..resource(id, "ec2:instance", "us-east-1") };
+
+        let mut conn = aws_inventory_sdk::db::init_db(std::path::Path::new(":memory:"))?;
+        aws_inventory_sdk::db::save_resources(
+            &mut conn,
+            &[
+                instance("i-prod", &[("Environment", "prod"), ("Team", "payments")]),
+                instance("i-staging", &[("Environment", "staging"), ("Team", "payments")]),
+                instance("i-untagged", &[]),
+            ],
+            &ScanContext::default(),
+        )?;
+
+        let arns = |filters: &[&str]| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+            let filter = QueryFilter {
+                tags: filters.iter().map(|t| t.to_string()).collect(),
+                ..Default::default()
+            };
+            let mut arns: Vec<String> = query::run_query_on(&conn, &filter)?.into_iter().map(|r| r.id).collect();
+            arns.sort();
+            Ok(arns)
+        };
+        assert_eq!(arns(&["Environment=prod"])?, vec!["i-prod".to_string()]);
+        assert_eq!(arns(&["Team"])?, vec!["i-prod".to_string(), "i-staging".to_string()]);
+        assert_eq!(arns(&["Team=payments", "Environment=staging"])?, vec!["i-staging".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_reads_during_write_transaction() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_concurrent_{}.db", std::process::id()));
+        let mut writer = aws_inventory_sdk::db::init_db(&db_path)?;
+        aws_inventory_sdk::db::save_resources(&mut writer, &[resource("i-committed", "ec2:instance", "us-east-1")], &ScanContext::default())?;
+
+        // Hold an open write transaction, as a scan does while saving.
+        let tx = writer.transaction()?;
+        tx.execute(
+            "INSERT INTO resources (uid, region, resource_type, name) VALUES ('i-pending', 'us-east-1', 'ec2:instance', 'pending')",
+            [],
+        )?;
+
+        let arns: Vec<String> = query::run_query(&db_path, &QueryFilter::default())?.into_iter().map(|r| r.id).collect();
+        assert_eq!(arns, vec!["i-committed".to_string()]);
+
+        tx.commit()?;
+        drop(writer);
+        remove_db(&db_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_legacy_arn_column_is_migrated_to_uid() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_legacy_{}.db", std::process::id()));
+        {
+            let legacy = rusqlite::Connection::open(&db_path)?;
+            legacy.execute_batch(
+                "CREATE TABLE resources (id INTEGER PRIMARY KEY, arn TEXT NOT NULL UNIQUE, region TEXT NOT NULL, resource_type TEXT NOT NULL, name TEXT, details TEXT);
+                 CREATE TABLE notes (arn TEXT PRIMARY KEY, note TEXT NOT NULL, updated_at TEXT NOT NULL);
+                 INSERT INTO resources (arn, region, resource_type, name, details) VALUES ('i-0abc', 'us-east-1', 'ec2:instance', 'web', '{}');
+                 INSERT INTO resources (arn, region, resource_type, name, details) VALUES ('arn:aws:rds:us-east-1:123456789012:db:orders', 'us-east-1', 'rds:db_instance', 'orders', '{}');
+                 INSERT INTO notes (arn, note, updated_at) VALUES ('i-0abc', 'owned by web team', '2024-01-01T00:00:00.000Z');",
+            )?;
+        }
+
+        let conn = aws_inventory_sdk::db::init_db(&db_path)?;
+        let mut resources = query::run_query_on(&conn, &QueryFilter::default())?;
+        resources.sort_by(|a, b| a.id.cmp(&b.id));
+        let keys: Vec<(String, Option<String>, Option<String>)> =
+            resources.into_iter().map(|r| (r.id, r.arn, r.note)).collect();
+        assert_eq!(
+            keys,
+            vec![
+                (
+                    "arn:aws:rds:us-east-1:123456789012:db:orders".to_string(),
+                    Some("arn:aws:rds:us-east-1:123456789012:db:orders".to_string()),
+                    None
+                ),
+                ("i-0abc".to_string(), None, Some("owned by web team".to_string())),
+            ]
+        );
+
+        // A second save of the same id replaces the row rather than adding one.
+        let mut conn = conn;
+        let instance = CollectedResource {
+            arn: Some("arn:aws:ec2:us-east-1:123456789012:instance/i-0abc".to_string()),
+            name: "web".to_string(),
+            ..resource("i-0abc", "ec2:instance", "us-east-1")
+        };
+        aws_inventory_sdk::db::save_resources(&mut conn, &[instance], &Default::default())?;
+        let filter = QueryFilter { arns: vec!["arn:aws:ec2:us-east-1:123456789012:instance/i-0abc".to_string()], ..Default::default() };
+        let found = query::run_query_on(&conn, &filter)?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, "i-0abc");
+
+        drop(conn);
+        remove_db(&db_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_anonymizes_accounts_consistently() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_anonymize_{}.db", std::process::id()));
+        let output = std::env::temp_dir().join(format!("aws_inventory_anonymize_{}.ndjson", std::process::id()));
+        let mut conn = aws_inventory_sdk::db::init_db(&db_path)?;
+        for (account_id, name) in [("111111111111", "orders"), ("222222222222", "billing")] {
+            let ctx = ScanContext { account_id: Some(account_id.to_string()), ..Default::default() };
+            let db_instance = CollectedResource {
+                name: name.to_string(),
+                details: serde_json::json!({ "owner": account_id }),
+                ..resource(&format!("arn:aws:rds:us-east-1:{}:db:{}", account_id, name), "rds:db_instance", "us-east-1")
+            };
+            aws_inventory_sdk::db::save_resources(&mut conn, &[db_instance], &ctx)?;
+        }
+        drop(conn);
+
+        aws_inventory_sdk::export::to_ndjson(&db_path, &output, false, true)?;
+        let contents = std::fs::read_to_string(&output)?;
+        assert!(!contents.contains("111111111111") && !contents.contains("222222222222"));
+        for line in contents.lines() {
+            let row: serde_json::Value = serde_json::from_str(line)?;
+            let account = row["account_id"].as_str().unwrap_or_default().to_string();
+            assert!(account.starts_with("account-"));
+            assert!(row["id"].as_str().unwrap_or_default().contains(&format!(":{}:db:", account)));
+            assert_eq!(row["details"]["owner"].as_str(), Some(account.as_str()));
+        }
+
+        remove_db(&db_path);
+        std::fs::remove_file(&output).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_reports_added_removed_and_changed() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+
+        let instance = |id: &str, addresses: &[&str], pairs: &[(&str, &str)], size: &str| CollectedResource {
+            ips: ips(addresses),
+            tags: tags(pairs),
+            details: serde_json::json!({ "instance_type": size }),
+            ..resource(id, "ec2:instance", "us-east-1")
+        };
+        let path = |name: &str| std::env::temp_dir().join(format!("aws_inventory_diff_{}_{}.db", name, std::process::id()));
+        let (old_path, new_path) = (path("old"), path("new"));
+
+        let mut old = aws_inventory_sdk::db::init_db(&old_path)?;
+        aws_inventory_sdk::db::save_resources(
+            &mut old,
+            &[
+                instance("i-same", &["10.0.0.1", "10.0.0.2"], &[("Team", "a"), ("Env", "prod")], "t3.micro"),
+                instance("i-resized", &["10.0.0.3"], &[], "t3.micro"),
+                instance("i-gone", &[], &[], "t3.micro"),
+            ],
+            &ScanContext::default(),
+        )?;
+        let mut new = aws_inventory_sdk::db::init_db(&new_path)?;
+        aws_inventory_sdk::db::save_resources(
+            &mut new,
+            &[
+                instance("i-same", &["10.0.0.2", "10.0.0.1"], &[("Env", "prod"), ("Team", "a")], "t3.micro"),
+                instance("i-resized", &["10.0.0.3"], &[], "t3.large"),
+                instance("i-new", &[], &[], "t3.micro"),
+            ],
+            &ScanContext::default(),
+        )?;
+        drop((old, new));
+
+        let diff = aws_inventory_sdk::diff::diff_inventories(&old_path, &new_path)?;
+        assert_eq!(diff.added.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), vec!["i-new"]);
+        assert_eq!(diff.removed.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), vec!["i-gone"]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].resource.id, "i-resized");
+        let fields: Vec<&str> = diff.changed[0].changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, vec!["details.instance_type"]);
+
+        use aws_inventory_sdk::diff::PatchOperation;
+        let patches = aws_inventory_sdk::diff::patch_inventories(&old_path, &new_path)?;
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].resource.id, "i-resized");
+        assert_eq!(
+            patches[0].patch,
+            vec![PatchOperation::Replace { path: "/details/instance_type".to_string(), value: serde_json::json!("t3.large") }]
+        );
+        let ops = aws_inventory_sdk::diff::json_patch(
+            &serde_json::json!({ "tags": { "a/b": "1", "Gone": "x" } }),
+            &serde_json::json!({ "tags": { "a/b": "1", "New": "y" } }),
+        );
+        assert_eq!(
+            ops,
+            vec![
+                PatchOperation::Remove { path: "/tags/Gone".to_string() },
+                PatchOperation::Add { path: "/tags/New".to_string(), value: serde_json::json!("y") },
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&ops[0])?,
+            serde_json::json!({ "op": "remove", "path": "/tags/Gone" })
+        );
+
+        remove_db(&old_path);
+        remove_db(&new_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_blank_tag_keys_are_dropped_and_empty_values_kept() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let mut conn = aws_inventory_sdk::db::init_db(std::path::Path::new(":memory:"))?;
+        let instance = CollectedResource {
+            tags: tags(&[("", "orphan"), (" ", "blank"), ("Team", "payments"), ("Env", "")]),
+            ..resource("i-tagged", "ec2:instance", "us-east-1")
+        };
+        aws_inventory_sdk::db::save_resources(&mut conn, &[instance], &ScanContext::default())?;
+
+        let resources = query::run_query_on(&conn, &QueryFilter::default())?;
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].tags, serde_json::json!({ "Team": "payments", "Env": "" }));
+
+        let matches = |tag: &str| -> Result<usize, Box<dyn std::error::Error>> {
+            let filter = QueryFilter { tags: vec![tag.to_string()], ..Default::default() };
+            Ok(query::run_query_on(&conn, &filter)?.len())
+        };
+        assert_eq!(matches("Env=")?, 1);
+        assert_eq!(matches("Env=prod")?, 0);
+        assert_eq!(matches("Team=payments")?, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_inventory_reports_unknown_services() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::scan::{run_inventory, ScanOptions};
+
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_embedded_{}.db", std::process::id()));
+        let options = ScanOptions {
+            regions: vec!["us-east-1".to_string()],
+            services: vec!["no-such-service".to_string()],
+            output: db_path.clone(),
+            ..Default::default()
+        };
+        let report = run_inventory(&options).await?;
+        assert_eq!(report.unknown_services, vec!["no-such-service".to_string()]);
+        assert_eq!(report.resources_saved, 0);
+        assert!(report.errors.is_empty());
+        assert!(db_path.exists());
+
+        remove_db(&db_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tag_coverage_per_type_and_account() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+
+        let mut conn = aws_inventory_sdk::db::init_db(std::path::Path::new(":memory:"))?;
+        let instance = |id: &str, pairs: &[(&str, &str)]| CollectedResource { tags: tags(pairs), ..resource(id, "ec2:instance", "us-east-1") };
+        let ctx = ScanContext { account_id: Some("111111111111".to_string()), ..Default::default() };
+        aws_inventory_sdk::db::save_resources(
+            &mut conn,
+            &[
+                instance("i-full", &[("Owner", "a"), ("CostCenter", "42")]),
+                instance("i-owner", &[("Owner", "b"), ("CostCenter", " ")]),
+                instance("i-none", &[("owner", "lowercase")]),
+                instance("i-other", &[("Name", "x")]),
+            ],
+            &ctx,
+        )?;
+
+        let required = vec!["Owner".to_string(), "CostCenter".to_string()];
+        let rows = aws_inventory_sdk::query::run_tag_coverage_on(&conn, &required)?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].account_id, "111111111111");
+        assert_eq!(rows[0].total, 4);
+        assert_eq!(rows[0].with_tag["Owner"], 2);
+        assert_eq!(rows[0].with_tag["CostCenter"], 1);
+        assert_eq!(rows[0].with_all_tags, 1);
+        assert_eq!(rows[0].coverage["Owner"], 50.0);
+        assert_eq!(rows[0].coverage_all, 25.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_only_touches_the_scanned_scope() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::db::{self, PruneScope};
+        use aws_inventory_sdk::inventory::ScanContext;
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let mut conn = db::init_db(std::path::Path::new(":memory:"))?;
+        let ctx = ScanContext { account_id: Some("111111111111".to_string()), ..Default::default() };
+        db::save_resources(
+            &mut conn,
+            &[
+                resource("i-deleted", "ec2:instance", "us-east-1"),
+                resource("i-other-region", "ec2:instance", "eu-west-1"),
+                resource("db-other-service", "rds:db_instance", "us-east-1"),
+                resource("i-still-there", "ec2:instance", "us-east-1"),
+            ],
+            &ctx,
+        )?;
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let run_started = db::now_timestamp();
+        db::save_resources(&mut conn, &[resource("i-still-there", "ec2:instance", "us-east-1")], &ctx)?;
+
+        let scope = PruneScope {
+            resource_types: vec!["ec2:instance".to_string()],
+            regions_by_account: [("111111111111".to_string(), vec!["us-east-1".to_string(), "global".to_string()])]
+                .into_iter()
+                .collect(),
+        };
+        assert_eq!(db::prune_resources_in_scope(&mut conn, &run_started, &scope)?, 1);
+
+        let mut ids: Vec<String> = query::run_query_on(&conn, &QueryFilter::default())?.into_iter().map(|r| r.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["db-other-service", "i-other-region", "i-still-there"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_keeps_regions_scanned_only_for_other_accounts() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::db::{self, PruneScope};
+        use aws_inventory_sdk::inventory::ScanContext;
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let mut conn = db::init_db(std::path::Path::new(":memory:"))?;
+        for (account_id, prefix) in [("111111111111", "a"), ("222222222222", "b")] {
+            let ctx = ScanContext { account_id: Some(account_id.to_string()), ..Default::default() };
+            db::save_resources(
+                &mut conn,
+                &[
+                    resource(&format!("i-{}-us", prefix), "ec2:instance", "us-east-1"),
+                    resource(&format!("i-{}-eu", prefix), "ec2:instance", "eu-west-1"),
+                ],
+                &ctx,
+            )?;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let run_started = db::now_timestamp();
+
+        // As with a matrix scanning account A in us-east-1 and B in eu-west-1.
+        let scope = PruneScope {
+            resource_types: vec!["ec2:instance".to_string()],
+            regions_by_account: [
+                ("111111111111".to_string(), vec!["us-east-1".to_string(), "global".to_string()]),
+                ("222222222222".to_string(), vec!["eu-west-1".to_string(), "global".to_string()]),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        assert_eq!(db::prune_resources_in_scope(&mut conn, &run_started, &scope)?, 2);
+
+        let mut ids: Vec<String> = query::run_query_on(&conn, &QueryFilter::default())?.into_iter().map(|r| r.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["i-a-eu", "i-b-us"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prune_is_skipped_when_the_account_is_unknown() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let mut server = mockito::Server::new_async().await;
+        let _sts_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex("Action=GetCallerIdentity".into()))
+            .with_status(403)
+            .with_header("content-type", "text/xml")
+            .with_body("
+                <ErrorResponse>
+                    <Error>
+                        <Type>Sender</Type>
+                        <Code>AccessDenied</Code>
+                        <Message>Not allowed</Message>
+                    </Error>
+                    <RequestId>sts-denied</RequestId>
+                </ErrorResponse>
+            ")
+            .create_async()
+            .await;
+        let ec2_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex("Action=DescribeInstances".into()))
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body("<DescribeInstancesResponse><reservationSet/></DescribeInstancesResponse>")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_prune_unknown_{}.db", std::process::id()));
+        let mut conn = aws_inventory_sdk::db::init_db(&db_path)?;
+        let other_account = ScanContext { account_id: Some("999999999999".to_string()), ..Default::default() };
+        let stale = resource("i-other-account", "ec2:instance", "us-east-1");
+        aws_inventory_sdk::db::save_resources(&mut conn, &[stale], &other_account)?;
+        drop(conn);
+
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.env("AWS_ENDPOINT_URL", server.url());
+        cmd.arg("inventory").arg("--regions").arg("us-east-1").arg("--prune").arg("--output").arg(&db_path);
+        cmd.assert().success().stderr(predicate::str::contains("no resources were pruned"));
+        ec2_mock.assert_async().await;
+
+        let ids: Vec<String> = query::run_query(&db_path, &QueryFilter::default())?.into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec!["i-other-account"]);
+
+        remove_db(&db_path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prune_is_skipped_after_a_sampled_scan() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let mut server = mockito::Server::new_async().await;
+        let _sts_mock = mock_caller_identity(&mut server).await;
+        let ec2_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex("Action=DescribeInstances".into()))
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body("<DescribeInstancesResponse><reservationSet/></DescribeInstancesResponse>")
+            .expect(1)
+            .create_async()
+            .await;
+
+        // Beyond the sample, so the scan never reaches it.
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_prune_sample_{}.db", std::process::id()));
+        let mut conn = aws_inventory_sdk::db::init_db(&db_path)?;
+        let ctx = ScanContext { account_id: Some("123456789012".to_string()), ..Default::default() };
+        aws_inventory_sdk::db::save_resources(&mut conn, &[resource("i-unsampled", "ec2:instance", "us-east-1")], &ctx)?;
+        drop(conn);
+
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.env("AWS_ENDPOINT_URL", server.url());
+        cmd.arg("inventory").arg("--regions").arg("us-east-1").arg("--sample").arg("5").arg("--prune").arg("--output").arg(&db_path);
+        cmd.assert().success().stderr(predicate::str::contains("--sample collects only some resources, so no resources were pruned"));
+        ec2_mock.assert_async().await;
+
+        let ids: Vec<String> = query::run_query(&db_path, &QueryFilter::default())?.into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec!["i-unsampled"]);
+
+        remove_db(&db_path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prune_is_skipped_when_eks_clusters_are_selected() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let mut server = mockito::Server::new_async().await;
+        let _sts_mock = mock_caller_identity(&mut server).await;
+        let describe_mock = server
+            .mock("GET", "/clusters/prod")
+            .with_status(404)
+            .with_header("content-type", "application/json")
+            .with_header("x-amzn-errortype", "ResourceNotFoundException")
+            .with_body(r#"{"message":"No cluster found for name: prod."}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        // A pod of another cluster in the same account and region.
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_prune_eks_{}.db", std::process::id()));
+        let mut conn = aws_inventory_sdk::db::init_db(&db_path)?;
+        let ctx = ScanContext { account_id: Some("123456789012".to_string()), ..Default::default() };
+        let pod = resource("123456789012/us-east-1/staging/default/web-0", "eks:pod", "us-east-1");
+        aws_inventory_sdk::db::save_resources(&mut conn, &[pod], &ctx)?;
+        drop(conn);
+
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.env("AWS_ENDPOINT_URL", server.url());
+        cmd.arg("inventory")
+            .arg("--regions")
+            .arg("us-east-1")
+            .arg("--services")
+            .arg("eks")
+            .arg("--eks-clusters")
+            .arg("prod")
+            .arg("--prune")
+            .arg("--output")
+            .arg(&db_path);
+        cmd.assert()
+            .success()
+            .stderr(predicate::str::contains("--eks-clusters scans only some EKS clusters, so no resources were pruned"));
+        describe_mock.assert_async().await;
+
+        let ids: Vec<String> = query::run_query(&db_path, &QueryFilter::default())?.into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec!["123456789012/us-east-1/staging/default/web-0"]);
+
+        remove_db(&db_path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_watch_prunes_resources_that_disappear() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let mut server = mockito::Server::new_async().await;
+        let _sts_mock = mock_caller_identity(&mut server).await;
+        let _addresses_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex("Action=DescribeAddresses".into()))
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body("<DescribeAddressesResponse><addressesSet/></DescribeAddressesResponse>")
+            .create_async()
+            .await;
+        let _ec2_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex("Action=DescribeInstances".into()))
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body("<DescribeInstancesResponse><reservationSet/></DescribeInstancesResponse>")
+            .create_async()
+            .await;
+
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_watch_prune_{}.db", std::process::id()));
+        let mut conn = aws_inventory_sdk::db::init_db(&db_path)?;
+        let ctx = ScanContext { account_id: Some("123456789012".to_string()), ..Default::default() };
+        aws_inventory_sdk::db::save_resources(&mut conn, &[resource("i-deleted", "ec2:instance", "us-east-1")], &ctx)?;
+        drop(conn);
+
+        // The first run starts at once; the next would be an hour later.
+        let mut watcher = Command::cargo_bin("aws-inventory-sdk")?
+            .env("AWS_ENDPOINT_URL", server.url())
+            .arg("inventory")
+            .arg("--regions")
+            .arg("us-east-1")
+            .arg("--watch")
+            .arg("1h")
+            .arg("--output")
+            .arg(&db_path)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()?;
+        let mut ids = vec!["i-deleted".to_string()];
+        for _ in 0..100 {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            ids = query::run_query(&db_path, &QueryFilter::default())?.into_iter().map(|r| r.id).collect();
+            if ids.is_empty() {
+                break;
+            }
+        }
+        watcher.kill()?;
+        watcher.wait()?;
+        assert!(ids.is_empty(), "still in the database: {:?}", ids);
+
+        remove_db(&db_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_watch_rejects_an_interval_that_overflows() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.arg("inventory").arg("--watch").arg("18446744073709551615h");
+        cmd.assert().failure().stderr(predicate::str::contains("is too long"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_created_bound_rejects_an_age_that_overflows() {
+        use aws_inventory_sdk::query::parse_created_bound;
+
+        assert!(parse_created_bound("7d").is_ok());
+        for age in ["999999999999999d", "9223372036854775807h", "106751991167d"] {
+            assert_eq!(parse_created_bound(age), Err(format!("'{}' is out of range", age)));
+        }
+    }
+
+    #[test]
+    fn test_query_services_match_every_collected_type() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let mut conn = aws_inventory_sdk::db::init_db(std::path::Path::new(":memory:"))?;
+        aws_inventory_sdk::db::save_resources(
+            &mut conn,
+            &[
+                resource("arn:aws:batch:us-east-1:111111111111:compute-environment/ce", "batch:compute_environment", "us-east-1"),
+                resource("arn:aws:batch:us-east-1:111111111111:job-queue/jq", "batch:job_queue", "us-east-1"),
+                resource("111111111111/us-east-1/prod/default/web-0", "eks:pod", "us-east-1"),
+                resource("111111111111/us-east-1/prod/ip-10-0-0-1", "eks:node", "us-east-1"),
+                resource("111111111111/us-east-1/prod/default/services/web", "eks:service", "us-east-1"),
+                resource("i-0abc", "ec2:instance", "us-east-1"),
+                resource("vpc-0abc", "ec2:vpc", "us-east-1"),
+                resource("subnet-0abc", "ec2:subnet", "us-east-1"),
+                resource("/hostedzone/Z123EXAMPLE", "route53:hostedzone", "global"),
+                resource("hostedzone/Z123EXAMPLE/www.example.com./A", "route53:record", "global"),
+            ],
+            &ScanContext::default(),
+        )?;
+
+        let types = |service: &str| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+            let filter = QueryFilter { services: vec![service.to_string()], ..Default::default() };
+            let mut types: Vec<String> = query::run_query_on(&conn, &filter)?.into_iter().map(|r| r.resource_type).collect();
+            types.sort();
+            Ok(types)
+        };
+        assert_eq!(types("vpc")?, vec!["ec2:subnet", "ec2:vpc"]);
+        assert_eq!(types("route53")?, vec!["route53:hostedzone", "route53:record"]);
+        assert_eq!(types("ec2:subnet")?, vec!["ec2:subnet"]);
+        assert_eq!(types("batch")?, vec!["batch:compute_environment", "batch:job_queue"]);
+        assert_eq!(types("eks")?, vec!["eks:node", "eks:pod", "eks:service"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_only_global_addresses_are_public() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+
+        let cases = [
+            ("2600:1f18:1234::1", true),
+            ("::ffff:8.8.8.8", true),
+            ("54.1.2.3", true),
+            ("ff02::1", false),
+            ("fd12:3456::1", false),
+            ("fe80::1", false),
+            ("2001:db8::1", false),
+            ("::ffff:10.0.0.1", false),
+            ("::1", false),
+        ];
+        for (ip, expected) in cases {
+            let mut conn = aws_inventory_sdk::db::init_db(std::path::Path::new(":memory:"))?;
+            let instance = CollectedResource { ips: vec![ip.parse()?], ..resource("i-addr", "ec2:instance", "us-east-1") };
+            aws_inventory_sdk::db::save_resources(&mut conn, &[instance], &ScanContext::default())?;
+            let stats = aws_inventory_sdk::query::run_stats_on(&conn)?;
+            assert_eq!(stats.public_ips == 1, expected, "{}", ip);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats_counts_by_type_and_region() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_stats_{}.db", std::process::id()));
+        let mut conn = aws_inventory_sdk::db::init_db(&db_path)?;
+        let addressed = |id: &str, resource_type: &str, region: &str, ip: &str| CollectedResource { ips: ips(&[ip]), ..resource(id, resource_type, region) };
+        aws_inventory_sdk::db::save_resources(
+            &mut conn,
+            &[
+                addressed("i-1", "ec2:instance", "us-east-1", "10.0.0.1"),
+                addressed("i-2", "ec2:instance", "eu-west-1", "54.1.2.3"),
+                addressed("i-3", "ec2:instance", "us-east-1", "10.0.0.2"),
+                addressed("db-1", "rds:db_instance", "us-east-1", "10.0.0.1"),
+            ],
+            &ScanContext::default(),
+        )?;
+        drop(conn);
+
+        let stats = aws_inventory_sdk::query::run_stats(&db_path)?;
+        assert_eq!(stats.total_resources, 4);
+        assert_eq!(stats.by_type_and_region["ec2:instance"]["us-east-1"], 2);
+        assert_eq!(stats.by_type_and_region["ec2:instance"]["eu-west-1"], 1);
+        assert_eq!(stats.by_region["us-east-1"], 3);
+        assert_eq!((stats.total_ips, stats.public_ips, stats.private_ips), (3, 1, 2));
+
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.arg("stats").arg("--inventory").arg(&db_path);
+        cmd.assert().success().stdout(predicate::str::contains("rds:db_instance"));
+
+        remove_db(&db_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_filters_by_ip_scope() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+        use aws_inventory_sdk::query::{self, IpScope, QueryFilter};
+
+        let mut conn = aws_inventory_sdk::db::init_db(std::path::Path::new(":memory:"))?;
+        let instance = |id: &str, addresses: &[&str]| CollectedResource { ips: ips(addresses), ..resource(id, "ec2:instance", "us-east-1") };
+        aws_inventory_sdk::db::save_resources(
+            &mut conn,
+            &[
+                instance("i-public", &["54.1.2.3"]),
+                instance("i-private", &["10.0.0.1"]),
+                instance("i-both", &["10.0.0.2", "54.1.2.4"]),
+                instance("i-none", &[]),
+            ],
+            &ScanContext::default(),
+        )?;
+
+        let ids = |scope| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+            let filter = QueryFilter { ip_scope: scope, ..Default::default() };
+            let mut ids: Vec<String> = query::run_query_on(&conn, &filter)?.into_iter().map(|r| r.id).collect();
+            ids.sort();
+            Ok(ids)
+        };
+        assert_eq!(ids(Some(IpScope::Public))?, vec!["i-both", "i-public"]);
+        assert_eq!(ids(Some(IpScope::Private))?, vec!["i-both", "i-private"]);
+        assert_eq!(ids(None)?.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_pages_do_not_overlap() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_pages_{}.db", std::process::id()));
+        let mut conn = aws_inventory_sdk::db::init_db(&db_path)?;
+        let resources: Vec<CollectedResource> = (0..5)
+            .map(|n| CollectedResource {
+                ips: ips(&[format!("10.0.0.{}", n).as_str()]),
+                ..resource(&format!("i-{}", n), "ec2:instance", "us-east-1")
+            })
+            .collect();
+        aws_inventory_sdk::db::save_resources(&mut conn, &resources, &ScanContext::default())?;
+        drop(conn);
+
+        let filter = QueryFilter { services: vec!["ec2".to_string()], ..Default::default() };
+        let mut seen = Vec::new();
+        for offset in [0, 2, 4] {
+            let page = query::run_query_page(&db_path, &filter, 2, offset)?;
+            assert_eq!(page.total, 5);
+            seen.extend(page.resources.into_iter().map(|r| r.id));
+        }
+        seen.sort();
+        assert_eq!(seen, vec!["i-0", "i-1", "i-2", "i-3", "i-4"]);
+        assert!(query::run_query_page(&db_path, &filter, 2, 10)?.resources.is_empty());
+
+        remove_db(&db_path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_serve_requires_auth_token() -> Result<(), Box<dyn std::error::Error>> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_auth_{}.db", std::process::id()));
+        drop(aws_inventory_sdk::db::init_db(&db_path)?);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let app = aws_inventory_sdk::server::router(db_path.clone(), Some("s3cret".to_string()));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let status = |authorization: Option<&'static str>| async move {
+            let mut stream = tokio::net::TcpStream::connect(addr).await?;
+            let mut request = "GET /api/query HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n".to_string();
+            if let Some(value) = authorization {
+                request.push_str(&format!("Authorization: {}\r\n", value));
+            }
+            request.push_str("\r\n");
+            stream.write_all(request.as_bytes()).await?;
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await?;
+            Ok::<_, std::io::Error>(response.split(' ').nth(1).unwrap_or_default().to_string())
+        };
+        assert_eq!(status(None).await?, "401");
+        assert_eq!(status(Some("Bearer wrong!")).await?, "401");
+        assert_eq!(status(Some("Bearer s3cret")).await?, "200");
+
+        remove_db(&db_path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_inventory_retries_throttled_calls() -> Result<(), Box<dyn std::error::Error>> {
+        let mut server = mockito::Server::new_async().await;
+
+        // Created first, so it answers the first call; once used up, the
+        // successful mock below takes over.
+        let throttled_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex("Action=DescribeInstances".into()))
+            .with_status(400)
+            .with_header("content-type", "application/xml")
+            .with_body("
+                <Response>
+                    <Errors>
+                        <Error>
+                            <Code>Throttling</Code>
+                            <Message>Rate exceeded</Message>
+                        </Error>
+                    </Errors>
+                    <RequestID>throttled-request</RequestID>
+                </Response>
+            ")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let ec2_mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::Regex("Action=DescribeInstances".into()))
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body("
+                <DescribeInstancesResponse>
+                    <reservationSet>
+                        <item>
+                            <reservationId>r-0retry</reservationId>
+                            <instancesSet>
+                                <item>
+                                    <instanceId>i-0retry</instanceId>
+                                    <privateIpAddress>10.0.9.9</privateIpAddress>
+                                    <tagSet>
+                                        <item>
+                                            <key>Name</key>
+                                            <value>RetriedInstance</value>
+                                        </item>
+                                    </tagSet>
+                                </item>
+                            </instancesSet>
+                        </item>
+                    </reservationSet>
+                </DescribeInstancesResponse>
+            ")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_retry_{}.db", std::process::id()));
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.env("AWS_ENDPOINT_URL", server.url());
+        cmd.arg("inventory").arg("--regions").arg("us-east-1").arg("--max-retries").arg("3").arg("--output").arg(&db_path);
+        cmd.assert().success().stdout(predicate::str::contains("AWS throttled 1 requests"));
+
+        throttled_mock.assert_async().await;
+        ec2_mock.assert_async().await;
+
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.arg("query").arg("--inventory").arg(&db_path);
+        cmd.assert().success().stdout(predicate::str::contains("RetriedInstance"));
+
+        remove_db(&db_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resources_record_their_profile() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let mut conn = aws_inventory_sdk::db::init_db(std::path::Path::new(":memory:"))?;
+        for (id, profile, account_id) in [("i-dev", "dev", "111111111111"), ("i-prod", "prod", "222222222222")] {
+            let ctx = ScanContext { account_id: Some(account_id.to_string()), ..ScanContext::new(profile) };
+            aws_inventory_sdk::db::save_resources(&mut conn, &[resource(id, "ec2:instance", "us-east-1")], &ctx)?;
+        }
+        aws_inventory_sdk::db::save_resources(&mut conn, &[resource("i-default", "ec2:instance", "us-east-1")], &ScanContext::default())?;
+
+        let mut found: Vec<(String, Option<String>, Option<String>)> = query::run_query_on(&conn, &QueryFilter::default())?
+            .into_iter()
+            .map(|r| (r.id, r.profile, r.account_id))
+            .collect();
+        found.sort();
+        assert_eq!(
+            found,
+            vec![
+                ("i-default".to_string(), None, None),
+                ("i-dev".to_string(), Some("dev".to_string()), Some("111111111111".to_string())),
+                ("i-prod".to_string(), Some("prod".to_string()), Some("222222222222".to_string())),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_by_arn_substring() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let mut conn = aws_inventory_sdk::db::init_db(std::path::Path::new(":memory:"))?;
+        aws_inventory_sdk::db::save_resources(
+            &mut conn,
+            &[
+                resource("arn:aws:rds:us-east-1:111111111111:db:orders", "rds:db_instance", "us-east-1"),
+                resource("arn:aws:rds:us-east-1:222222222222:db:billing", "rds:db_instance", "us-east-1"),
+                resource("arn:aws:dynamodb:us-east-1:111111111111:table/orders", "dynamodb:table", "us-east-1"),
+                resource("i-111111111111", "ec2:instance", "us-east-1"),
+            ],
+            &ScanContext::default(),
+        )?;
+
+        let ids = |filter: &QueryFilter| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+            let mut ids: Vec<String> = query::run_query_on(&conn, filter)?.into_iter().map(|r| r.id).collect();
+            ids.sort();
+            Ok(ids)
+        };
+        let by_account = QueryFilter { arn_contains: Some(":111111111111:".to_string()), ..Default::default() };
+        assert_eq!(
+            ids(&by_account)?,
+            vec!["arn:aws:dynamodb:us-east-1:111111111111:table/orders", "arn:aws:rds:us-east-1:111111111111:db:orders"]
+        );
+        let rds_only = QueryFilter { services: vec!["rds".to_string()], ..by_account };
+        assert_eq!(ids(&rds_only)?, vec!["arn:aws:rds:us-east-1:111111111111:db:orders"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_hosts_with_template_and_filters() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::ScanContext;
+        use aws_inventory_sdk::query::QueryFilter;
+
+        let dir = std::env::temp_dir();
+        let db_path = dir.join(format!("aws_inventory_hosts_template_{}.db", std::process::id()));
+        let hosts_path = dir.join(format!("aws_inventory_hosts_template_{}.txt", std::process::id()));
+        let mut conn = aws_inventory_sdk::db::init_db(&db_path)?;
+        let instance = |id: &str, name: &str, addresses: &[&str], env: &str| CollectedResource {
+            name: name.to_string(),
+            ips: ips(addresses),
+            tags: tags(&[("Environment", env)]),
+            ..resource(id, "ec2:instance", "us-east-1")
+        };
+        aws_inventory_sdk::db::save_resources(
+            &mut conn,
+            &[
+                instance("i-prod", "web", &["10.0.0.1", "10.0.0.2"], "prod"),
+                instance("i-dev", "scratch", &["10.1.0.1"], "dev"),
+            ],
+            &ScanContext::default(),
+        )?;
+        drop(conn);
+
+        let filter = QueryFilter { services: vec!["ec2".to_string()], tags: vec!["Environment=prod".to_string()], ..Default::default() };
+        aws_inventory_sdk::export::to_hosts_file_from_db(&db_path, &hosts_path, &filter, Some("{ip}\\t{name}.{region}"))?;
+        let mut entries: Vec<String> = std::fs::read_to_string(&hosts_path)?
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+        entries.sort();
+        assert_eq!(entries, vec!["10.0.0.1\tweb.us-east-1", "10.0.0.2\tweb.us-east-1"]);
+
+        assert!(aws_inventory_sdk::export::to_hosts_file_from_db(&db_path, &hosts_path, &filter, Some("{ip} {owner}")).is_err());
+
+        remove_db(&db_path);
+        std::fs::remove_file(&hosts_path).ok();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sqs_queues_report_encryption_and_keep_their_arn_without_attributes() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let mut server = mockito::Server::new_async().await;
+        let queue_url = |name: &str| format!("https://sqs.us-east-1.amazonaws.com/111111111111/{}", name);
+        let mut sqs_mock = |action: &str| server.mock("POST", "/").match_header("x-amz-target", format!("AmazonSQS.{}", action).as_str());
+
+        let _list_mock = sqs_mock("ListQueues")
+            .with_status(200)
+            .with_header("content-type", "application/x-amz-json-1.0")
+            .with_body(serde_json::json!({ "QueueUrls": [queue_url("orders"), queue_url("events"), queue_url("locked")] }).to_string())
+            .create_async()
+            .await;
+        let _tags_mock = sqs_mock("ListQueueTags")
+            .with_status(200)
+            .with_header("content-type", "application/x-amz-json-1.0")
+            .with_body(r#"{"Tags":{"Team":"payments"}}"#)
+            .create_async()
+            .await;
+        let attributes = [
+            ("orders", serde_json::json!({ "QueueArn": "arn:aws:sqs:us-east-1:111111111111:orders", "KmsMasterKeyId": "alias/orders", "SqsManagedSseEnabled": "false" })),
+            ("events", serde_json::json!({ "QueueArn": "arn:aws:sqs:us-east-1:111111111111:events", "SqsManagedSseEnabled": "true" })),
+        ];
+        let mut attribute_mocks = Vec::new();
+        for (name, attributes) in attributes {
+            let mock = sqs_mock("GetQueueAttributes")
+                .match_body(mockito::Matcher::Regex(format!("/{}\"", name)))
+                .with_status(200)
+                .with_header("content-type", "application/x-amz-json-1.0")
+                .with_body(serde_json::json!({ "Attributes": attributes }).to_string())
+                .create_async()
+                .await;
+            attribute_mocks.push(mock);
+        }
+        let _denied_mock = sqs_mock("GetQueueAttributes")
+            .match_body(mockito::Matcher::Regex("/locked\"".into()))
+            .with_status(400)
+            .with_header("content-type", "application/x-amz-json-1.0")
+            .with_body(r#"{"__type":"com.amazon.coral.service#AccessDeniedException","message":"Access to the resource is denied."}"#)
+            .create_async()
+            .await;
+
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_sqs_{}.db", std::process::id()));
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.env("AWS_ENDPOINT_URL", server.url());
+        cmd.arg("inventory").arg("--services").arg("sqs").arg("--regions").arg("us-east-1").arg("--output").arg(&db_path);
+        cmd.assert()
+            .success()
+            .stderr(predicate::str::contains("Collectors with incomplete results"))
+            .stderr(predicate::str::contains("could not get attributes for SQS queue https://sqs.us-east-1.amazonaws.com/111111111111/locked"));
+
+        let mut queues = query::run_query(&db_path, &QueryFilter::default())?;
+        queues.sort_by(|a, b| a.id.cmp(&b.id));
+        let found: Vec<(&str, &str, &serde_json::Value)> =
+            queues.iter().map(|q| (q.id.as_str(), q.name.as_str(), &q.details["encryption"])).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("arn:aws:sqs:us-east-1:111111111111:events", "events", &serde_json::json!("sse-sqs")),
+                ("arn:aws:sqs:us-east-1:111111111111:locked", "locked", &serde_json::Value::Null),
+                ("arn:aws:sqs:us-east-1:111111111111:orders", "orders", &serde_json::json!("sse-kms")),
+            ]
+        );
+        assert_eq!(queues[1].tags, serde_json::json!({ "Team": "payments" }));
+        assert_eq!(queues[2].details["kms_key_id"], "alias/orders");
+
+        remove_db(&db_path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_org_skips_only_when_the_organization_is_refused() -> Result<(), Box<dyn std::error::Error>> {
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_org_{}.db", std::process::id()));
+        for (error_type, refused) in [("AccessDeniedException", true), ("AWSOrganizationsNotInUseException", true), ("InvalidInputException", false)] {
+            let mut server = mockito::Server::new_async().await;
+            let _roots_mock = server
+                .mock("POST", "/")
+                .match_header("x-amz-target", "AWSOrganizationsV20161128.ListRoots")
+                .with_status(400)
+                .with_header("content-type", "application/x-amz-json-1.1")
+                .with_body(serde_json::json!({ "__type": error_type, "Message": "no organization for you" }).to_string())
+                .create_async()
+                .await;
+
+            let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+            cmd.env("AWS_ENDPOINT_URL", server.url());
+            cmd.arg("inventory").arg("--services").arg("org").arg("--regions").arg("us-east-1").arg("--output").arg(&db_path);
+            if refused {
+                cmd.assert().success().stderr(predicate::str::contains("Collectors that failed").not());
+            } else {
+                cmd.assert().failure().stderr(predicate::str::contains("Collectors that failed"));
+            }
+            remove_db(&db_path);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_eks_nodes_and_services_map_their_addresses() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::inventory::{EksCollector, ScanContext};
+        use k8s_openapi::api::core::v1::{Node, Service};
+
+        let prefix = EksCollector::object_id_prefix(Some("arn:aws:eks:us-east-1:111111111111:cluster/prod"), "prod", "us-east-1");
+        assert_eq!(prefix, "111111111111/us-east-1/prod");
+        assert_eq!(EksCollector::object_id_prefix(None, "prod", "us-east-1"), "us-east-1/prod");
+
+        let address_descriptions = |resource: &CollectedResource| -> Vec<(String, String)> {
+            resource
+                .ips
+                .iter()
+                .map(|ip| (ip.to_string(), resource.ip_details[ip].description.clone().unwrap_or_default()))
+                .collect()
+        };
+
+        let node: Node = serde_json::from_value(serde_json::json!({
+            "apiVersion": "v1",
+            "kind": "Node",
+            "metadata": {
+                "name": "ip-10-0-1-5.ec2.internal",
+                "labels": { "eks.amazonaws.com/nodegroup": "workers", "node.kubernetes.io/instance-type": "m5.large" }
+            },
+            "spec": { "providerID": "aws:///us-east-1a/i-0123456789abcdef0" },
+            "status": {
+                "addresses": [
+                    { "type": "InternalIP", "address": "10.0.1.5" },
+                    { "type": "ExternalIP", "address": "54.1.2.3" },
+                    { "type": "Hostname", "address": "ip-10-0-1-5.ec2.internal" },
+                    { "type": "InternalDNS", "address": "ip-10-0-1-5.ec2.internal" }
+                ],
+                "conditions": [{ "type": "Ready", "status": "True" }]
+            }
+        }))?;
+        let node = EksCollector::node_resource(&ScanContext::default(), node, "prod", "us-east-1", &prefix);
+        assert_eq!(node.id, "111111111111/us-east-1/prod/ip-10-0-1-5.ec2.internal");
+        assert_eq!(
+            address_descriptions(&node),
+            vec![("10.0.1.5".to_string(), "InternalIP".to_string()), ("54.1.2.3".to_string(), "ExternalIP".to_string())]
+        );
+        assert_eq!(node.details["instance_id"], "i-0123456789abcdef0");
+        assert_eq!(node.details["nodegroup"], "workers");
+        assert_eq!(node.details["ready"], true);
+
+        let service: Service = serde_json::from_value(serde_json::json!({
+            "apiVersion": "v1",
+            "kind": "Service",
+            "metadata": { "name": "web", "namespace": "shop" },
+            "spec": {
+                "type": "LoadBalancer",
+                "clusterIP": "172.20.1.10",
+                "clusterIPs": ["172.20.1.10", "fd00::a"],
+                "ports": [{ "port": 443, "protocol": "TCP" }]
+            },
+            "status": { "loadBalancer": { "ingress": [{ "ip": "203.0.113.7" }, { "hostname": "web-123.elb.us-east-1.amazonaws.com" }] } }
+        }))?;
+        let service = EksCollector::service_resource(&ScanContext::default(), service, "prod", "us-east-1", &prefix);
+        assert_eq!(service.id, "111111111111/us-east-1/prod/shop/services/web");
+        assert_eq!(
+            address_descriptions(&service),
+            vec![
+                ("172.20.1.10".to_string(), "ClusterIP".to_string()),
+                ("fd00::a".to_string(), "ClusterIP".to_string()),
+                ("203.0.113.7".to_string(), "LoadBalancer ingress".to_string()),
+            ]
+        );
+        assert_eq!(service.details["load_balancer_hostnames"], serde_json::json!(["web-123.elb.us-east-1.amazonaws.com"]));
+        assert_eq!(service.details["ports"], serde_json::json!(["443/TCP"]));
+
+        let headless: Service = serde_json::from_value(serde_json::json!({
+            "apiVersion": "v1",
+            "kind": "Service",
+            "metadata": { "name": "db", "namespace": "shop" },
+            "spec": { "clusterIP": "None", "clusterIPs": ["None"] }
+        }))?;
+        let headless = EksCollector::service_resource(&ScanContext::default(), headless, "prod", "us-east-1", &prefix);
+        assert!(headless.ips.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_route53_record_ids_include_the_set_identifier() -> Result<(), Box<dyn std::error::Error>> {
+        use aws_inventory_sdk::query::{self, QueryFilter};
+
+        let mut server = mockito::Server::new_async().await;
+        let _zones_mock = server
+            .mock("GET", mockito::Matcher::Regex(r"^/2013-04-01/hostedzone(\?.*)?$".into()))
+            .with_status(200)
+            .with_header("content-type", "text/xml")
+            .with_body("
+                <ListHostedZonesResponse xmlns=\"https://route53.amazonaws.com/doc/2013-04-01/\">
+                    <HostedZones>
+                        <HostedZone>
+                            <Id>/hostedzone/Z123EXAMPLE</Id>
+                            <Name>example.com.</Name>
+                            <CallerReference>zone-ref</CallerReference>
+                            <Config><PrivateZone>false</PrivateZone></Config>
+                            <ResourceRecordSetCount>5</ResourceRecordSetCount>
+                        </HostedZone>
+                    </HostedZones>
+                    <Marker></Marker>
+                    <IsTruncated>false</IsTruncated>
+                    <MaxItems>100</MaxItems>
+                </ListHostedZonesResponse>
+            ")
+            .create_async()
+            .await;
+        let _tags_mock = server
+            .mock("GET", mockito::Matcher::Regex(r"^/2013-04-01/tags/hostedzone/Z123EXAMPLE".into()))
+            .with_status(200)
+            .with_header("content-type", "text/xml")
+            .with_body("
+                <ListTagsForResourceResponse xmlns=\"https://route53.amazonaws.com/doc/2013-04-01/\">
+                    <ResourceTagSet>
+                        <ResourceType>hostedzone</ResourceType>
+                        <ResourceId>Z123EXAMPLE</ResourceId>
+                        <Tags/>
+                    </ResourceTagSet>
+                </ListTagsForResourceResponse>
+            ")
+            .create_async()
+            .await;
+        let records_mock = server
+            .mock("GET", mockito::Matcher::Regex(r"^/2013-04-01/hostedzone/Z123EXAMPLE/rrset".into()))
+            .with_status(200)
+            .with_header("content-type", "text/xml")
+            .with_body("
+                <ListResourceRecordSetsResponse xmlns=\"https://route53.amazonaws.com/doc/2013-04-01/\">
+                    <ResourceRecordSets>
+                        <ResourceRecordSet>
+                            <Name>www.example.com.</Name>
+                            <Type>A</Type>
+                            <TTL>300</TTL>
+                            <ResourceRecords><ResourceRecord><Value>192.0.2.10</Value></ResourceRecord></ResourceRecords>
+                        </ResourceRecordSet>
+                        <ResourceRecordSet>
+                            <Name>api.example.com.</Name>
+                            <Type>A</Type>
+                            <SetIdentifier>blue</SetIdentifier>
+                            <Weight>90</Weight>
+                            <TTL>60</TTL>
+                            <ResourceRecords><ResourceRecord><Value>192.0.2.20</Value></ResourceRecord></ResourceRecords>
+                        </ResourceRecordSet>
+                        <ResourceRecordSet>
+                            <Name>api.example.com.</Name>
+                            <Type>A</Type>
+                            <SetIdentifier>green</SetIdentifier>
+                            <Weight>10</Weight>
+                            <TTL>60</TTL>
+                            <ResourceRecords><ResourceRecord><Value>192.0.2.21</Value></ResourceRecord></ResourceRecords>
+                        </ResourceRecordSet>
+                        <ResourceRecordSet>
+                            <Name>www.example.com.</Name>
+                            <Type>AAAA</Type>
+                            <TTL>300</TTL>
+                            <ResourceRecords><ResourceRecord><Value>2001:db8::10</Value></ResourceRecord></ResourceRecords>
+                        </ResourceRecordSet>
+                        <ResourceRecordSet>
+                            <Name>example.com.</Name>
+                            <Type>MX</Type>
+                            <TTL>300</TTL>
+                            <ResourceRecords><ResourceRecord><Value>10 mail.example.com.</Value></ResourceRecord></ResourceRecords>
+                        </ResourceRecordSet>
+                    </ResourceRecordSets>
+                    <IsTruncated>false</IsTruncated>
+                    <MaxItems>100</MaxItems>
+                </ListResourceRecordSetsResponse>
+            ")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let db_path = std::env::temp_dir().join(format!("aws_inventory_route53_{}.db", std::process::id()));
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.env("AWS_ENDPOINT_URL", server.url());
+        cmd.arg("inventory").arg("--services").arg("route53").arg("--regions").arg("us-east-1").arg("--output").arg(&db_path);
+        cmd.assert().success();
+        records_mock.assert_async().await;
+
+        let mut ids: Vec<String> = query::run_query(&db_path, &QueryFilter::default())?.into_iter().map(|r| r.id).collect();
+        ids.sort();
+        assert_eq!(
+            ids,
+            vec![
+                "/hostedzone/Z123EXAMPLE",
+                "hostedzone/Z123EXAMPLE/api.example.com./A/blue",
+                "hostedzone/Z123EXAMPLE/api.example.com./A/green",
+                "hostedzone/Z123EXAMPLE/www.example.com./A",
+                "hostedzone/Z123EXAMPLE/www.example.com./AAAA",
+            ]
+        );
+
+        let mut cmd = Command::cargo_bin("aws-inventory-sdk")?;
+        cmd.arg("identify").arg("192.0.2.21").arg("--inventory").arg(&db_path);
+        cmd.assert().success().stdout(predicate::str::contains("api.example.com."));
+
+        remove_db(&db_path);
         Ok(())
     }
 }